@@ -9,6 +9,8 @@ pub struct AnalyticsEvent {
 // ===== RELEASE BUILD: Full analytics implementation =====
 #[cfg(not(debug_assertions))]
 mod release {
+    use crate::error::DotaKeeperError;
+    use crate::http::{self, ApiHost};
     use serde_json::json;
 
     const POSTHOG_API_KEY: &str = env!("POSTHOG_API_KEY");
@@ -19,7 +21,7 @@ mod release {
     pub async fn identify_user(
         analytics_enabled: bool,
         installation_id: String,
-    ) -> Result<(), String> {
+    ) -> Result<(), DotaKeeperError> {
         // Respect opt-out
         if !analytics_enabled {
             return Ok(());
@@ -43,10 +45,9 @@ mod release {
 
         // Send async, fail silently
         tokio::spawn(async move {
-            let client = reqwest::Client::new();
             let url = format!("{}/capture/", POSTHOG_HOST);
 
-            let _ = client.post(&url).json(&payload).send().await;
+            let _ = http::send(ApiHost::PostHog, http::client().post(&url).json(&payload)).await;
         });
 
         Ok(())
@@ -60,7 +61,7 @@ mod release {
         analytics_enabled: bool,
         installation_id: String,
         session_id: String,
-    ) -> Result<(), String> {
+    ) -> Result<(), DotaKeeperError> {
         // Respect opt-out
         if !analytics_enabled {
             return Ok(());
@@ -98,10 +99,9 @@ mod release {
 
         // Send async, fail silently
         tokio::spawn(async move {
-            let client = reqwest::Client::new();
             let url = format!("{}/capture/", POSTHOG_HOST);
 
-            let _ = client.post(&url).json(&payload).send().await;
+            let _ = http::send(ApiHost::PostHog, http::client().post(&url).json(&payload)).await;
         });
 
         Ok(())
@@ -111,11 +111,13 @@ mod release {
 // ===== DEBUG BUILD: No-op stub implementation =====
 #[cfg(debug_assertions)]
 mod debug {
+    use crate::error::DotaKeeperError;
+
     /// Debug stub: identify disabled in development builds
     pub async fn identify_user(
         _analytics_enabled: bool,
         _installation_id: String,
-    ) -> Result<(), String> {
+    ) -> Result<(), DotaKeeperError> {
         // No-op in debug builds
         Ok(())
     }
@@ -127,7 +129,7 @@ mod debug {
         _analytics_enabled: bool,
         _installation_id: String,
         _session_id: String,
-    ) -> Result<(), String> {
+    ) -> Result<(), DotaKeeperError> {
         // No-op in debug builds
         Ok(())
     }
@@ -0,0 +1,224 @@
+//! Backup archives: one file holding a snapshot of the active profile's database plus
+//! its settings, so data can be moved between installs (e.g. desktop to Android).
+//!
+//! Layout: the `BACKUP_MAGIC` line, one line of JSON `BackupManifest`, then the raw
+//! SQLite database file (`database_size` bytes).
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::database;
+use crate::error::DotaKeeperError;
+use crate::settings::Settings;
+
+const BACKUP_MAGIC: &str = "DOTA-KEEPER-BACKUP";
+
+/// Bump when the archive layout changes; older archives must keep importing.
+pub const BACKUP_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub app_version: String,
+    /// Database schema version (see `database::MIGRATIONS`) at export time
+    pub schema_version: i64,
+    pub created_at: i64,
+    /// Whether `settings` still contains the OpenDota/Stratz API keys
+    pub includes_api_keys: bool,
+    pub settings: Settings,
+    pub database_size: u64,
+}
+
+/// How `import_archive` combines a backup with the data already on this install.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Keep local data and add anything from the backup that isn't here yet.
+    Merge,
+    /// Discard local data and restore the backup as-is.
+    Replace,
+}
+
+impl ImportMode {
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "merge" => Some(ImportMode::Merge),
+            "replace" => Some(ImportMode::Replace),
+            _ => None,
+        }
+    }
+}
+
+/// Scratch file next to the database (temp dirs aren't always writable on mobile).
+fn scratch_path(label: &str) -> PathBuf {
+    let dir = database::get_db_dir().cloned().unwrap_or_else(std::env::temp_dir);
+    dir.join(format!("dota_keeper_{}_{}.db", label, uuid::Uuid::new_v4()))
+}
+
+/// Write a backup of `conn` and `settings` to `dest`. API keys are stripped from the
+/// settings unless `include_api_keys` is set.
+pub fn export_archive(
+    conn: &Connection,
+    settings: &Settings,
+    include_api_keys: bool,
+    dest: &Path,
+) -> Result<BackupManifest, DotaKeeperError> {
+    let snapshot = scratch_path("export");
+    let result = database::backup_database(conn, &snapshot).and_then(|()| {
+        let db_bytes = std::fs::read(&snapshot)
+            .map_err(|e| DotaKeeperError::Other(format!("Failed to read database snapshot: {}", e)))?;
+        write_archive(conn, settings, include_api_keys, &db_bytes, dest)
+    });
+    let _ = std::fs::remove_file(&snapshot);
+    result
+}
+
+fn write_archive(
+    conn: &Connection,
+    settings: &Settings,
+    include_api_keys: bool,
+    db_bytes: &[u8],
+    dest: &Path,
+) -> Result<BackupManifest, DotaKeeperError> {
+    let mut settings = settings.clone();
+    if !include_api_keys {
+        settings.opendota_api_key = None;
+        settings.stratz_api_key = None;
+    }
+
+    let manifest = BackupManifest {
+        format_version: BACKUP_FORMAT_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version: database::get_schema_version(conn)?,
+        created_at: chrono::Utc::now().timestamp(),
+        includes_api_keys: include_api_keys,
+        settings,
+        database_size: db_bytes.len() as u64,
+    };
+    let manifest_json = serde_json::to_string(&manifest)
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to serialize backup manifest: {}", e)))?;
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| DotaKeeperError::Other(format!("Failed to create backup directory: {}", e)))?;
+    }
+    let mut file = std::fs::File::create(dest)
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to create backup file: {}", e)))?;
+    write!(file, "{}\n{}\n", BACKUP_MAGIC, manifest_json)
+        .and_then(|()| file.write_all(db_bytes))
+        .and_then(|()| file.flush())
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to write backup file: {}", e)))?;
+
+    Ok(manifest)
+}
+
+/// Read and validate an archive's manifest, returning it with the embedded database bytes.
+pub fn read_archive(src: &Path) -> Result<(BackupManifest, Vec<u8>), DotaKeeperError> {
+    let file = std::fs::File::open(src).map_err(|e| DotaKeeperError::Other(format!("Failed to open backup file: {}", e)))?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = String::new();
+    reader
+        .read_line(&mut magic)
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to read backup file: {}", e)))?;
+    if magic.trim_end() != BACKUP_MAGIC {
+        return Err(DotaKeeperError::InvalidInput("This file is not a Dota Keeper backup.".to_string()));
+    }
+
+    let mut manifest_line = String::new();
+    reader
+        .read_line(&mut manifest_line)
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to read backup file: {}", e)))?;
+    let manifest: BackupManifest = serde_json::from_str(&manifest_line)
+        .map_err(|e| DotaKeeperError::InvalidInput(format!("Failed to read backup manifest: {}", e)))?;
+
+    if manifest.format_version > BACKUP_FORMAT_VERSION {
+        return Err(DotaKeeperError::InvalidInput("This backup was made by a newer version of Dota Keeper. Update the app to import it.".to_string()));
+    }
+    let latest_schema = database::MIGRATIONS.last().map(|m| m.version).unwrap_or(0);
+    if manifest.schema_version > latest_schema {
+        return Err(DotaKeeperError::InvalidInput(format!(
+            "This backup uses database version {} but this app only supports up to {}. Update the app to import it.",
+            manifest.schema_version, latest_schema
+        )));
+    }
+
+    let mut db_bytes = Vec::new();
+    reader
+        .read_to_end(&mut db_bytes)
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to read backup file: {}", e)))?;
+    if db_bytes.len() as u64 != manifest.database_size {
+        return Err(DotaKeeperError::InvalidInput("The backup file is incomplete or corrupted.".to_string()));
+    }
+
+    Ok((manifest, db_bytes))
+}
+
+/// Import the archive at `src` into `conn`, which must belong to `account_id`. The
+/// embedded database is migrated to the current schema first. Returns the manifest and
+/// the number of matches added (merge) or restored (replace).
+pub fn import_archive(
+    conn: &mut Connection,
+    account_id: u32,
+    src: &Path,
+    mode: ImportMode,
+) -> Result<(BackupManifest, usize), DotaKeeperError> {
+    let (manifest, db_bytes) = read_archive(src)?;
+
+    let staged = scratch_path("import");
+    std::fs::write(&staged, &db_bytes).map_err(|e| DotaKeeperError::Other(format!("Failed to unpack backup: {}", e)))?;
+    let result = import_staged(conn, account_id, &staged, mode);
+    let _ = std::fs::remove_file(&staged);
+
+    result.map(|count| (manifest, count))
+}
+
+fn import_staged(conn: &mut Connection, account_id: u32, staged: &Path, mode: ImportMode) -> Result<usize, DotaKeeperError> {
+    {
+        let mut backup = Connection::open(staged)
+            .map_err(|e| DotaKeeperError::db("Failed to open backup database", e))?;
+        if backup.query_row("PRAGMA integrity_check", [], |row| row.get::<_, String>(0)).ok().as_deref() != Some("ok") {
+            return Err(DotaKeeperError::InvalidInput("The backup database is corrupted.".to_string()));
+        }
+        if database::get_db_account(&backup)?.is_some_and(|owner| owner != account_id) {
+            return Err(DotaKeeperError::InvalidInput("This backup belongs to a different Steam account. Switch to that account before importing it.".to_string()));
+        }
+        database::run_migrations(&mut backup, database::MIGRATIONS)?;
+    } // closed before SQLite reads the file again below
+
+    match mode {
+        ImportMode::Merge => Ok(database::merge_database(conn, staged)?),
+        ImportMode::Replace => {
+            database::restore_database(conn, staged)?;
+            database::set_db_account(conn, account_id)?;
+            conn.query_row("SELECT COUNT(*) FROM matches", [], |row| row.get::<_, i64>(0))
+                .map(|n| n as usize)
+                .map_err(|e| DotaKeeperError::db("Failed to count matches", e))
+        }
+    }
+}
+
+/// Settings after importing a backup with `mode`. Identity (installation ID, GSI token,
+/// profiles, active account) always stays local. Replace takes the backup's preferences; merge
+/// keeps local ones. Either way a local API key is never cleared by a stripped backup.
+pub fn merged_settings(local: &Settings, backup: &Settings, mode: ImportMode) -> Settings {
+    let mut merged = match mode {
+        ImportMode::Replace => Settings {
+            steam_id: local.steam_id.clone(),
+            profiles: local.profiles.clone(),
+            installation_id: local.installation_id.clone(),
+            gsi_token: local.gsi_token.clone(),
+            ..backup.clone()
+        },
+        ImportMode::Merge => local.clone(),
+    };
+    if merged.opendota_api_key.is_none() {
+        merged.opendota_api_key = local.opendota_api_key.clone().or_else(|| backup.opendota_api_key.clone());
+    }
+    if merged.stratz_api_key.is_none() {
+        merged.stratz_api_key = local.stratz_api_key.clone().or_else(|| backup.stratz_api_key.clone());
+    }
+    merged
+}
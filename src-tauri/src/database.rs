@@ -1,10 +1,12 @@
 use rusqlite::{Connection, OptionalExtension, params};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 use rand::Rng;
 
+use crate::error::DotaKeeperError;
+
 /// Global app data directory, set once during Tauri setup.
 /// Used instead of `dirs::data_local_dir()` so mobile platforms work correctly.
 static DB_DIR: OnceLock<PathBuf> = OnceLock::new();
@@ -20,12 +22,20 @@ pub fn init_shared_db(conn: Connection) {
 
 /// Acquire the shared database connection.
 /// Returns an error if `init_shared_db` has not been called yet or the Mutex is poisoned.
-pub fn get_db_conn() -> Result<std::sync::MutexGuard<'static, Connection>, String> {
+pub fn get_db_conn() -> Result<std::sync::MutexGuard<'static, Connection>, DotaKeeperError> {
     DB_CONN
         .get()
-        .ok_or_else(|| "Database not initialized".to_string())?
+        .ok_or_else(|| DotaKeeperError::Database("Database not initialized".to_string()))?
         .lock()
-        .map_err(|e| format!("Database lock poisoned: {}", e))
+        .map_err(|e| DotaKeeperError::Database(format!("Database lock poisoned: {}", e)))
+}
+
+/// Acquire the shared connection only if it still belongs to `account_id`. Long-running
+/// sync and parse tasks use this so they stop writing once the user switches profile.
+pub fn get_account_db_conn(account_id: u32) -> Result<std::sync::MutexGuard<'static, Connection>, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    ensure_db_account(&conn, account_id)?;
+    Ok(conn)
 }
 
 /// Called from `run()` setup before any commands execute.
@@ -49,6 +59,7 @@ pub enum GoalMetric {
     Level,
     ItemTiming,
     PartnerNetworth,
+    LaneNetworthLead,  // Networth of the player's side of the lane minus the enemy laners'
 }
 
 impl GoalMetric {
@@ -62,6 +73,7 @@ impl GoalMetric {
             GoalMetric::Level => "level",
             GoalMetric::ItemTiming => "item_timing",
             GoalMetric::PartnerNetworth => "partner_networth",
+            GoalMetric::LaneNetworthLead => "lane_networth_lead",
         }
     }
 
@@ -75,6 +87,7 @@ impl GoalMetric {
             "level" => Some(GoalMetric::Level),
             "item_timing" => Some(GoalMetric::ItemTiming),
             "partner_networth" => Some(GoalMetric::PartnerNetworth),
+            "lane_networth_lead" => Some(GoalMetric::LaneNetworthLead),
             _ => None,
         }
     }
@@ -139,6 +152,131 @@ impl GoalGameMode {
     }
 }
 
+/// Where a goal is in its lifecycle. Only active goals are evaluated against matches;
+/// achieved and archived goals are retired and keep their final stats.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum GoalState {
+    #[default]
+    Active,
+    Paused,
+    Achieved,
+    Archived,
+}
+
+impl GoalState {
+    fn to_string(self) -> &'static str {
+        match self {
+            GoalState::Active => "active",
+            GoalState::Paused => "paused",
+            GoalState::Achieved => "achieved",
+            GoalState::Archived => "archived",
+        }
+    }
+
+    fn from_string(s: &str) -> Self {
+        match s {
+            "paused" => GoalState::Paused,
+            "achieved" => GoalState::Achieved,
+            "archived" => GoalState::Archived,
+            _ => GoalState::Active,
+        }
+    }
+
+    pub fn is_retired(self) -> bool {
+        matches!(self, GoalState::Achieved | GoalState::Archived)
+    }
+}
+
+/// Narrows which matches a goal counts, on top of its hero / hero scope and game mode.
+/// The default counts every match.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct GoalScope {
+    /// Lane roles (1 = carry ... 5 = hard support, as `Match::role`); empty means any role
+    #[serde(default)]
+    pub roles: Vec<i32>,
+    /// Lobby types (0 = normal, 7 = ranked, ...); empty means any lobby
+    #[serde(default)]
+    pub lobby_types: Vec<i32>,
+    /// First patch counted, e.g. "7.36"
+    #[serde(default)]
+    pub patch_from: Option<String>,
+    /// Last patch counted; "7.36" includes its lettered updates ("7.36c")
+    #[serde(default)]
+    pub patch_to: Option<String>,
+    /// Only count matches played after the goal was created
+    #[serde(default)]
+    pub since_created: bool,
+}
+
+impl GoalScope {
+    fn is_empty(&self) -> bool {
+        *self == GoalScope::default()
+    }
+
+    fn includes(&self, match_data: &Match, goal_created_at: i64) -> bool {
+        (self.roles.is_empty() || self.roles.contains(&match_data.role))
+            && (self.lobby_types.is_empty() || self.lobby_types.contains(&match_data.lobby_type))
+            && (!self.since_created || match_data.start_time >= goal_created_at)
+            && self.includes_patch(match_data.patch.as_deref())
+    }
+
+    /// Matches with an unknown patch are left out once a patch range is set
+    fn includes_patch(&self, patch: Option<&str>) -> bool {
+        if self.patch_from.is_none() && self.patch_to.is_none() {
+            return true;
+        }
+        let Some(patch) = patch.and_then(patch_version) else {
+            return false;
+        };
+        let after_from = self.patch_from.as_deref().and_then(patch_version).is_none_or(|from| patch >= from);
+        let before_to = self.patch_to.as_deref().and_then(patch_version).is_none_or(|to| {
+            if to.2.is_empty() { (patch.0, patch.1) <= (to.0, to.1) } else { patch <= to }
+        });
+        after_from && before_to
+    }
+}
+
+/// "7.36c" as (7, 36, "c"), ordered the way patches were released
+fn patch_version(name: &str) -> Option<(u32, u32, String)> {
+    let (major, rest) = name.trim().split_once('.')?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    Some((major.parse().ok()?, rest[..digits].parse().ok()?, rest[digits..].to_string()))
+}
+
+/// One condition of a compound goal; the fields mean the same as on `Goal`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct GoalCondition {
+    pub metric: GoalMetric,
+    pub target_value: i32,  // For ItemTiming: target time in seconds
+    pub target_time_minutes: i32,  // Not used for ItemTiming conditions
+    #[serde(default)]
+    pub item_id: Option<i32>,  // Only used for ItemTiming conditions
+}
+
+/// How a compound goal combines its conditions
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum GoalCombinator {
+    #[default]
+    And,
+    Or,
+}
+
+impl GoalCombinator {
+    fn to_string(self) -> &'static str {
+        match self {
+            GoalCombinator::And => "and",
+            GoalCombinator::Or => "or",
+        }
+    }
+
+    fn from_string(s: &str) -> Self {
+        match s {
+            "or" => GoalCombinator::Or,
+            _ => GoalCombinator::And,
+        }
+    }
+}
+
 /// Patch version info cached from OpenDota constants
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PatchInfo {
@@ -159,6 +297,36 @@ pub struct Goal {
     pub game_mode: GoalGameMode,
     pub frequency_type: FrequencyType,
     pub created_at: i64,
+    /// Extra conditions that make this a compound goal, combined with the goal's own
+    /// metric by `combinator` (e.g. 50 LH at 10 AND at most 1 death by 10)
+    #[serde(default)]
+    pub conditions: Vec<GoalCondition>,
+    #[serde(default)]
+    pub combinator: GoalCombinator,
+    /// Which matches count towards the goal beyond hero and game mode
+    #[serde(default)]
+    pub scope: GoalScope,
+    /// Lifecycle state; changed with `set_goal_state`, never by `update_goal`
+    #[serde(default)]
+    pub state: GoalState,
+    /// When `state` last changed
+    #[serde(default)]
+    pub state_changed_at: Option<i64>,
+    /// When the goal was last marked achieved
+    #[serde(default)]
+    pub achieved_at: Option<i64>,
+}
+
+impl Goal {
+    /// The goal's own metric and target as a condition
+    pub fn primary_condition(&self) -> GoalCondition {
+        GoalCondition {
+            metric: self.metric.clone(),
+            target_value: self.target_value,
+            target_time_minutes: self.target_time_minutes,
+            item_id: self.item_id,
+        }
+    }
 }
 
 /// Input for creating a new goal (without id and created_at)
@@ -172,6 +340,54 @@ pub struct NewGoal {
     pub item_id: Option<i32>,  // Only used for ItemTiming goals
     pub game_mode: GoalGameMode,
     pub frequency_type: FrequencyType,
+    #[serde(default)]
+    pub conditions: Vec<GoalCondition>,
+    #[serde(default)]
+    pub combinator: GoalCombinator,
+    #[serde(default)]
+    pub scope: GoalScope,
+}
+
+/// An edit to an existing goal, as sent by the edit forms. Fields a form doesn't send
+/// (a compound goal's extra conditions and combinator, the match scope) keep their
+/// stored values.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GoalEdit {
+    pub id: i64,
+    pub hero_id: Option<i32>,
+    pub hero_scope: Option<String>,
+    pub metric: GoalMetric,
+    pub target_value: i32,
+    pub target_time_minutes: i32,
+    pub item_id: Option<i32>,
+    pub game_mode: GoalGameMode,
+    pub frequency_type: FrequencyType,
+    #[serde(default)]
+    pub conditions: Option<Vec<GoalCondition>>,
+    #[serde(default)]
+    pub combinator: Option<GoalCombinator>,
+    #[serde(default)]
+    pub scope: Option<GoalScope>,
+}
+
+impl GoalEdit {
+    /// The stored goal with this edit applied
+    pub fn apply_to(self, stored: Goal) -> Goal {
+        Goal {
+            hero_id: self.hero_id,
+            hero_scope: self.hero_scope,
+            metric: self.metric,
+            target_value: self.target_value,
+            target_time_minutes: self.target_time_minutes,
+            item_id: self.item_id,
+            game_mode: self.game_mode,
+            frequency_type: self.frequency_type,
+            conditions: self.conditions.unwrap_or(stored.conditions),
+            combinator: self.combinator.unwrap_or(stored.combinator),
+            scope: self.scope.unwrap_or(stored.scope),
+            ..stored
+        }
+    }
 }
 
 /// Hero goal suggestion (weekly personalized goal)
@@ -255,6 +471,7 @@ pub struct Match {
     pub role: i32,  // 0=unknown, 1=carry, 2=mid, 3=offlane, 4=soft support, 5=hard support
     pub rank_tier: Option<i32>,  // OpenDota rank_tier: 11-15=Herald, 21-25=Guardian, ..., 80=Immortal
     pub patch: Option<String>,   // e.g., "7.41" — determined from start_time via patches table
+    pub parsed_by: Option<String>,  // Data provider that produced the parsed data ("opendota" or "stratz")
 }
 
 impl Match {
@@ -265,52 +482,282 @@ impl Match {
     }
 }
 
-/// Get the path to the database file.
-/// Prefers the globally initialised app-data dir (set during Tauri setup, works on all
-/// platforms including mobile). Falls back to `dirs::data_local_dir()` for situations
-/// where setup hasn't run yet (e.g. unit tests).
-/// Dev builds use `dota_keeper_dev.db`; beta builds use `dota_keeper_beta.db`;
-/// release builds use `dota_keeper.db`.
-fn get_db_path() -> Option<PathBuf> {
-    let mut base = match DB_DIR.get() {
-        Some(dir) => dir.clone(),
-        None => {
-            let mut p = dirs::data_local_dir()?;
-            p.push("DotaKeeper");
-            p
-        }
-    };
+/// The directory holding the database files. Prefers the globally initialised app-data
+/// dir (set during Tauri setup, works on all platforms including mobile). Falls back to
+/// `dirs::data_local_dir()` for situations where setup hasn't run yet.
+pub fn db_dir() -> Option<PathBuf> {
+    match DB_DIR.get() {
+        Some(dir) => Some(dir.clone()),
+        None => Some(dirs::data_local_dir()?.join("DotaKeeper")),
+    }
+}
 
+/// Path of a profile's database file in `dir`.
+/// Dev builds use `dota_keeper_dev.db`; beta builds use `dota_keeper_beta.db`;
+/// release builds use `dota_keeper.db`. Profiles with a `db_key` get their own file
+/// alongside it, e.g. `dota_keeper_<db_key>.db`.
+///
+/// Each account gets its own file rather than sharing one database with every row tagged
+/// by account_id: no query can mix two accounts' data, and switching, backing up or
+/// deleting a profile is a matter of picking a file.
+pub fn db_path_in(dir: &Path, db_key: Option<&str>) -> PathBuf {
     #[cfg(debug_assertions)]
-    base.push("dota_keeper_dev.db");
+    let stem = "dota_keeper_dev";
 
     #[cfg(all(not(debug_assertions), feature = "beta"))]
-    base.push("dota_keeper_beta.db");
+    let stem = "dota_keeper_beta";
 
     #[cfg(all(not(debug_assertions), not(feature = "beta")))]
-    base.push("dota_keeper.db");
+    let stem = "dota_keeper";
+
+    match db_key {
+        Some(key) => dir.join(format!("{}_{}.db", stem, key)),
+        None => dir.join(format!("{}.db", stem)),
+    }
+}
 
-    Some(base)
+fn get_db_path(db_key: Option<&str>) -> Option<PathBuf> {
+    Some(db_path_in(&db_dir()?, db_key))
 }
 
-/// Initialize the database, creating tables if they don't exist
-pub fn init_db() -> Result<Connection, String> {
-    let path = get_db_path().ok_or_else(|| "Could not determine database directory".to_string())?;
+/// Initialize a profile's database: open the file, bring the schema up to date and reset
+/// any state left behind by a previous run. `db_key` selects the profile's file (`None`
+/// for the original database); `account_id` claims the file for that account if it has
+/// no owner yet.
+pub fn init_db(db_key: Option<&str>, account_id: Option<u32>) -> Result<Connection, DotaKeeperError> {
+    let path = get_db_path(db_key).ok_or_else(|| DotaKeeperError::Database("Could not determine database directory".to_string()))?;
+    open_db(&path, account_id)
+}
 
+/// `init_db` for the database file at `path`.
+fn open_db(path: &Path, account_id: Option<u32>) -> Result<Connection, DotaKeeperError> {
     // Create the directory if it doesn't exist
     if let Some(parent) = path.parent() {
         std::fs::create_dir_all(parent)
-            .map_err(|e| format!("Failed to create database directory: {}", e))?;
+            .map_err(|e| DotaKeeperError::Database(format!("Failed to create database directory: {}", e)))?;
     }
 
-    let conn = Connection::open(&path)
-        .map_err(|e| format!("Failed to open database: {}", e))?;
+    let mut conn = Connection::open(path)
+        .map_err(|e| DotaKeeperError::db("Failed to open database", e))?;
 
     // Enable WAL mode for better concurrency and set a busy timeout so that
     // any stray second connection waits instead of immediately returning SQLITE_BUSY.
     conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA busy_timeout=5000;")
-        .map_err(|e| format!("Failed to set database pragmas: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to set database pragmas", e))?;
+
+    run_migrations(&mut conn, MIGRATIONS)?;
+    reset_startup_state(&conn)?;
+    if let Some(account_id) = account_id {
+        if get_db_account(&conn)?.is_none() {
+            set_db_account(&conn, account_id)?;
+        }
+    }
+
+    Ok(conn)
+}
+
+/// Delete a database file (and its WAL side files). Missing files are ignored.
+fn delete_db_file(path: &Path) -> Result<(), DotaKeeperError> {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(suffix);
+        match std::fs::remove_file(&file) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(DotaKeeperError::Database(format!("Failed to delete profile database: {}", e))),
+        }
+    }
+    Ok(())
+}
+
+/// Factory reset across profiles: clear the open database (the file for `active_key`) and
+/// delete every other profile's file in `dir`. The original database is always included,
+/// so a reset made while a secondary profile is active doesn't leave the first profile's
+/// data and account claim behind.
+pub fn factory_reset_profiles(conn: &Connection, dir: &Path, active_key: Option<&str>, profile_keys: &[Option<&str>]) -> Result<(), DotaKeeperError> {
+    factory_reset_db(conn)?;
+    let others = std::iter::once(None)
+        .chain(profile_keys.iter().copied().filter(Option::is_some))
+        .filter(|key| *key != active_key);
+    for key in others {
+        delete_db_file(&db_path_in(dir, key))?;
+    }
+    Ok(())
+}
+
+/// The account (32-bit Steam ID) this database belongs to, if it has been claimed.
+pub fn get_db_account(conn: &Connection) -> Result<Option<u32>, DotaKeeperError> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM app_metadata WHERE key = 'account_id'", [], |row| row.get(0))
+        .optional()
+        .map_err(|e| DotaKeeperError::db("Failed to read database account", e))?;
+    Ok(value.and_then(|v| v.parse().ok()))
+}
+
+/// Record which account this database belongs to.
+pub fn set_db_account(conn: &Connection, account_id: u32) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('account_id', ?1)",
+        params![account_id.to_string()],
+    ).map_err(|e| DotaKeeperError::db("Failed to set database account", e))?;
+    Ok(())
+}
+
+/// Fail if this database belongs to a different account than `account_id`. Sync and parse
+/// flows call this before writing, so results fetched before a profile switch are dropped
+/// instead of landing in the newly active profile. Unclaimed databases accept any account.
+pub fn ensure_db_account(conn: &Connection, account_id: u32) -> Result<(), DotaKeeperError> {
+    match get_db_account(conn)? {
+        Some(owner) if owner != account_id => {
+            Err(DotaKeeperError::Other("The active account changed while this request was running; results were discarded.".to_string()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Per-launch housekeeping that is not a schema change and therefore runs on every start.
+fn reset_startup_state(conn: &Connection) -> Result<(), DotaKeeperError> {
+    // Check the reparse_dirty flag — if set, reset ALL parsed matches to unparsed
+    let reparse_dirty: bool = conn.query_row(
+        "SELECT value FROM app_metadata WHERE key = 'reparse_dirty'",
+        [],
+        |row| row.get::<_, String>(0),
+    ).unwrap_or_default() == "1";
+
+    if reparse_dirty {
+        conn.execute(
+            // Replay imports can't be fetched again from a provider, so they keep their data.
+            "UPDATE matches SET parse_state = 'unparsed'
+             WHERE (parse_state = 'parsed' OR parse_state = 'failed') AND parsed_by IS NOT 'replay'",
+            [],
+        ).map_err(|e| DotaKeeperError::db("Failed to mark matches for reparse", e))?;
+        // Give every match a fresh set of attempts
+        conn.execute("DELETE FROM parse_queue", [])
+            .map_err(|e| DotaKeeperError::db("Failed to reset parse queue", e))?;
+        conn.execute("DELETE FROM goal_evaluations", [])
+            .map_err(|e| DotaKeeperError::db("Failed to reset goal evaluations", e))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('reparse_dirty', '0')",
+            [],
+        ).map_err(|e| DotaKeeperError::db("Failed to clear reparse_dirty flag", e))?;
+    }
+
+    // Cleanup: Reset any "parsing" matches to "unparsed" (in case app crashed during parsing)
+    conn.execute(
+        "UPDATE matches SET parse_state = 'unparsed' WHERE parse_state = 'parsing'",
+        [],
+    ).map_err(|e| DotaKeeperError::db("Failed to cleanup parsing state", e))?;
+
+    Ok(())
+}
+
+// ===== Schema migrations =====
+
+/// A single forward-only schema change. `version` must be strictly increasing.
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub up: fn(&Connection) -> Result<(), DotaKeeperError>,
+}
+
+/// Every schema change the app knows about, in order. Append new migrations to the end;
+/// never edit or reorder one that has shipped.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "baseline", up: migrate_v1_baseline },
+    Migration { version: 2, name: "match_events", up: migrate_v2_match_events },
+    Migration { version: 3, name: "match_parsed_by", up: migrate_v3_match_parsed_by },
+    Migration { version: 4, name: "parse_queue", up: migrate_v4_parse_queue },
+    Migration { version: 5, name: "match_players", up: migrate_v5_match_players },
+    Migration { version: 6, name: "lane_opponent_slot", up: migrate_v6_lane_opponent_slot },
+    Migration { version: 7, name: "gsi_recordings", up: migrate_v7_gsi_recordings },
+    Migration { version: 8, name: "compound_goals", up: migrate_v8_compound_goals },
+    Migration { version: 9, name: "goal_lifecycle", up: migrate_v9_goal_lifecycle },
+    Migration { version: 10, name: "goal_scope", up: migrate_v10_goal_scope },
+    Migration { version: 11, name: "goal_evaluations", up: migrate_v11_goal_evaluations },
+    Migration { version: 12, name: "match_query_indexes", up: migrate_v12_match_query_indexes },
+    Migration { version: 13, name: "goal_state_baseline", up: migrate_v13_goal_state_baseline },
+    Migration { version: 14, name: "match_sort_indexes", up: migrate_v14_match_sort_indexes },
+    Migration { version: 15, name: "drop_lane_opponent_slot", up: migrate_v15_drop_lane_opponent_slot },
+];
+
+/// Return the highest migration version recorded in the database (0 for a database
+/// created before migrations were tracked, or a brand new file).
+pub fn get_schema_version(conn: &Connection) -> Result<i64, DotaKeeperError> {
+    let has_table: bool = conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+        [],
+        |row| row.get::<_, i32>(0),
+    ).map_err(|e| DotaKeeperError::db("Failed to read schema version", e))? > 0;
+    if !has_table {
+        return Ok(0);
+    }
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+        [],
+        |row| row.get(0),
+    ).map_err(|e| DotaKeeperError::db("Failed to read schema version", e))
+}
 
+/// Apply every migration newer than the database's recorded version.
+/// Each migration runs in its own transaction together with its `schema_migrations` row,
+/// so a crash part-way through startup leaves the database at the last completed version.
+/// Refuses to touch a database written by a newer version of the app.
+pub fn run_migrations(conn: &mut Connection, migrations: &[Migration]) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    ).map_err(|e| DotaKeeperError::db("Failed to create schema_migrations table", e))?;
+
+    let current = get_schema_version(conn)?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+    if current > latest {
+        return Err(DotaKeeperError::Schema(format!(
+            "Database schema version {} is newer than this version of Dota Keeper supports ({}). Please update the app.",
+            current, latest
+        )));
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()
+            .map_err(|e| DotaKeeperError::db(format!("Failed to start migration {}", migration.version), e))?;
+        (migration.up)(&tx).map_err(|e| {
+            DotaKeeperError::Schema(format!("Migration {} ({}) failed: {}", migration.version, migration.name, e))
+        })?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.name, chrono::Utc::now().timestamp()],
+        ).map_err(|e| DotaKeeperError::db(format!("Failed to record migration {}", migration.version), e))?;
+        tx.commit()
+            .map_err(|e| DotaKeeperError::db(format!("Failed to commit migration {}", migration.version), e))?;
+    }
+
+    Ok(())
+}
+
+/// Add a column unless the table already has it.
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<(), DotaKeeperError> {
+    let exists: bool = conn.query_row(
+        &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+        params![column],
+        |row| row.get::<_, i32>(0),
+    ).map_err(|e| DotaKeeperError::db(format!("Failed to inspect {} table", table), e))? > 0;
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        ).map_err(|e| DotaKeeperError::db(format!("Failed to add {}.{} column", table, column), e))?;
+    }
+    Ok(())
+}
+
+/// Version 1: the schema as it stood before migrations were tracked.
+/// Databases from that era can be in any intermediate shape, so every step here
+/// tolerates the table or column already existing.
+fn migrate_v1_baseline(conn: &Connection) -> Result<(), DotaKeeperError> {
     // Create the matches table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS matches (
@@ -336,7 +783,7 @@ pub fn init_db() -> Result<Connection, String> {
             role INTEGER NOT NULL DEFAULT 0
         )",
         [],
-    ).map_err(|e| format!("Failed to create matches table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create matches table", e))?;
 
     // Create the app_metadata key-value table for flags like reparse_dirty
     conn.execute(
@@ -345,10 +792,10 @@ pub fn init_db() -> Result<Connection, String> {
             value TEXT NOT NULL
         )",
         [],
-    ).map_err(|e| format!("Failed to create app_metadata table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create app_metadata table", e))?;
 
-    // One-time migration: force reparse for existing users who haven't had it yet.
-    // Uses a versioned key so it only fires once.
+    // Databases that predate the reparse_v1 flag were parsed by an older pipeline;
+    // flag them so reset_startup_state() queues every match for a fresh parse.
     let reparse_v1_done: bool = conn.query_row(
         "SELECT value FROM app_metadata WHERE key = 'reparse_v1'",
         [],
@@ -358,54 +805,21 @@ pub fn init_db() -> Result<Connection, String> {
         conn.execute(
             "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('reparse_dirty', '1')",
             [],
-        ).map_err(|e| format!("Failed to set reparse_dirty for migration: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to set reparse_dirty for migration", e))?;
         conn.execute(
             "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('reparse_v1', '1')",
             [],
-        ).map_err(|e| format!("Failed to mark reparse_v1 migration done: {}", e))?;
-    }
-
-    // Check the reparse_dirty flag — if set, reset ALL parsed matches to unparsed
-    let reparse_dirty: bool = conn.query_row(
-        "SELECT value FROM app_metadata WHERE key = 'reparse_dirty'",
-        [],
-        |row| row.get::<_, String>(0),
-    ).unwrap_or_default() == "1";
-
-    if reparse_dirty {
-        conn.execute(
-            "UPDATE matches SET parse_state = 'unparsed' WHERE parse_state = 'parsed' OR parse_state = 'failed'",
-            [],
-        ).map_err(|e| format!("Failed to mark matches for reparse: {}", e))?;
-        conn.execute(
-            "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('reparse_dirty', '0')",
-            [],
-        ).map_err(|e| format!("Failed to clear reparse_dirty flag: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to mark reparse_v1 migration done", e))?;
     }
 
-    // Cleanup: Reset any "parsing" matches to "unparsed" (in case app crashed during parsing)
-    conn.execute(
-        "UPDATE matches SET parse_state = 'unparsed' WHERE parse_state = 'parsing'",
-        [],
-    ).map_err(|e| format!("Failed to cleanup parsing state: {}", e))?;
-
     // Add parse_state column if it doesn't exist (for existing databases)
-    let _ = conn.execute(
-        "ALTER TABLE matches ADD COLUMN parse_state TEXT NOT NULL DEFAULT 'unparsed'",
-        [],
-    );
+    add_column_if_missing(conn, "matches", "parse_state", "TEXT NOT NULL DEFAULT 'unparsed'")?;
 
     // Add role column if it doesn't exist (for existing databases)
-    let _ = conn.execute(
-        "ALTER TABLE matches ADD COLUMN role INTEGER NOT NULL DEFAULT 0",
-        [],
-    );
+    add_column_if_missing(conn, "matches", "role", "INTEGER NOT NULL DEFAULT 0")?;
 
     // Add rank_tier column if it doesn't exist (for existing databases)
-    let _ = conn.execute(
-        "ALTER TABLE matches ADD COLUMN rank_tier INTEGER",
-        [],
-    );
+    add_column_if_missing(conn, "matches", "rank_tier", "INTEGER")?;
 
     // Create the goal_progress table for storing time-based metrics
     conn.execute(
@@ -419,7 +833,7 @@ pub fn init_db() -> Result<Connection, String> {
             UNIQUE(match_id, time_minutes)
         )",
         [],
-    ).map_err(|e| format!("Failed to create goal_progress table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create goal_progress table", e))?;
 
     // Create the match_cs table for storing per-minute CS data
     conn.execute(
@@ -433,7 +847,7 @@ pub fn init_db() -> Result<Connection, String> {
             UNIQUE(match_id, minute)
         )",
         [],
-    ).map_err(|e| format!("Failed to create match_cs table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create match_cs table", e))?;
 
     // Create the goals table
     conn.execute(
@@ -447,31 +861,19 @@ pub fn init_db() -> Result<Connection, String> {
             created_at INTEGER NOT NULL
         )",
         [],
-    ).map_err(|e| format!("Failed to create goals table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create goals table", e))?;
 
     // Add item_id column if it doesn't exist (for item timing goals)
-    let _ = conn.execute(
-        "ALTER TABLE goals ADD COLUMN item_id INTEGER",
-        [],
-    );
+    add_column_if_missing(conn, "goals", "item_id", "INTEGER")?;
 
     // Add hero_scope column if it doesn't exist (for role-group goals)
-    let _ = conn.execute(
-        "ALTER TABLE goals ADD COLUMN hero_scope TEXT",
-        [],
-    );
+    add_column_if_missing(conn, "goals", "hero_scope", "TEXT")?;
 
     // Add frequency_type column if it doesn't exist
-    let _ = conn.execute(
-        "ALTER TABLE goals ADD COLUMN frequency_type TEXT DEFAULT 'pct_75'",
-        [],
-    );
+    add_column_if_missing(conn, "goals", "frequency_type", "TEXT DEFAULT 'pct_75'")?;
 
     // Add patch column to matches if it doesn't exist
-    let _ = conn.execute(
-        "ALTER TABLE matches ADD COLUMN patch TEXT",
-        [],
-    );
+    add_column_if_missing(conn, "matches", "patch", "TEXT")?;
 
     // Create the patches cache table (stores Dota 2 patch versions with release dates)
     conn.execute(
@@ -480,7 +882,7 @@ pub fn init_db() -> Result<Connection, String> {
             date_epoch INTEGER NOT NULL
         )",
         [],
-    ).map_err(|e| format!("Failed to create patches table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create patches table", e))?;
 
     // Create the hero_favorites table
     conn.execute(
@@ -488,7 +890,7 @@ pub fn init_db() -> Result<Connection, String> {
             hero_id INTEGER PRIMARY KEY
         )",
         [],
-    ).map_err(|e| format!("Failed to create hero_favorites table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create hero_favorites table", e))?;
 
     // Create the hero_goal_suggestions table
     conn.execute(
@@ -501,7 +903,7 @@ pub fn init_db() -> Result<Connection, String> {
             games_analyzed INTEGER NOT NULL
         )",
         [],
-    ).map_err(|e| format!("Failed to create hero_goal_suggestions table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create hero_goal_suggestions table", e))?;
 
     // Create the player_networth table (per-minute networth for all players, used for PartnerNetworth goals)
     conn.execute(
@@ -514,7 +916,7 @@ pub fn init_db() -> Result<Connection, String> {
             FOREIGN KEY (match_id) REFERENCES matches(match_id)
         )",
         [],
-    ).map_err(|e| format!("Failed to create player_networth table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create player_networth table", e))?;
 
     // Create the match_xp table (per-minute XP for the player, used for XP/Level charts)
     conn.execute(
@@ -526,13 +928,10 @@ pub fn init_db() -> Result<Connection, String> {
             FOREIGN KEY (match_id) REFERENCES matches(match_id)
         )",
         [],
-    ).map_err(|e| format!("Failed to create match_xp table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create match_xp table", e))?;
 
     // Add partner_slot column if it doesn't exist (set during parsing for support players)
-    let _ = conn.execute(
-        "ALTER TABLE matches ADD COLUMN partner_slot INTEGER",
-        [],
-    );
+    add_column_if_missing(conn, "matches", "partner_slot", "INTEGER")?;
 
     // Create the item_timings table (no unique constraint — duplicates allowed for same item bought multiple times)
     conn.execute(
@@ -544,7 +943,7 @@ pub fn init_db() -> Result<Connection, String> {
             FOREIGN KEY (match_id) REFERENCES matches(match_id)
         )",
         [],
-    ).map_err(|e| format!("Failed to create item_timings table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create item_timings table", e))?;
 
     // Migration: remove UNIQUE(match_id, item_id) constraint that prevented duplicate item purchases.
     // SQLite can't drop inline constraints, so we recreate the table if the autoindex still exists.
@@ -555,7 +954,6 @@ pub fn init_db() -> Result<Connection, String> {
     ).unwrap_or(0) > 0;
     if has_unique {
         conn.execute_batch("
-            BEGIN;
             ALTER TABLE item_timings RENAME TO item_timings_old;
             CREATE TABLE item_timings (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -566,8 +964,7 @@ pub fn init_db() -> Result<Connection, String> {
             );
             INSERT INTO item_timings SELECT id, match_id, item_id, timing_seconds FROM item_timings_old;
             DROP TABLE item_timings_old;
-            COMMIT;
-        ").map_err(|e| format!("Failed to migrate item_timings: {}", e))?;
+        ").map_err(|e| DotaKeeperError::db("Failed to migrate item_timings", e))?;
     }
 
     // Create the daily_challenges table
@@ -587,7 +984,7 @@ pub fn init_db() -> Result<Connection, String> {
             UNIQUE(challenge_date)
         )",
         [],
-    ).map_err(|e| format!("Failed to create daily_challenges table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create daily_challenges table", e))?;
 
     // Create the challenge_history table (shared between daily and weekly)
     conn.execute(
@@ -601,7 +998,7 @@ pub fn init_db() -> Result<Connection, String> {
             target_achieved INTEGER
         )",
         [],
-    ).map_err(|e| format!("Failed to create challenge_history table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create challenge_history table", e))?;
 
     // Create the weekly challenge options table (3 cards shown to user)
     conn.execute(
@@ -619,7 +1016,7 @@ pub fn init_db() -> Result<Connection, String> {
             created_at INTEGER NOT NULL
         )",
         [],
-    ).map_err(|e| format!("Failed to create challenge_options table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create challenge_options table", e))?;
 
     // Create the mood check-ins table (mental health tracking)
     conn.execute(
@@ -634,7 +1031,7 @@ pub fn init_db() -> Result<Connection, String> {
             hidden      INTEGER NOT NULL DEFAULT 0
         )",
         [],
-    ).map_err(|e| format!("Failed to create mood_checkins table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create mood_checkins table", e))?;
 
     // Create the accepted weekly challenges table
     conn.execute(
@@ -653,12 +1050,12 @@ pub fn init_db() -> Result<Connection, String> {
             reroll_count INTEGER NOT NULL DEFAULT 0
         )",
         [],
-    ).map_err(|e| format!("Failed to create weekly_challenges table: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create weekly_challenges table", e))?;
 
     // Hero benchmark data (fetched from GitHub CSV on startup)
-    // Drop and recreate if schema changed (this is just cached data, safe to rebuild)
-    let _ = conn.execute("DROP TABLE IF EXISTS hero_benchmarks", []);
-    let _ = conn.execute("DROP TABLE IF EXISTS benchmark_metadata", []);
+    // Older builds used a different layout; this is just cached data, safe to rebuild
+    conn.execute_batch("DROP TABLE IF EXISTS hero_benchmarks; DROP TABLE IF EXISTS benchmark_metadata;")
+        .map_err(|e| DotaKeeperError::db("Failed to drop old hero_benchmarks tables", e))?;
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS hero_benchmarks (
             hero_id     INTEGER NOT NULL,
@@ -677,35 +1074,214 @@ pub fn init_db() -> Result<Connection, String> {
             key   TEXT PRIMARY KEY,
             value TEXT NOT NULL
         );"
-    ).map_err(|e| format!("Failed to create hero_benchmarks tables: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to create hero_benchmarks tables", e))?;
 
-    Ok(conn)
+    Ok(())
+}
+
+/// Version 2: per-match kill/death timestamps for the player, used by Kills/Deaths goals.
+fn migrate_v2_match_events(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute_batch(
+        "CREATE TABLE match_events (
+            match_id INTEGER NOT NULL,
+            event_type TEXT NOT NULL,
+            time_seconds INTEGER NOT NULL,
+            FOREIGN KEY (match_id) REFERENCES matches(match_id)
+        );
+        CREATE INDEX idx_match_events_match ON match_events(match_id, event_type);"
+    ).map_err(|e| DotaKeeperError::db("Failed to create match_events table", e))
+}
+
+/// v3: record which data provider produced each match's parsed data.
+fn migrate_v3_match_parsed_by(conn: &Connection) -> Result<(), DotaKeeperError> {
+    add_column_if_missing(conn, "matches", "parsed_by", "TEXT")
+}
+
+/// v4: durable queue of matches waiting to be parsed, with retry bookkeeping.
+fn migrate_v4_parse_queue(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute_batch(
+        "CREATE TABLE parse_queue (
+            match_id INTEGER PRIMARY KEY,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_error TEXT,
+            next_attempt_at INTEGER NOT NULL DEFAULT 0,
+            enqueued_at INTEGER NOT NULL,
+            FOREIGN KEY (match_id) REFERENCES matches(match_id)
+        );
+        CREATE INDEX idx_parse_queue_next_attempt ON parse_queue(next_attempt_at);"
+    ).map_err(|e| DotaKeeperError::db("Failed to create parse_queue table", e))
+}
+
+/// v5: end-of-game lines, per-minute timelines and purchases for all ten players.
+fn migrate_v5_match_players(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute_batch(
+        "CREATE TABLE match_players (
+            match_id INTEGER NOT NULL,
+            player_slot INTEGER NOT NULL,
+            account_id INTEGER,
+            hero_id INTEGER NOT NULL,
+            lane_role INTEGER NOT NULL DEFAULT 0,
+            kills INTEGER,
+            deaths INTEGER,
+            assists INTEGER,
+            last_hits INTEGER,
+            denies INTEGER,
+            xp_per_min INTEGER,
+            gold_per_min INTEGER,
+            hero_damage INTEGER,
+            tower_damage INTEGER,
+            hero_healing INTEGER,
+            PRIMARY KEY (match_id, player_slot),
+            FOREIGN KEY (match_id) REFERENCES matches(match_id)
+        );
+        CREATE INDEX idx_match_players_hero ON match_players(hero_id);
+        CREATE TABLE match_player_timelines (
+            match_id INTEGER NOT NULL,
+            player_slot INTEGER NOT NULL,
+            minute INTEGER NOT NULL,
+            last_hits INTEGER,
+            denies INTEGER,
+            xp INTEGER,
+            PRIMARY KEY (match_id, player_slot, minute),
+            FOREIGN KEY (match_id) REFERENCES matches(match_id)
+        );
+        CREATE TABLE match_player_items (
+            match_id INTEGER NOT NULL,
+            player_slot INTEGER NOT NULL,
+            item_id INTEGER NOT NULL,
+            timing_seconds INTEGER NOT NULL,
+            FOREIGN KEY (match_id) REFERENCES matches(match_id)
+        );
+        CREATE INDEX idx_match_player_items_match ON match_player_items(match_id, player_slot);"
+    ).map_err(|e| DotaKeeperError::db("Failed to create match_players tables", e))
+}
+
+/// v6: the direct lane opponent's player_slot, set during parsing.
+fn migrate_v6_lane_opponent_slot(conn: &Connection) -> Result<(), DotaKeeperError> {
+    add_column_if_missing(conn, "matches", "lane_opponent_slot", "INTEGER")
+}
+
+/// v7: games recorded over Game State Integration, kept until their match is fetched.
+/// No foreign key: the recording is made before the match appears in the match list.
+fn migrate_v7_gsi_recordings(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute_batch(
+        "CREATE TABLE gsi_recordings (
+            match_id INTEGER PRIMARY KEY,
+            account_id INTEGER NOT NULL,
+            data TEXT NOT NULL,
+            recorded_at INTEGER NOT NULL
+        );"
+    ).map_err(|e| DotaKeeperError::db("Failed to create gsi_recordings table", e))
+}
+
+/// v8: extra conditions (JSON) and their AND/OR combinator for compound goals.
+fn migrate_v8_compound_goals(conn: &Connection) -> Result<(), DotaKeeperError> {
+    add_column_if_missing(conn, "goals", "conditions", "TEXT")?;
+    add_column_if_missing(conn, "goals", "combinator", "TEXT NOT NULL DEFAULT 'and'")
+}
+
+/// v9: goal lifecycle state with its timestamps, and the stats a goal retired with.
+fn migrate_v9_goal_lifecycle(conn: &Connection) -> Result<(), DotaKeeperError> {
+    add_column_if_missing(conn, "goals", "state", "TEXT NOT NULL DEFAULT 'active'")?;
+    add_column_if_missing(conn, "goals", "state_changed_at", "INTEGER")?;
+    add_column_if_missing(conn, "goals", "achieved_at", "INTEGER")?;
+    add_column_if_missing(conn, "goals", "final_stats", "TEXT")
+}
+
+/// v10: role / lobby type / patch / since-created scoping of goals, as JSON.
+fn migrate_v10_goal_scope(conn: &Connection) -> Result<(), DotaKeeperError> {
+    add_column_if_missing(conn, "goals", "scope", "TEXT")
+}
+
+/// v11: cache of goal-vs-match evaluations, so the match list doesn't re-evaluate every
+/// goal against every match. `applicable = 0` records that the goal doesn't apply.
+fn migrate_v11_goal_evaluations(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute_batch(
+        "CREATE TABLE goal_evaluations (
+            goal_id INTEGER NOT NULL,
+            match_id INTEGER NOT NULL,
+            applicable INTEGER NOT NULL,
+            achieved INTEGER NOT NULL DEFAULT 0,
+            actual_value INTEGER,
+            conditions TEXT,
+            PRIMARY KEY (goal_id, match_id)
+        );
+        CREATE INDEX idx_goal_evaluations_match ON goal_evaluations(match_id);"
+    ).map_err(|e| DotaKeeperError::db("Failed to create goal_evaluations table", e))
+}
+
+/// v12: indexes behind `query_matches` (newest-first paging, optionally per hero).
+fn migrate_v12_match_query_indexes(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_matches_start_time ON matches(start_time, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_hero_start_time ON matches(hero_id, start_time, match_id);
+        CREATE INDEX IF NOT EXISTS idx_goal_evaluations_outcome ON goal_evaluations(goal_id, applicable, achieved);"
+    ).map_err(|e| DotaKeeperError::db("Failed to create match query indexes", e))
+}
+
+/// v13: goals created before the lifecycle existed count as active from now on, so games
+/// played before the upgrade don't complete them retroactively.
+fn migrate_v13_goal_state_baseline(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "UPDATE goals SET state_changed_at = CAST(strftime('%s', 'now') AS INTEGER) WHERE state_changed_at IS NULL",
+        [],
+    ).map_err(|e| DotaKeeperError::db("Failed to set goal state baseline", e))?;
+    Ok(())
+}
+
+/// v14: one index per `MatchSortField` column, so sorted match lists page without a full scan.
+fn migrate_v14_match_sort_indexes(conn: &Connection) -> Result<(), DotaKeeperError> {
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_matches_duration ON matches(duration, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_kills ON matches(kills, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_deaths ON matches(deaths, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_assists ON matches(assists, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_last_hits ON matches(last_hits, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_gold_per_min ON matches(gold_per_min, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_xp_per_min ON matches(xp_per_min, match_id);
+        CREATE INDEX IF NOT EXISTS idx_matches_hero_damage ON matches(hero_damage, match_id);"
+    ).map_err(|e| DotaKeeperError::db("Failed to create match sort indexes", e))
+}
+
+/// v15: lane opponents are worked out from match_players' lane roles (see `lane_sides`),
+/// so the single opponent slot from v6 is no longer kept.
+fn migrate_v15_drop_lane_opponent_slot(conn: &Connection) -> Result<(), DotaKeeperError> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info('matches') WHERE name = 'lane_opponent_slot'",
+        [],
+        |row| row.get::<_, i32>(0),
+    ).map_err(|e| DotaKeeperError::db("Failed to inspect matches table", e))? > 0;
+    if exists {
+        conn.execute("ALTER TABLE matches DROP COLUMN lane_opponent_slot", [])
+            .map_err(|e| DotaKeeperError::db("Failed to drop matches.lane_opponent_slot column", e))?;
+    }
+    Ok(())
 }
 
 /// Set the reparse_dirty flag so all matches get reparsed on next app start.
-pub fn set_reparse_dirty(conn: &Connection) -> Result<(), String> {
+pub fn set_reparse_dirty(conn: &Connection) -> Result<(), DotaKeeperError> {
     conn.execute(
         "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('reparse_dirty', '1')",
         [],
-    ).map_err(|e| format!("Failed to set reparse_dirty flag: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to set reparse_dirty flag", e))?;
     Ok(())
 }
 
 /// Check if a match already exists in the database
-pub fn match_exists(conn: &Connection, match_id: i64) -> Result<bool, String> {
+pub fn match_exists(conn: &Connection, match_id: i64) -> Result<bool, DotaKeeperError> {
     let count: i32 = conn
         .query_row(
             "SELECT COUNT(*) FROM matches WHERE match_id = ?1",
             params![match_id],
             |row| row.get(0),
         )
-        .map_err(|e| format!("Failed to check if match exists: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to check if match exists", e))?;
 
     Ok(count > 0)
 }
 
 /// Insert a match into the database
-pub fn insert_match(conn: &Connection, m: &Match) -> Result<(), String> {
+pub fn insert_match(conn: &Connection, m: &Match) -> Result<(), DotaKeeperError> {
     conn.execute(
         "INSERT OR IGNORE INTO matches (
             match_id, hero_id, start_time, duration, game_mode, lobby_type,
@@ -736,38 +1312,110 @@ pub fn insert_match(conn: &Connection, m: &Match) -> Result<(), String> {
             m.rank_tier,
             m.patch,
         ],
-    ).map_err(|e| format!("Failed to insert match: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to insert match", e))?;
 
     Ok(())
 }
 
-/// Get the oldest match timestamp from the database
-pub fn get_oldest_match_timestamp(conn: &Connection) -> Result<Option<i64>, String> {
-    let result = conn.query_row(
-        "SELECT MIN(start_time) FROM matches",
-        [],
-        |row| row.get::<_, Option<i64>>(0),
-    );
-
-    match result {
-        Ok(timestamp) => Ok(timestamp),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(format!("Failed to get oldest match timestamp: {}", e)),
+/// Position and totals of the full match history import. Saved in `app_metadata` after
+/// every page so an interrupted import resumes where it stopped.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BackfillState {
+    /// How many of the player's matches (newest first) have been paged through
+    pub offset: usize,
+    /// Start time of the oldest match reached so far
+    pub oldest_start_time: Option<i64>,
+    /// Stop at matches that started before this Unix timestamp (`None`: the first match)
+    pub stop_before: Option<i64>,
+    /// The patch `stop_before` was derived from, when the cap was given as a patch
+    pub stop_at_patch: Option<String>,
+    pub matches_seen: usize,
+    pub matches_added: usize,
+    /// How many matches the player has in total, when known (measures uncapped imports)
+    pub total_matches: Option<usize>,
+    pub started_at: i64,
+    pub finished: bool,
+}
+
+impl BackfillState {
+    pub fn new(stop_before: Option<i64>, stop_at_patch: Option<String>, now: i64) -> Self {
+        BackfillState { stop_before, stop_at_patch, started_at: now, ..BackfillState::default() }
+    }
+
+    /// Take in one page (of at most `page_size` matches, newest first) and return the ones
+    /// within the cap, to be stored. Marks the import finished once the history or the
+    /// cap runs out.
+    pub fn advance(&mut self, page: Vec<Match>, page_size: usize) -> Vec<Match> {
+        let last_page = page.len() < page_size;
+        self.offset += page.len();
+        self.matches_seen += page.len();
+        if let Some(oldest) = page.iter().map(|m| m.start_time).min() {
+            self.oldest_start_time = Some(self.oldest_start_time.map_or(oldest, |o| o.min(oldest)));
+        }
+        let in_range: Vec<Match> = page
+            .into_iter()
+            .filter(|m| self.stop_before.is_none_or(|stop| m.start_time >= stop))
+            .collect();
+        let past_cap = self
+            .stop_before
+            .is_some_and(|stop| self.oldest_start_time.is_some_and(|oldest| oldest < stop));
+        self.finished = last_page || past_cap;
+        in_range
+    }
+
+    /// Share of the capped range covered so far. Without a cap, share of the player's
+    /// total matches seen; `None` if that total couldn't be fetched.
+    pub fn progress(&self) -> Option<f64> {
+        if self.finished {
+            return Some(1.0);
+        }
+        let Some(stop) = self.stop_before else {
+            let total = self.total_matches?.max(1) as f64;
+            return Some((self.matches_seen as f64 / total).clamp(0.0, 1.0));
+        };
+        let span = (self.started_at - stop).max(1) as f64;
+        let covered = self.oldest_start_time.map_or(0, |oldest| self.started_at - oldest) as f64;
+        Some((covered / span).clamp(0.0, 1.0))
     }
 }
 
+/// The saved match history import, if one was ever started on this database.
+pub fn get_backfill_state(conn: &Connection) -> Result<Option<BackfillState>, DotaKeeperError> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM app_metadata WHERE key = 'backfill_state'", [], |row| row.get(0))
+        .optional()
+        .map_err(|e| DotaKeeperError::db("Failed to read backfill state", e))?;
+    Ok(value.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+pub fn save_backfill_state(conn: &Connection, state: &BackfillState) -> Result<(), DotaKeeperError> {
+    let json = serde_json::to_string(state).map_err(|e| DotaKeeperError::Database(format!("Failed to serialize backfill state: {}", e)))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO app_metadata (key, value) VALUES ('backfill_state', ?1)",
+        params![json],
+    ).map_err(|e| DotaKeeperError::db("Failed to save backfill state", e))?;
+    Ok(())
+}
+
+/// Release date of a cached patch, e.g. "7.35"
+pub fn get_patch_release(conn: &Connection, patch: &str) -> Result<Option<i64>, DotaKeeperError> {
+    conn.query_row("SELECT date_epoch FROM patches WHERE name = ?1", params![patch], |row| row.get(0))
+        .optional()
+        .map_err(|e| DotaKeeperError::db(format!("Failed to read patch {}", patch), e))
+}
+
 /// Get all unparsed or failed matches from the database
-pub fn get_unparsed_matches(conn: &Connection) -> Result<Vec<Match>, String> {
+pub fn get_unparsed_matches(conn: &Connection) -> Result<Vec<Match>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT match_id, hero_id, start_time, duration, game_mode, lobby_type,
                     radiant_win, player_slot, kills, deaths, assists, xp_per_min,
-                    gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch
+                    gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch, parsed_by
              FROM matches
              WHERE parse_state = 'unparsed' OR parse_state = 'failed'
              ORDER BY start_time DESC",
         )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let matches = stmt
         .query_map([], |row| {
@@ -795,47 +1443,349 @@ pub fn get_unparsed_matches(conn: &Connection) -> Result<Vec<Match>, String> {
                 role: row.get(19).unwrap_or(0),
                 rank_tier: row.get(20).ok(),
                 patch: row.get(21).ok(),
+                parsed_by: row.get(22).ok(),
             })
         })
-        .map_err(|e| format!("Failed to query matches: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query matches", e))?;
 
     let mut result = Vec::new();
     for m in matches {
-        result.push(m.map_err(|e| format!("Failed to read match: {}", e))?);
+        result.push(m.map_err(|e| DotaKeeperError::db("Failed to read match", e))?);
     }
 
     Ok(result)
 }
 
+// ===== Backup / restore =====
+
+/// Copy the live database to `dest` using SQLite's online backup API, which gives a
+/// consistent snapshot even while the WAL holds pages that haven't been checkpointed.
+pub fn backup_database(conn: &Connection, dest: &Path) -> Result<(), DotaKeeperError> {
+    conn.backup(rusqlite::DatabaseName::Main, dest, None::<fn(rusqlite::backup::Progress)>)
+        .map_err(|e| DotaKeeperError::db("Failed to back up database", e))
+}
+
+/// Overwrite the live database with the contents of the database file at `src`.
+pub fn restore_database(conn: &mut Connection, src: &Path) -> Result<(), DotaKeeperError> {
+    conn.restore(rusqlite::DatabaseName::Main, src, None::<fn(rusqlite::backup::Progress)>)
+        .map_err(|e| DotaKeeperError::db("Failed to restore database", e))
+}
+
+/// How rows from a backup are merged into a table that already has data.
+enum MergeRule {
+    /// Only rows belonging to matches the live database doesn't have yet.
+    NewMatches,
+    /// `INSERT OR IGNORE`: a row clashing on a unique key keeps the local version.
+    KeepLocal,
+    /// No natural key: a row is skipped when an identical one already exists.
+    SkipIdentical,
+    /// A row is skipped when a local one has the same values in these columns, so rows
+    /// edited on either side since the backup keep their local version.
+    KeepLocalBy(&'static [&'static str]),
+}
+
+/// User data copied by `merge_database`, parents before children. Cached remote data
+/// (patches, benchmarks) and bookkeeping (parse queue, metadata) is left alone.
+const MERGE_TABLES: &[(&str, MergeRule)] = &[
+    ("matches", MergeRule::NewMatches),
+    ("match_cs", MergeRule::NewMatches),
+    ("goal_progress", MergeRule::NewMatches),
+    ("item_timings", MergeRule::NewMatches),
+    ("player_networth", MergeRule::NewMatches),
+    ("match_xp", MergeRule::NewMatches),
+    ("match_events", MergeRule::NewMatches),
+    ("match_players", MergeRule::NewMatches),
+    ("match_player_timelines", MergeRule::NewMatches),
+    ("match_player_items", MergeRule::NewMatches),
+    ("mood_checkins", MergeRule::KeepLocal),
+    ("hero_favorites", MergeRule::KeepLocal),
+    ("daily_challenges", MergeRule::KeepLocal),
+    ("weekly_challenges", MergeRule::KeepLocal),
+    ("goals", MergeRule::KeepLocalBy(&["created_at", "metric"])),
+    ("challenge_history", MergeRule::SkipIdentical),
+    ("challenge_options", MergeRule::SkipIdentical),
+];
+
+/// Merge the user data from the database file at `src` into the live database, keeping
+/// local rows wherever the two overlap. `src` must already be at the current schema
+/// version. Returns the number of matches added.
+pub fn merge_database(conn: &Connection, src: &Path) -> Result<usize, DotaKeeperError> {
+    conn.execute("ATTACH DATABASE ?1 AS backup", params![src.to_string_lossy()])
+        .map_err(|e| DotaKeeperError::db("Failed to open backup database", e))?;
+    let result = merge_attached_backup(conn);
+    conn.execute_batch("DROP TABLE IF EXISTS temp.merge_new_matches; DETACH DATABASE backup")
+        .map_err(|e| DotaKeeperError::db("Failed to close backup database", e))?;
+    result
+}
+
+fn merge_attached_backup(conn: &Connection) -> Result<usize, DotaKeeperError> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| DotaKeeperError::db("Failed to begin merge", e))?;
+
+    tx.execute(
+        "CREATE TEMP TABLE merge_new_matches AS
+         SELECT match_id FROM backup.matches WHERE match_id NOT IN (SELECT match_id FROM main.matches)",
+        [],
+    ).map_err(|e| DotaKeeperError::db("Failed to compare matches", e))?;
+    let new_matches: i64 = tx
+        .query_row("SELECT COUNT(*) FROM temp.merge_new_matches", [], |row| row.get(0))
+        .map_err(|e| DotaKeeperError::db("Failed to count new matches", e))?;
+
+    for (table, rule) in MERGE_TABLES {
+        // Surrogate ids are reassigned so they can't collide with local rows.
+        let backup_columns = table_columns(&tx, "backup", table)?;
+        let columns: Vec<String> = table_columns(&tx, "main", table)?
+            .into_iter()
+            .filter(|c| c != "id" && backup_columns.contains(c))
+            .collect();
+        let insert_list = columns.join(", ");
+        let select_list = columns.iter().map(|c| format!("b.{}", c)).collect::<Vec<_>>().join(", ");
+
+        let sql = match rule {
+            MergeRule::NewMatches => format!(
+                "INSERT OR IGNORE INTO main.{t} ({i}) SELECT {s} FROM backup.{t} b
+                 WHERE b.match_id IN (SELECT match_id FROM temp.merge_new_matches)",
+                t = table, i = insert_list, s = select_list
+            ),
+            MergeRule::KeepLocal => format!(
+                "INSERT OR IGNORE INTO main.{t} ({i}) SELECT {s} FROM backup.{t} b",
+                t = table, i = insert_list, s = select_list
+            ),
+            MergeRule::SkipIdentical | MergeRule::KeepLocalBy(_) => {
+                let key: Vec<&str> = match rule {
+                    MergeRule::KeepLocalBy(key) => key.to_vec(),
+                    _ => columns.iter().map(String::as_str).collect(),
+                };
+                let same = key.iter().map(|c| format!("m.{c} IS b.{c}", c = c)).collect::<Vec<_>>().join(" AND ");
+                format!(
+                    "INSERT INTO main.{t} ({i}) SELECT {s} FROM backup.{t} b
+                     WHERE NOT EXISTS (SELECT 1 FROM main.{t} m WHERE {w})",
+                    t = table, i = insert_list, s = select_list, w = same
+                )
+            }
+        };
+        tx.execute(&sql, [])
+            .map_err(|e| DotaKeeperError::db(format!("Failed to merge {}", table), e))?;
+    }
+
+    tx.commit().map_err(|e| DotaKeeperError::db("Failed to commit merge", e))?;
+    Ok(new_matches as usize)
+}
+
+/// Column names of `schema.table`, in declaration order.
+fn table_columns(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>, DotaKeeperError> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}.table_info({})", schema, table))
+        .map_err(|e| DotaKeeperError::db(format!("Failed to read columns of {}", table), e))?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| DotaKeeperError::db(format!("Failed to read columns of {}", table), e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| DotaKeeperError::db(format!("Failed to read columns of {}", table), e))?;
+    Ok(columns)
+}
+
+// ===== Parse queue =====
+
+/// Stop retrying a match after this many failed parse attempts.
+pub const MAX_PARSE_ATTEMPTS: i32 = 8;
+
+/// Delay before retrying a match that has failed `attempts` times:
+/// 1 minute, doubling each time, capped at 6 hours.
+pub fn parse_retry_delay_secs(attempts: i32) -> i64 {
+    let doublings = (attempts.max(1) - 1).min(16) as u32;
+    (60_i64 << doublings).min(6 * 60 * 60)
+}
+
+/// Queue every unparsed or failed match that isn't queued yet, and every match parsed only
+/// from a GSI recording (which holds the player's own data but not the other nine players').
+/// Returns the number of newly queued matches.
+pub fn enqueue_unparsed_matches(conn: &Connection, now: i64) -> Result<usize, DotaKeeperError> {
+    conn.execute(
+        "INSERT OR IGNORE INTO parse_queue (match_id, attempts, next_attempt_at, enqueued_at)
+         SELECT match_id, 0, 0, ?1 FROM matches
+         WHERE parse_state = 'unparsed' OR parse_state = 'failed'
+            OR (parse_state = 'parsed' AND parsed_by = 'gsi')",
+        params![now],
+    ).map_err(|e| DotaKeeperError::db("Failed to enqueue unparsed matches", e))
+}
+
+/// Queue a match the user asked to reparse, due now with a fresh set of attempts
+/// (even if earlier attempts had used them all up).
+pub fn reset_parse_job(conn: &Connection, match_id: i64, now: i64) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "INSERT INTO parse_queue (match_id, attempts, next_attempt_at, enqueued_at)
+         VALUES (?1, 0, ?2, ?2)
+         ON CONFLICT(match_id) DO UPDATE SET attempts = 0, last_error = NULL, next_attempt_at = ?2",
+        params![match_id, now],
+    ).map_err(|e| DotaKeeperError::db("Failed to reset parse job", e))?;
+    Ok(())
+}
+
+/// The most recent queued match that is due for an attempt, if any. Matches parsed from
+/// a GSI recording only come up once no unparsed or failed match is due. Matches already
+/// parsed by a provider, or being parsed right now, are skipped.
+pub fn next_due_parse_job(conn: &Connection, now: i64) -> Result<Option<i64>, DotaKeeperError> {
+    conn.query_row(
+        "SELECT q.match_id FROM parse_queue q
+         JOIN matches m ON m.match_id = q.match_id
+         WHERE q.attempts < ?1 AND q.next_attempt_at <= ?2
+           AND (m.parse_state IN ('unparsed', 'failed') OR (m.parse_state = 'parsed' AND m.parsed_by = 'gsi'))
+         ORDER BY m.parse_state = 'parsed', m.start_time DESC
+         LIMIT 1",
+        params![MAX_PARSE_ATTEMPTS, now],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| DotaKeeperError::db("Failed to read parse queue", e))
+}
+
+/// Number of queued matches that will still be attempted.
+pub fn count_pending_parse_jobs(conn: &Connection) -> Result<usize, DotaKeeperError> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM parse_queue WHERE attempts < ?1",
+        params![MAX_PARSE_ATTEMPTS],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count as usize)
+    .map_err(|e| DotaKeeperError::db("Failed to count parse queue", e))
+}
+
+/// Record a failed attempt and schedule the next one with exponential backoff.
+pub fn record_parse_failure(conn: &Connection, match_id: i64, error: &str, now: i64) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "INSERT OR IGNORE INTO parse_queue (match_id, attempts, next_attempt_at, enqueued_at)
+         VALUES (?1, 0, 0, ?2)",
+        params![match_id, now],
+    ).map_err(|e| DotaKeeperError::db("Failed to enqueue match", e))?;
+
+    let attempts: i32 = conn.query_row(
+        "SELECT attempts FROM parse_queue WHERE match_id = ?1",
+        params![match_id],
+        |row| row.get::<_, i32>(0),
+    ).map_err(|e| DotaKeeperError::db("Failed to read parse attempts", e))? + 1;
+
+    conn.execute(
+        "UPDATE parse_queue SET attempts = ?1, last_error = ?2, next_attempt_at = ?3 WHERE match_id = ?4",
+        params![attempts, error, now + parse_retry_delay_secs(attempts), match_id],
+    ).map_err(|e| DotaKeeperError::db("Failed to record parse failure", e))?;
+
+    Ok(())
+}
+
+/// Drop a match from the parse queue once it has been parsed.
+pub fn remove_from_parse_queue(conn: &Connection, match_id: i64) -> Result<(), DotaKeeperError> {
+    conn.execute("DELETE FROM parse_queue WHERE match_id = ?1", params![match_id])
+        .map_err(|e| DotaKeeperError::db("Failed to remove match from parse queue", e))?;
+    Ok(())
+}
+
+/// The player's slot and the parse state of a stored match, if it exists.
+pub fn get_match_slot_and_state(conn: &Connection, match_id: i64) -> Result<Option<(i32, MatchState)>, DotaKeeperError> {
+    conn.query_row(
+        "SELECT player_slot, parse_state FROM matches WHERE match_id = ?1",
+        params![match_id],
+        |row| Ok((row.get(0)?, MatchState::from_string(&row.get::<_, String>(1)?))),
+    )
+    .optional()
+    .map_err(|e| DotaKeeperError::db("Failed to query match", e))
+}
+
+/// A finished game recorded over GSI (`data` is the serialized `gsi::LiveGame`).
+#[derive(Debug, Clone)]
+pub struct GsiRecording {
+    pub match_id: i64,
+    pub account_id: u32,
+    pub data: String,
+    pub recorded_at: i64,
+}
+
+pub fn save_gsi_recording(conn: &Connection, match_id: i64, account_id: u32, data: &str) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO gsi_recordings (match_id, account_id, data, recorded_at) VALUES (?1, ?2, ?3, ?4)",
+        params![match_id, account_id, data, chrono::Utc::now().timestamp()],
+    ).map_err(|e| DotaKeeperError::db("Failed to save GSI recording", e))?;
+    Ok(())
+}
+
+pub fn get_gsi_recordings(conn: &Connection) -> Result<Vec<GsiRecording>, DotaKeeperError> {
+    let mut stmt = conn
+        .prepare("SELECT match_id, account_id, data, recorded_at FROM gsi_recordings ORDER BY recorded_at")
+        .map_err(|e| DotaKeeperError::db("Failed to prepare GSI recordings query", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(GsiRecording {
+                match_id: row.get(0)?,
+                account_id: row.get(1)?,
+                data: row.get(2)?,
+                recorded_at: row.get(3)?,
+            })
+        })
+        .map_err(|e| DotaKeeperError::db("Failed to query GSI recordings", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| DotaKeeperError::db("Failed to read GSI recording", e))
+}
+
+pub fn delete_gsi_recording(conn: &Connection, match_id: i64) -> Result<(), DotaKeeperError> {
+    conn.execute("DELETE FROM gsi_recordings WHERE match_id = ?1", params![match_id])
+        .map_err(|e| DotaKeeperError::db("Failed to delete GSI recording", e))?;
+    Ok(())
+}
+
 /// Clear all matches and related data from the database
-pub fn clear_all_matches(conn: &Connection) -> Result<(), String> {
+pub fn clear_all_matches(conn: &Connection) -> Result<(), DotaKeeperError> {
     // Clear all child tables before matches to avoid FK constraint issues
     conn.execute("DELETE FROM match_cs", [])
-        .map_err(|e| format!("Failed to delete match CS data: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to delete match CS data", e))?;
     conn.execute("DELETE FROM goal_progress", [])
-        .map_err(|e| format!("Failed to delete goal progress: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to delete goal progress", e))?;
     conn.execute("DELETE FROM player_networth", [])
-        .map_err(|e| format!("Failed to delete player networth: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to delete player networth", e))?;
     conn.execute("DELETE FROM item_timings", [])
-        .map_err(|e| format!("Failed to delete item timings: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to delete item timings", e))?;
+    conn.execute("DELETE FROM match_events", [])
+        .map_err(|e| DotaKeeperError::db("Failed to delete match events", e))?;
+    conn.execute("DELETE FROM parse_queue", [])
+        .map_err(|e| DotaKeeperError::db("Failed to delete parse queue", e))?;
+    conn.execute("DELETE FROM match_player_items", [])
+        .map_err(|e| DotaKeeperError::db("Failed to delete match player items", e))?;
+    conn.execute("DELETE FROM match_player_timelines", [])
+        .map_err(|e| DotaKeeperError::db("Failed to delete match player timelines", e))?;
+    conn.execute("DELETE FROM match_players", [])
+        .map_err(|e| DotaKeeperError::db("Failed to delete match players", e))?;
     conn.execute("DELETE FROM mood_checkins", [])
-        .map_err(|e| format!("Failed to delete mood check-ins: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to delete mood check-ins", e))?;
+    conn.execute("DELETE FROM gsi_recordings", [])
+        .map_err(|e| DotaKeeperError::db("Failed to delete GSI recordings", e))?;
+    conn.execute("DELETE FROM goal_evaluations", [])
+        .map_err(|e| DotaKeeperError::db("Failed to delete goal evaluations", e))?;
     conn.execute("DELETE FROM matches", [])
-        .map_err(|e| format!("Failed to delete matches: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to delete matches", e))?;
+    // The history import starts over from the newest match
+    conn.execute("DELETE FROM app_metadata WHERE key = 'backfill_state'", [])
+        .map_err(|e| DotaKeeperError::db("Failed to reset backfill state", e))?;
 
     Ok(())
 }
 
 /// Delete all data from every table (factory reset).
 /// Settings are managed separately; this only clears the SQLite database.
-pub fn factory_reset_db(conn: &Connection) -> Result<(), String> {
+pub fn factory_reset_db(conn: &Connection) -> Result<(), DotaKeeperError> {
     // Order matters: child tables (FK → matches) must be cleared before matches itself.
     let tables = [
         "match_cs",
         "goal_progress",
         "item_timings",
+        "match_events",
+        "parse_queue",
+        "match_player_items",
+        "match_player_timelines",
+        "match_players",
         "player_networth",
         "mood_checkins",
+        "gsi_recordings",
+        "goal_evaluations",
         "matches",
         "goals",
         "hero_favorites",
@@ -847,21 +1797,26 @@ pub fn factory_reset_db(conn: &Connection) -> Result<(), String> {
     ];
     for table in &tables {
         conn.execute(&format!("DELETE FROM {}", table), [])
-            .map_err(|e| format!("Failed to clear table {}: {}", table, e))?;
+            .map_err(|e| DotaKeeperError::db(format!("Failed to clear table {}", table), e))?;
     }
+    conn.execute("DELETE FROM app_metadata WHERE key = 'backfill_state'", [])
+        .map_err(|e| DotaKeeperError::db("Failed to reset backfill state", e))?;
+    // Release the file so the next profile to log in can claim it.
+    conn.execute("DELETE FROM app_metadata WHERE key = 'account_id'", [])
+        .map_err(|e| DotaKeeperError::db("Failed to clear database account", e))?;
     Ok(())
 }
 
 /// Get all matches from the database, ordered by start_time descending
-pub fn get_all_matches(conn: &Connection) -> Result<Vec<Match>, String> {
+pub fn get_all_matches(conn: &Connection) -> Result<Vec<Match>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT match_id, hero_id, start_time, duration, game_mode, lobby_type,
                     radiant_win, player_slot, kills, deaths, assists, xp_per_min,
-                    gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch
+                    gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch, parsed_by
              FROM matches ORDER BY start_time DESC",
         )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let matches = stmt
         .query_map([], |row| {
@@ -889,28 +1844,43 @@ pub fn get_all_matches(conn: &Connection) -> Result<Vec<Match>, String> {
                 role: row.get(19).unwrap_or(0),
                 rank_tier: row.get(20).ok(),
                 patch: row.get(21).ok(),
+                parsed_by: row.get(22).ok(),
             })
         })
-        .map_err(|e| format!("Failed to query matches: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query matches", e))?;
 
     let mut result = Vec::new();
     for m in matches {
-        result.push(m.map_err(|e| format!("Failed to read match: {}", e))?);
+        result.push(m.map_err(|e| DotaKeeperError::db("Failed to read match", e))?);
     }
 
     Ok(result)
 }
 
+/// Get a single match by ID
+pub fn get_match_by_id(conn: &Connection, match_id: i64) -> Result<Option<Match>, DotaKeeperError> {
+    conn.query_row(
+        "SELECT match_id, hero_id, start_time, duration, game_mode, lobby_type,
+                radiant_win, player_slot, kills, deaths, assists, xp_per_min,
+                gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch, parsed_by
+         FROM matches WHERE match_id = ?1",
+        params![match_id],
+        row_to_match,
+    )
+    .optional()
+    .map_err(|e| DotaKeeperError::db("Failed to get match", e))
+}
+
 /// Insert a new goal into the database
-pub fn insert_goal(conn: &Connection, goal: &NewGoal) -> Result<Goal, String> {
+pub fn insert_goal(conn: &Connection, goal: &NewGoal) -> Result<Goal, DotaKeeperError> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
-        .map_err(|e| format!("Failed to get current time: {}", e))?
+        .map_err(|e| DotaKeeperError::Database(format!("Failed to get current time: {}", e)))?
         .as_secs() as i64;
 
     conn.execute(
-        "INSERT INTO goals (hero_id, metric, target_value, target_time_minutes, game_mode, item_id, created_at, hero_scope, frequency_type)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        "INSERT INTO goals (hero_id, metric, target_value, target_time_minutes, game_mode, item_id, created_at, hero_scope, frequency_type, conditions, combinator, scope, state, state_changed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 'active', ?7)",
         params![
             goal.hero_id,
             goal.metric.to_string(),
@@ -921,8 +1891,11 @@ pub fn insert_goal(conn: &Connection, goal: &NewGoal) -> Result<Goal, String> {
             now,
             goal.hero_scope,
             goal.frequency_type.to_string(),
+            conditions_to_json(&goal.conditions)?,
+            goal.combinator.to_string(),
+            scope_to_json(&goal.scope)?,
         ],
-    ).map_err(|e| format!("Failed to insert goal: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to insert goal", e))?;
 
     let id = conn.last_insert_rowid();
 
@@ -937,51 +1910,105 @@ pub fn insert_goal(conn: &Connection, goal: &NewGoal) -> Result<Goal, String> {
         game_mode: goal.game_mode.clone(),
         frequency_type: goal.frequency_type.clone(),
         created_at: now,
+        conditions: goal.conditions.clone(),
+        combinator: goal.combinator,
+        scope: goal.scope.clone(),
+        state: GoalState::Active,
+        state_changed_at: Some(now),
+        achieved_at: None,
     })
 }
 
-/// Get all goals from the database
-pub fn get_all_goals(conn: &Connection) -> Result<Vec<Goal>, String> {
+/// Column list read by `goal_from_row`
+const GOAL_COLUMNS: &str =
+    "id, hero_id, metric, target_value, target_time_minutes, game_mode, created_at, item_id, hero_scope, frequency_type, conditions, combinator,
+     state, state_changed_at, achieved_at, scope";
+
+fn goal_from_row(row: &rusqlite::Row) -> rusqlite::Result<Goal> {
+    let metric_str: String = row.get(2)?;
+    let game_mode_str: String = row.get(5)?;
+    let freq_str: String = row.get(9).unwrap_or_else(|_| "pct_75".to_string());
+    let conditions_json: Option<String> = row.get(10).unwrap_or(None);
+    let combinator_str: String = row.get(11).unwrap_or_else(|_| "and".to_string());
+    let state_str: String = row.get(12).unwrap_or_else(|_| "active".to_string());
+    let scope_json: Option<String> = row.get(15).unwrap_or(None);
+    Ok(Goal {
+        id: row.get(0)?,
+        hero_id: row.get(1)?,
+        metric: GoalMetric::from_string(&metric_str).unwrap_or(GoalMetric::Networth),
+        target_value: row.get(3)?,
+        target_time_minutes: row.get(4)?,
+        game_mode: GoalGameMode::from_string(&game_mode_str).unwrap_or(GoalGameMode::All),
+        created_at: row.get(6)?,
+        item_id: row.get(7)?,
+        hero_scope: row.get(8).unwrap_or(None),
+        frequency_type: FrequencyType::from_string(&freq_str),
+        conditions: conditions_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        combinator: GoalCombinator::from_string(&combinator_str),
+        scope: scope_json
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default(),
+        state: GoalState::from_string(&state_str),
+        state_changed_at: row.get(13).unwrap_or(None),
+        achieved_at: row.get(14).unwrap_or(None),
+    })
+}
+
+/// Compound-goal conditions as stored in the `conditions` column (NULL when there are none)
+fn conditions_to_json(conditions: &[GoalCondition]) -> Result<Option<String>, DotaKeeperError> {
+    if conditions.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(conditions)
+        .map(Some)
+        .map_err(|e| DotaKeeperError::Database(format!("Failed to serialize goal conditions: {}", e)))
+}
+
+/// Goal scope as stored in the `scope` column (NULL when it counts every match)
+fn scope_to_json(scope: &GoalScope) -> Result<Option<String>, DotaKeeperError> {
+    if scope.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(scope)
+        .map(Some)
+        .map_err(|e| DotaKeeperError::Database(format!("Failed to serialize goal scope: {}", e)))
+}
+
+/// Get all goals from the database, whatever their state
+pub fn get_all_goals(conn: &Connection) -> Result<Vec<Goal>, DotaKeeperError> {
+    query_goals(conn, "1 = 1")
+}
+
+/// Get the goals that are evaluated against matches (not paused or retired)
+pub fn get_active_goals(conn: &Connection) -> Result<Vec<Goal>, DotaKeeperError> {
+    query_goals(conn, "state = 'active'")
+}
+
+fn query_goals(conn: &Connection, filter: &str) -> Result<Vec<Goal>, DotaKeeperError> {
     let mut stmt = conn
-        .prepare(
-            "SELECT id, hero_id, metric, target_value, target_time_minutes, game_mode, created_at, item_id, hero_scope, frequency_type
-             FROM goals ORDER BY created_at DESC",
-        )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .prepare(&format!("SELECT {} FROM goals WHERE {} ORDER BY created_at DESC", GOAL_COLUMNS, filter))
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let goals = stmt
-        .query_map([], |row| {
-            let metric_str: String = row.get(2)?;
-            let game_mode_str: String = row.get(5)?;
-            let freq_str: String = row.get(9).unwrap_or_else(|_| "pct_75".to_string());
-            Ok(Goal {
-                id: row.get(0)?,
-                hero_id: row.get(1)?,
-                metric: GoalMetric::from_string(&metric_str).unwrap_or(GoalMetric::Networth),
-                target_value: row.get(3)?,
-                target_time_minutes: row.get(4)?,
-                game_mode: GoalGameMode::from_string(&game_mode_str).unwrap_or(GoalGameMode::All),
-                created_at: row.get(6)?,
-                item_id: row.get(7)?,
-                hero_scope: row.get(8).unwrap_or(None),
-                frequency_type: FrequencyType::from_string(&freq_str),
-            })
-        })
-        .map_err(|e| format!("Failed to query goals: {}", e))?;
+        .query_map([], goal_from_row)
+        .map_err(|e| DotaKeeperError::db("Failed to query goals", e))?;
 
     let mut result = Vec::new();
     for g in goals {
-        result.push(g.map_err(|e| format!("Failed to read goal: {}", e))?);
+        result.push(g.map_err(|e| DotaKeeperError::db("Failed to read goal", e))?);
     }
 
     Ok(result)
 }
 
 /// Update an existing goal
-pub fn update_goal(conn: &Connection, goal: &Goal) -> Result<(), String> {
+pub fn update_goal(conn: &Connection, goal: &Goal) -> Result<(), DotaKeeperError> {
     conn.execute(
         "UPDATE goals SET hero_id = ?1, metric = ?2, target_value = ?3,
-         target_time_minutes = ?4, game_mode = ?5, item_id = ?6, hero_scope = ?7, frequency_type = ?8 WHERE id = ?9",
+         target_time_minutes = ?4, game_mode = ?5, item_id = ?6, hero_scope = ?7, frequency_type = ?8,
+         conditions = ?9, combinator = ?10, scope = ?11 WHERE id = ?12",
         params![
             goal.hero_id,
             goal.metric.to_string(),
@@ -991,39 +2018,46 @@ pub fn update_goal(conn: &Connection, goal: &Goal) -> Result<(), String> {
             goal.item_id,
             goal.hero_scope,
             goal.frequency_type.to_string(),
+            conditions_to_json(&goal.conditions)?,
+            goal.combinator.to_string(),
+            scope_to_json(&goal.scope)?,
             goal.id,
         ],
-    ).map_err(|e| format!("Failed to update goal: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to update goal", e))?;
 
+    conn.execute("DELETE FROM goal_evaluations WHERE goal_id = ?1", params![goal.id])
+        .map_err(|e| DotaKeeperError::db("Failed to clear goal evaluations", e))?;
     Ok(())
 }
 
 /// Delete a goal by ID
-pub fn delete_goal(conn: &Connection, goal_id: i64) -> Result<(), String> {
+pub fn delete_goal(conn: &Connection, goal_id: i64) -> Result<(), DotaKeeperError> {
     conn.execute(
         "DELETE FROM goals WHERE id = ?1",
         params![goal_id],
-    ).map_err(|e| format!("Failed to delete goal: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to delete goal", e))?;
+    conn.execute("DELETE FROM goal_evaluations WHERE goal_id = ?1", params![goal_id])
+        .map_err(|e| DotaKeeperError::db("Failed to clear goal evaluations", e))?;
 
     Ok(())
 }
 
 /// Insert or replace patch data in the patches cache table
-pub fn upsert_patches(conn: &Connection, patches: &[PatchInfo]) -> Result<(), String> {
+pub fn upsert_patches(conn: &Connection, patches: &[PatchInfo]) -> Result<(), DotaKeeperError> {
     for p in patches {
         conn.execute(
             "INSERT OR REPLACE INTO patches (name, date_epoch) VALUES (?1, ?2)",
             params![p.name, p.date_epoch],
-        ).map_err(|e| format!("Failed to upsert patch {}: {}", p.name, e))?;
+        ).map_err(|e| DotaKeeperError::db(format!("Failed to upsert patch {}", p.name), e))?;
     }
     Ok(())
 }
 
 /// Get all cached patches ordered oldest first
-pub fn get_all_patches(conn: &Connection) -> Result<Vec<PatchInfo>, String> {
+pub fn get_all_patches(conn: &Connection) -> Result<Vec<PatchInfo>, DotaKeeperError> {
     let mut stmt = conn
         .prepare("SELECT name, date_epoch FROM patches ORDER BY date_epoch ASC")
-        .map_err(|e| format!("Failed to prepare patches query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare patches query", e))?;
 
     let patches = stmt
         .query_map([], |row| {
@@ -1032,9 +2066,9 @@ pub fn get_all_patches(conn: &Connection) -> Result<Vec<PatchInfo>, String> {
                 date_epoch: row.get(1)?,
             })
         })
-        .map_err(|e| format!("Failed to query patches: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query patches", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to read patch: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to read patch", e))?;
 
     Ok(patches)
 }
@@ -1050,25 +2084,27 @@ pub fn get_patch_for_timestamp(conn: &Connection, timestamp: i64) -> Option<Stri
 }
 
 /// Set the patch field on a match
-pub fn update_match_patch(conn: &Connection, match_id: i64, patch: &str) -> Result<(), String> {
+pub fn update_match_patch(conn: &Connection, match_id: i64, patch: &str) -> Result<(), DotaKeeperError> {
     conn.execute(
         "UPDATE matches SET patch = ?1 WHERE match_id = ?2",
         params![patch, match_id],
-    ).map_err(|e| format!("Failed to update match patch: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to update match patch", e))?;
+    // Patch-scoped goals may now apply
+    clear_match_goal_evaluations(conn, match_id)?;
     Ok(())
 }
 
 /// Back-fill patch data for all matches that have NULL patch.
 /// Returns the number of matches updated.
-pub fn backfill_match_patches(conn: &Connection) -> Result<usize, String> {
+pub fn backfill_match_patches(conn: &Connection) -> Result<usize, DotaKeeperError> {
     let match_ids: Vec<(i64, i64)> = {
         let mut stmt = conn
             .prepare("SELECT match_id, start_time FROM matches WHERE patch IS NULL")
-            .map_err(|e| format!("Failed to prepare backfill query: {}", e))?;
+            .map_err(|e| DotaKeeperError::db("Failed to prepare backfill query", e))?;
         let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
-            .map_err(|e| format!("Failed to query matches for backfill: {}", e))?
+            .map_err(|e| DotaKeeperError::db("Failed to query matches for backfill", e))?
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| format!("Failed to read match for backfill: {}", e))?;
+            .map_err(|e| DotaKeeperError::db("Failed to read match for backfill", e))?;
         rows
     };
 
@@ -1087,7 +2123,20 @@ pub fn backfill_match_patches(conn: &Connection) -> Result<usize, String> {
 pub struct GoalEvaluation {
     pub goal: Goal,
     pub achieved: bool,
+    /// Value of the goal's own metric (the first condition of a compound goal)
     pub actual_value: i32,
+    /// Per-condition results of a compound goal (empty for single-metric goals)
+    #[serde(default)]
+    pub conditions: Vec<ConditionEvaluation>,
+}
+
+/// Result of one condition of a compound goal
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConditionEvaluation {
+    pub condition: GoalCondition,
+    pub achieved: bool,
+    /// `None` when the match lacks the data for this condition
+    pub actual_value: Option<i32>,
 }
 
 /// Match with goal evaluation summary
@@ -1099,14 +2148,8 @@ pub struct MatchWithGoals {
     pub goals_applicable: i32,
 }
 
-/// Evaluate a single goal against a match
-pub fn evaluate_goal(conn: &Connection, goal: &Goal, match_data: &Match) -> Option<GoalEvaluation> {
-    // Skip goal evaluation for unparsed matches - data might be incomplete
-    if match_data.parse_state == MatchState::Unparsed {
-        return None;
-    }
-
-    // Check if goal applies to this match based on hero / hero scope
+/// Whether a match counts towards a goal: its hero / hero scope, game mode and scope
+fn goal_applies_to_match(goal: &Goal, match_data: &Match) -> bool {
     let hero_matches = match goal.hero_scope.as_deref() {
         Some("any_carry")  => match_data.role == 1,
         Some("any_core")   => matches!(match_data.role, 1 | 2 | 3),
@@ -1116,48 +2159,75 @@ pub fn evaluate_goal(conn: &Connection, goal: &Goal, match_data: &Match) -> Opti
             None => true, // any hero
         },
     };
-    if !hero_matches {
-        return None;
-    }
 
-    // Check if goal applies based on game mode
     // Game mode values: https://github.com/odota/dotaconstants/blob/master/build/game_mode.json
     // 22 = All Pick Ranked, 23 = Turbo
-    let is_ranked = match_data.game_mode == 22;
-    let is_turbo = match_data.game_mode == 23;
-
     let mode_matches = match goal.game_mode {
-        GoalGameMode::Ranked => is_ranked,
-        GoalGameMode::Turbo => is_turbo,
+        GoalGameMode::Ranked => match_data.game_mode == 22,
+        GoalGameMode::Turbo => match_data.game_mode == 23,
         GoalGameMode::All => true,
     };
 
-    if !mode_matches {
-        return None; // Goal doesn't apply to this game mode
+    hero_matches && mode_matches && goal.scope.includes(match_data, goal.created_at)
+}
+
+/// Evaluate a single goal against a match
+pub fn evaluate_goal(conn: &Connection, goal: &Goal, match_data: &Match) -> Option<GoalEvaluation> {
+    // Skip goal evaluation for unparsed matches - data might be incomplete
+    if match_data.parse_state == MatchState::Unparsed {
+        return None;
     }
 
-    // Calculate actual value at target time
-    let duration_minutes = match_data.duration / 60;
-    let target_minutes = goal.target_time_minutes;
+    if !goal_applies_to_match(goal, match_data) {
+        return None;
+    }
 
-    let actual_value = match &goal.metric {
-        GoalMetric::Kills => {
-            // For kills, we assume linear progression
-            if duration_minutes <= target_minutes {
-                match_data.kills
-            } else {
-                // Estimate kills at target time
-                ((match_data.kills as f32 / duration_minutes as f32) * target_minutes as f32) as i32
-            }
+    let primary = goal.primary_condition();
+    if goal.conditions.is_empty() {
+        let (actual_value, achieved) = evaluate_condition(conn, &primary, match_data)?;
+        return Some(GoalEvaluation { goal: goal.clone(), achieved, actual_value, conditions: Vec::new() });
+    }
+
+    // Compound goal: the goal's own metric must be measurable for the match to count
+    let mut conditions: Vec<ConditionEvaluation> = Vec::new();
+    for condition in std::iter::once(primary).chain(goal.conditions.iter().cloned()) {
+        let result = evaluate_condition(conn, &condition, match_data);
+        if result.is_none() && (conditions.is_empty() || goal.combinator == GoalCombinator::And) {
+            return None;
         }
-        GoalMetric::Deaths => {
-            // For deaths, assume linear progression (same as kills)
-            if duration_minutes <= target_minutes {
-                match_data.deaths
-            } else {
-                // Estimate deaths at target time
-                ((match_data.deaths as f32 / duration_minutes as f32) * target_minutes as f32) as i32
-            }
+        conditions.push(ConditionEvaluation {
+            condition,
+            achieved: result.is_some_and(|(_, achieved)| achieved),
+            actual_value: result.map(|(value, _)| value),
+        });
+    }
+    let achieved = match goal.combinator {
+        GoalCombinator::And => conditions.iter().all(|c| c.achieved),
+        // A condition that can't be measured (e.g. the item was never bought) isn't met
+        GoalCombinator::Or => conditions.iter().any(|c| c.achieved),
+    };
+
+    let actual_value = conditions[0].actual_value?;
+    Some(GoalEvaluation { goal: goal.clone(), achieved, actual_value, conditions })
+}
+
+/// Measure one condition against a match: `(actual value, achieved)`, or `None` when the
+/// match lacks the data to judge it.
+fn evaluate_condition(conn: &Connection, condition: &GoalCondition, match_data: &Match) -> Option<(i32, bool)> {
+    // Calculate actual value at target time
+    let target_minutes = condition.target_time_minutes;
+
+    let actual_value = match &condition.metric {
+        GoalMetric::Kills | GoalMetric::Deaths => {
+            // Count the real kill/death events up to the target minute
+            let (total, event_type) = match condition.metric {
+                GoalMetric::Kills => (match_data.kills, MatchEventType::Kill),
+                _ => (match_data.deaths, MatchEventType::Death),
+            };
+            let timeline = get_match_events_at_minute(conn, match_data.match_id, &event_type, target_minutes)
+                .ok()
+                .flatten();
+            events_at_minute(total, match_data.duration, target_minutes, timeline)?
         }
         GoalMetric::LastHits => {
             // ONLY use exact per-minute CS data from OpenDota - never estimate
@@ -1190,12 +2260,27 @@ pub fn evaluate_goal(conn: &Connection, goal: &Goal, match_data: &Match) -> Opti
                 _ => return None,
             }
         }
+        GoalMetric::LaneNetworthLead => {
+            let players: Vec<(i32, i32)> = get_match_players(conn, match_data.match_id)
+                .ok()?
+                .iter()
+                .map(|p| (p.player_slot, p.lane_role))
+                .collect();
+            let (allies, enemies) = lane_sides(match_data.player_slot, match_data.role, &players)?;
+            let total = |slots: &[i32]| -> Option<i32> {
+                slots
+                    .iter()
+                    .map(|&slot| get_partner_networth_at_minute(conn, match_data.match_id, slot, target_minutes).ok().flatten())
+                    .sum()
+            };
+            total(&allies)? - total(&enemies)?
+        }
         GoalMetric::ItemTiming => {
             // For item timing goals, check when the item was purchased
-            // goal.target_value contains the target time in seconds
-            // goal.item_id contains the item ID to check
+            // condition.target_value contains the target time in seconds
+            // condition.item_id contains the item ID to check
 
-            let item_id = goal.item_id?; // If no item_id, can't evaluate
+            let item_id = condition.item_id?; // If no item_id, can't evaluate
 
             // Get the actual purchase time for this item in this match
             match get_item_timing(conn, match_data.match_id, item_id) {
@@ -1220,53 +2305,45 @@ pub fn evaluate_goal(conn: &Connection, goal: &Goal, match_data: &Match) -> Opti
             }
         }
         GoalMetric::Level => {
-            // Level requires per-minute data which is not currently stored
-            return None;
+            // Derive the level from the exact cumulative XP at the target minute
+            match get_match_xp_at_minute(conn, match_data.match_id, target_minutes) {
+                Ok(Some(xp)) => xp_to_level(xp),
+                _ => return None,
+            }
         }
     };
 
-    let achieved = match &goal.metric {
+    let achieved = match &condition.metric {
         GoalMetric::ItemTiming | GoalMetric::Deaths => {
             // Lower is better: achieved if actual <= target
-            actual_value <= goal.target_value
+            actual_value <= condition.target_value
         }
         _ => {
             // For other metrics, achieved if actual >= target
-            actual_value >= goal.target_value
+            actual_value >= condition.target_value
         }
     };
 
-    Some(GoalEvaluation {
-        goal: goal.clone(),
-        achieved,
-        actual_value,
-    })
+    Some((actual_value, achieved))
 }
 
 /// Evaluate all goals against a match
-pub fn evaluate_match_goals(conn: &Connection, match_data: &Match) -> Result<Vec<GoalEvaluation>, String> {
-    let goals = get_all_goals(conn)?;
-
-    let evaluations: Vec<GoalEvaluation> = goals
-        .iter()
-        .filter_map(|goal| evaluate_goal(conn, goal, match_data))
-        .collect();
-
-    Ok(evaluations)
+pub fn evaluate_match_goals(conn: &Connection, match_data: &Match) -> Result<Vec<GoalEvaluation>, DotaKeeperError> {
+    let goals = get_active_goals(conn)?;
+    let mut evaluations = cached_goal_evaluations(conn, &goals, std::slice::from_ref(match_data))?;
+    Ok(evaluations.remove(&match_data.match_id).unwrap_or_default())
 }
 
 /// Get all matches with goal evaluation summaries
-pub fn get_matches_with_goals(conn: &Connection) -> Result<Vec<MatchWithGoals>, String> {
+pub fn get_matches_with_goals(conn: &Connection) -> Result<Vec<MatchWithGoals>, DotaKeeperError> {
     let matches = get_all_matches(conn)?;
-    let goals = get_all_goals(conn)?;
+    let goals = get_active_goals(conn)?;
+    let mut evaluations_by_match = cached_goal_evaluations(conn, &goals, &matches)?;
 
     let matches_with_goals: Vec<MatchWithGoals> = matches
         .into_iter()
         .map(|match_data| {
-            let evaluations: Vec<GoalEvaluation> = goals
-                .iter()
-                .filter_map(|goal| evaluate_goal(conn, goal, &match_data))
-                .collect();
+            let evaluations = evaluations_by_match.remove(&match_data.match_id).unwrap_or_default();
 
             let goals_applicable = evaluations.len() as i32;
             let goals_achieved = evaluations.iter().filter(|e| e.achieved).count() as i32;
@@ -1282,6 +2359,443 @@ pub fn get_matches_with_goals(conn: &Connection) -> Result<Vec<MatchWithGoals>,
     Ok(matches_with_goals)
 }
 
+/// Column `query_matches` sorts by; ties are broken by match ID.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum MatchSortField {
+    #[default]
+    StartTime,
+    Duration,
+    Kills,
+    Deaths,
+    Assists,
+    LastHits,
+    GoldPerMin,
+    XpPerMin,
+    HeroDamage,
+}
+
+impl MatchSortField {
+    fn column(self) -> &'static str {
+        match self {
+            MatchSortField::StartTime => "start_time",
+            MatchSortField::Duration => "duration",
+            MatchSortField::Kills => "kills",
+            MatchSortField::Deaths => "deaths",
+            MatchSortField::Assists => "assists",
+            MatchSortField::LastHits => "last_hits",
+            MatchSortField::GoldPerMin => "gold_per_min",
+            MatchSortField::XpPerMin => "xp_per_min",
+            MatchSortField::HeroDamage => "hero_damage",
+        }
+    }
+}
+
+/// Keep only matches where a goal was achieved (or failed). Without `goal_id`, any active
+/// goal with that outcome counts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GoalOutcomeFilter {
+    #[serde(default)]
+    pub goal_id: Option<i64>,
+    pub achieved: bool,
+}
+
+/// Filters, sort order and page position for `query_matches`. Every filter is optional.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MatchQuery {
+    #[serde(default)]
+    pub hero_id: Option<i32>,
+    #[serde(default)]
+    pub role: Option<i32>,
+    /// Any of these game modes; empty for all
+    #[serde(default)]
+    pub game_modes: Vec<i32>,
+    #[serde(default)]
+    pub lobby_type: Option<i32>,
+    /// A patch name; a major patch such as "7.40" also matches its lettered sub-patches
+    #[serde(default)]
+    pub patch: Option<String>,
+    #[serde(default)]
+    pub won: Option<bool>,
+    /// Only matches that started at or after this Unix timestamp
+    #[serde(default)]
+    pub start_after: Option<i64>,
+    /// Only matches that started before this Unix timestamp
+    #[serde(default)]
+    pub start_before: Option<i64>,
+    #[serde(default)]
+    pub parse_state: Option<MatchState>,
+    #[serde(default)]
+    pub goal_outcome: Option<GoalOutcomeFilter>,
+    #[serde(default)]
+    pub sort: MatchSortField,
+    /// Newest / highest first unless set
+    #[serde(default)]
+    pub ascending: bool,
+    /// `next_cursor` of the previous page; omitted for the first page
+    #[serde(default)]
+    pub cursor: Option<String>,
+    /// Page size (default 50, at most 500)
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+/// One page of `query_matches`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchPage {
+    pub matches: Vec<MatchWithGoals>,
+    /// Pass back as `MatchQuery::cursor` for the next page; `None` on the last page
+    pub next_cursor: Option<String>,
+    /// Number of matches passing the filters, over all pages
+    pub total: i64,
+}
+
+const DEFAULT_MATCH_PAGE_SIZE: u32 = 50;
+const MAX_MATCH_PAGE_SIZE: u32 = 500;
+
+/// Matches passing `query`'s filters, one page at a time. Filtering, sorting and paging run
+/// in SQL (keyset pagination on the sort column and match ID); goal summaries are only
+/// computed for the returned page.
+pub fn query_matches(conn: &Connection, query: &MatchQuery) -> Result<MatchPage, DotaKeeperError> {
+    let goals = get_active_goals(conn)?;
+    if let Some(outcome) = &query.goal_outcome {
+        // The goal filter reads the evaluation cache, so make sure it's complete
+        let filtered: Vec<Goal> = goals.iter().filter(|g| outcome.goal_id.is_none_or(|id| g.id == id)).cloned().collect();
+        fill_goal_evaluation_cache(conn, &filtered)?;
+    }
+
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    let mut push = |condition: &str, value: Box<dyn rusqlite::ToSql>| {
+        params_vec.push(value);
+        conditions.push(condition.replace('?', &format!("?{}", params_vec.len())));
+    };
+    if let Some(hero_id) = query.hero_id {
+        push("m.hero_id = ?", Box::new(hero_id));
+    }
+    if let Some(role) = query.role {
+        push("m.role = ?", Box::new(role));
+    }
+    if let Some(lobby_type) = query.lobby_type {
+        push("m.lobby_type = ?", Box::new(lobby_type));
+    }
+    if let Some(patch) = &query.patch {
+        push("(m.patch = ? OR m.patch GLOB ? || '[a-z]')", Box::new(patch.clone()));
+    }
+    if let Some(won) = query.won {
+        push("((m.player_slot < 128) = (m.radiant_win != 0)) = ?", Box::new(won));
+    }
+    if let Some(start_after) = query.start_after {
+        push("m.start_time >= ?", Box::new(start_after));
+    }
+    if let Some(start_before) = query.start_before {
+        push("m.start_time < ?", Box::new(start_before));
+    }
+    if let Some(state) = &query.parse_state {
+        push("m.parse_state = ?", Box::new(state.to_string()));
+    }
+    if let Some(outcome) = &query.goal_outcome {
+        let active_goal = "ge.goal_id IN (SELECT id FROM goals WHERE state = 'active')";
+        match outcome.goal_id {
+            Some(goal_id) => push(
+                &format!("EXISTS (SELECT 1 FROM goal_evaluations ge WHERE ge.match_id = m.match_id AND ge.applicable = 1
+                          AND ge.achieved = {} AND {} AND ge.goal_id = ?)", outcome.achieved as i32, active_goal),
+                Box::new(goal_id),
+            ),
+            None => conditions.push(format!(
+                "EXISTS (SELECT 1 FROM goal_evaluations ge WHERE ge.match_id = m.match_id AND ge.applicable = 1
+                 AND ge.achieved = {} AND {})", outcome.achieved as i32, active_goal
+            )),
+        }
+        // Goals are never evaluated on unparsed matches
+        conditions.push("m.parse_state != 'unparsed'".to_string());
+    }
+    if !query.game_modes.is_empty() {
+        let modes = query.game_modes.iter().map(|mode| mode.to_string()).collect::<Vec<_>>().join(", ");
+        conditions.push(format!("m.game_mode IN ({})", modes));
+    }
+
+    let filter_sql = if conditions.is_empty() { "1 = 1".to_string() } else { conditions.join(" AND ") };
+    let total: i64 = {
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        conn.query_row(&format!("SELECT COUNT(*) FROM matches m WHERE {}", filter_sql), &params_refs[..], |row| row.get(0))
+            .map_err(|e| DotaKeeperError::db("Failed to count matches", e))?
+    };
+
+    let column = query.sort.column();
+    let (order, compare) = if query.ascending { ("ASC", ">") } else { ("DESC", "<") };
+    let mut page_sql = filter_sql;
+    if let Some(cursor) = &query.cursor {
+        let (value, match_id) = parse_match_cursor(cursor)?;
+        params_vec.push(Box::new(value));
+        params_vec.push(Box::new(match_id));
+        page_sql.push_str(&format!(
+            " AND (m.{c}, m.match_id) {cmp} (?{v}, ?{id})",
+            c = column, cmp = compare, v = params_vec.len() - 1, id = params_vec.len()
+        ));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_MATCH_PAGE_SIZE).clamp(1, MAX_MATCH_PAGE_SIZE);
+    // One extra row tells whether there's another page
+    params_vec.push(Box::new(limit + 1));
+
+    let matches: Vec<Match> = {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT m.match_id, m.hero_id, m.start_time, m.duration, m.game_mode, m.lobby_type,
+                        m.radiant_win, m.player_slot, m.kills, m.deaths, m.assists, m.xp_per_min,
+                        m.gold_per_min, m.last_hits, m.denies, m.hero_damage, m.tower_damage, m.hero_healing,
+                        m.parse_state, m.role, m.rank_tier, m.patch, m.parsed_by
+                 FROM matches m WHERE {w} ORDER BY m.{c} {o}, m.match_id {o} LIMIT ?{l}",
+                w = page_sql, c = column, o = order, l = params_vec.len()
+            ))
+            .map_err(|e| DotaKeeperError::db("Failed to prepare match query", e))?;
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+        let rows = stmt
+            .query_map(&params_refs[..], row_to_match)
+            .map_err(|e| DotaKeeperError::db("Failed to query matches", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| DotaKeeperError::db("Failed to read match", e))?
+    };
+
+    let has_more = matches.len() > limit as usize;
+    let matches: Vec<Match> = matches.into_iter().take(limit as usize).collect();
+    let next_cursor = if has_more {
+        matches.last().map(|m| format!("{}:{}", match_sort_value(m, query.sort), m.match_id))
+    } else {
+        None
+    };
+
+    let mut evaluations_by_match = cached_goal_evaluations(conn, &goals, &matches)?;
+    let matches = matches
+        .into_iter()
+        .map(|match_data| {
+            let evaluations = evaluations_by_match.remove(&match_data.match_id).unwrap_or_default();
+            MatchWithGoals {
+                goals_applicable: evaluations.len() as i32,
+                goals_achieved: evaluations.iter().filter(|e| e.achieved).count() as i32,
+                match_data,
+            }
+        })
+        .collect();
+
+    Ok(MatchPage { matches, next_cursor, total })
+}
+
+fn match_sort_value(m: &Match, sort: MatchSortField) -> i64 {
+    match sort {
+        MatchSortField::StartTime => m.start_time,
+        MatchSortField::Duration => m.duration as i64,
+        MatchSortField::Kills => m.kills as i64,
+        MatchSortField::Deaths => m.deaths as i64,
+        MatchSortField::Assists => m.assists as i64,
+        MatchSortField::LastHits => m.last_hits as i64,
+        MatchSortField::GoldPerMin => m.gold_per_min as i64,
+        MatchSortField::XpPerMin => m.xp_per_min as i64,
+        MatchSortField::HeroDamage => m.hero_damage as i64,
+    }
+}
+
+/// A `MatchPage::next_cursor`: "<sort value>:<match id>"
+fn parse_match_cursor(cursor: &str) -> Result<(i64, i64), DotaKeeperError> {
+    cursor
+        .split_once(':')
+        .and_then(|(value, match_id)| Some((value.parse().ok()?, match_id.parse().ok()?)))
+        .ok_or_else(|| DotaKeeperError::InvalidInput(format!("Invalid match cursor: {}", cursor)))
+}
+
+/// Values the match list can be filtered by, taken from every stored match.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MatchFilterOptions {
+    /// Heroes played, most recently played first
+    pub hero_ids: Vec<i32>,
+    /// Patches played on, most recent first
+    pub patches: Vec<String>,
+}
+
+pub fn get_match_filter_options(conn: &Connection) -> Result<MatchFilterOptions, DotaKeeperError> {
+    let mut stmt = conn
+        .prepare("SELECT hero_id FROM matches GROUP BY hero_id ORDER BY MAX(start_time) DESC")
+        .map_err(|e| DotaKeeperError::db("Failed to prepare hero query", e))?;
+    let hero_ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| DotaKeeperError::db("Failed to query heroes", e))?
+        .collect::<Result<Vec<i32>, _>>()
+        .map_err(|e| DotaKeeperError::db("Failed to read hero", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT patch FROM matches WHERE patch IS NOT NULL GROUP BY patch ORDER BY MAX(start_time) DESC")
+        .map_err(|e| DotaKeeperError::db("Failed to prepare patch query", e))?;
+    let patches = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| DotaKeeperError::db("Failed to query patches", e))?
+        .collect::<Result<Vec<String>, _>>()
+        .map_err(|e| DotaKeeperError::db("Failed to read patch", e))?;
+
+    Ok(MatchFilterOptions { hero_ids, patches })
+}
+
+/// Evaluate and cache `goals` against every parsed match that has no cached result for them yet.
+fn fill_goal_evaluation_cache(conn: &Connection, goals: &[Goal]) -> Result<(), DotaKeeperError> {
+    for goal in goals {
+        let missing: Vec<Match> = {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT match_id, hero_id, start_time, duration, game_mode, lobby_type,
+                            radiant_win, player_slot, kills, deaths, assists, xp_per_min,
+                            gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch, parsed_by
+                     FROM matches
+                     WHERE parse_state != 'unparsed'
+                       AND match_id NOT IN (SELECT match_id FROM goal_evaluations WHERE goal_id = ?1)",
+                )
+                .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
+            let rows = stmt
+                .query_map(params![goal.id], row_to_match)
+                .map_err(|e| DotaKeeperError::db("Failed to query matches", e))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| DotaKeeperError::db("Failed to read match", e))?
+        };
+        if !missing.is_empty() {
+            cached_goal_evaluations(conn, std::slice::from_ref(goal), &missing)?;
+        }
+    }
+    Ok(())
+}
+
+/// Drop the cached goal evaluations of a match so they're recomputed on next read
+pub fn clear_match_goal_evaluations(conn: &Connection, match_id: i64) -> Result<(), DotaKeeperError> {
+    conn.execute("DELETE FROM goal_evaluations WHERE match_id = ?1", params![match_id])
+        .map_err(|e| DotaKeeperError::db("Failed to clear goal evaluations", e))?;
+    Ok(())
+}
+
+/// Evaluate the active goals against a freshly parsed match and cache the results, so the
+/// match list doesn't have to on its next load.
+pub fn cache_match_goal_evaluations(conn: &Connection, match_id: i64) -> Result<(), DotaKeeperError> {
+    let Some(match_data) = get_match_by_id(conn, match_id)? else {
+        return Ok(());
+    };
+    evaluate_match_goals(conn, &match_data).map(|_| ())
+}
+
+/// One row of the `goal_evaluations` cache
+struct CachedEvaluation {
+    applicable: bool,
+    achieved: bool,
+    actual_value: i32,
+    conditions: Vec<ConditionEvaluation>,
+}
+
+/// Evaluations of `goals` against `matches`, keyed by match ID and in `goals` order. Pairs
+/// found in the `goal_evaluations` cache are read from it; the rest are evaluated and
+/// stored. Unparsed matches have no evaluations and are never cached.
+pub fn cached_goal_evaluations(
+    conn: &Connection,
+    goals: &[Goal],
+    matches: &[Match],
+) -> Result<HashMap<i64, Vec<GoalEvaluation>>, DotaKeeperError> {
+    let mut result: HashMap<i64, Vec<GoalEvaluation>> = HashMap::new();
+    if goals.is_empty() {
+        return Ok(result);
+    }
+
+    // A single match only needs its own rows; otherwise read the whole cache in one go
+    let single_match = match matches {
+        [m] => Some(m.match_id),
+        _ => None,
+    };
+    let mut cached: HashMap<(i64, i64), CachedEvaluation> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare(
+                "SELECT goal_id, match_id, applicable, achieved, actual_value, conditions FROM goal_evaluations
+                 WHERE ?1 IS NULL OR match_id = ?1",
+            )
+            .map_err(|e| DotaKeeperError::db("Failed to prepare goal evaluations query", e))?;
+        let rows = stmt
+            .query_map(params![single_match], |row| {
+                let conditions: Option<String> = row.get(5)?;
+                Ok((
+                    (row.get::<_, i64>(0)?, row.get::<_, i64>(1)?),
+                    CachedEvaluation {
+                        applicable: row.get(2)?,
+                        achieved: row.get(3)?,
+                        actual_value: row.get::<_, Option<i32>>(4)?.unwrap_or(0),
+                        conditions: conditions
+                            .and_then(|json| serde_json::from_str(&json).ok())
+                            .unwrap_or_default(),
+                    },
+                ))
+            })
+            .map_err(|e| DotaKeeperError::db("Failed to query goal evaluations", e))?;
+        for row in rows {
+            let (key, evaluation) = row.map_err(|e| DotaKeeperError::db("Failed to read goal evaluation", e))?;
+            cached.insert(key, evaluation);
+        }
+    }
+
+    let mut fresh = Vec::new();
+    for match_data in matches.iter().filter(|m| m.parse_state != MatchState::Unparsed) {
+        let evaluations = result.entry(match_data.match_id).or_default();
+        for goal in goals {
+            let evaluation = match cached.remove(&(goal.id, match_data.match_id)) {
+                Some(c) => c.applicable.then(|| GoalEvaluation {
+                    goal: goal.clone(),
+                    achieved: c.achieved,
+                    actual_value: c.actual_value,
+                    conditions: c.conditions,
+                }),
+                None => {
+                    let evaluation = evaluate_goal(conn, goal, match_data);
+                    fresh.push((goal.id, match_data.match_id, evaluation.clone()));
+                    evaluation
+                }
+            };
+            evaluations.extend(evaluation);
+        }
+    }
+
+    if !fresh.is_empty() {
+        store_goal_evaluations(conn, &fresh)?;
+    }
+    Ok(result)
+}
+
+fn store_goal_evaluations(conn: &Connection, rows: &[(i64, i64, Option<GoalEvaluation>)]) -> Result<(), DotaKeeperError> {
+    // A savepoint (rather than a transaction) so this also works inside a caller's transaction
+    conn.execute_batch("SAVEPOINT store_goal_evaluations")
+        .map_err(|e| DotaKeeperError::db("Failed to begin storing goal evaluations", e))?;
+    let stored = (|| {
+        let mut stmt = conn
+            .prepare(
+                "INSERT OR REPLACE INTO goal_evaluations (goal_id, match_id, applicable, achieved, actual_value, conditions)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(|e| DotaKeeperError::db("Failed to prepare goal evaluation insert", e))?;
+        for (goal_id, match_id, evaluation) in rows {
+            let conditions = match evaluation {
+                Some(e) if !e.conditions.is_empty() => Some(
+                    serde_json::to_string(&e.conditions)
+                        .map_err(|e| DotaKeeperError::Database(format!("Failed to serialize goal evaluation: {}", e)))?,
+                ),
+                _ => None,
+            };
+            stmt.execute(params![
+                goal_id,
+                match_id,
+                evaluation.is_some(),
+                evaluation.as_ref().is_some_and(|e| e.achieved),
+                evaluation.as_ref().map(|e| e.actual_value),
+                conditions,
+            ])
+            .map_err(|e| DotaKeeperError::db("Failed to store goal evaluation", e))?;
+        }
+        Ok(())
+    })();
+    let end = if stored.is_ok() { "RELEASE store_goal_evaluations" } else { "ROLLBACK TO store_goal_evaluations; RELEASE store_goal_evaluations" };
+    conn.execute_batch(end)
+        .map_err(|e| DotaKeeperError::db("Failed to finish storing goal evaluations", e))?;
+    stored
+}
+
 /// Goal progress at a specific time
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GoalProgress {
@@ -1292,7 +2806,7 @@ pub struct GoalProgress {
 }
 
 /// Insert goal progress data for a match
-pub fn insert_goal_progress(conn: &Connection, progress: &GoalProgress) -> Result<(), String> {
+pub fn insert_goal_progress(conn: &Connection, progress: &GoalProgress) -> Result<(), DotaKeeperError> {
     conn.execute(
         "INSERT OR REPLACE INTO goal_progress (match_id, time_minutes, last_hits, denies)
          VALUES (?1, ?2, ?3, ?4)",
@@ -1302,13 +2816,13 @@ pub fn insert_goal_progress(conn: &Connection, progress: &GoalProgress) -> Resul
             progress.last_hits,
             progress.denies,
         ],
-    ).map_err(|e| format!("Failed to insert goal progress: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to insert goal progress", e))?;
 
     Ok(())
 }
 
 /// Get goal progress for a specific match
-pub fn get_goal_progress(conn: &Connection, match_id: i64) -> Result<Vec<GoalProgress>, String> {
+pub fn get_goal_progress(conn: &Connection, match_id: i64) -> Result<Vec<GoalProgress>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT match_id, time_minutes, last_hits, denies
@@ -1316,7 +2830,7 @@ pub fn get_goal_progress(conn: &Connection, match_id: i64) -> Result<Vec<GoalPro
              WHERE match_id = ?1
              ORDER BY time_minutes ASC",
         )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let progress_items = stmt
         .query_map(params![match_id], |row| {
@@ -1327,32 +2841,34 @@ pub fn get_goal_progress(conn: &Connection, match_id: i64) -> Result<Vec<GoalPro
                 denies: row.get(3)?,
             })
         })
-        .map_err(|e| format!("Failed to query goal progress: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query goal progress", e))?;
 
     let mut result = Vec::new();
     for item in progress_items {
-        result.push(item.map_err(|e| format!("Failed to read goal progress: {}", e))?);
+        result.push(item.map_err(|e| DotaKeeperError::db("Failed to read goal progress", e))?);
     }
 
     Ok(result)
 }
 
 /// Update match parse state
-pub fn update_match_state(conn: &Connection, match_id: i64, state: MatchState) -> Result<(), String> {
+pub fn update_match_state(conn: &Connection, match_id: i64, state: MatchState) -> Result<(), DotaKeeperError> {
     conn.execute(
         "UPDATE matches SET parse_state = ?1 WHERE match_id = ?2",
         params![state.to_string(), match_id],
-    ).map_err(|e| format!("Failed to update match state: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to update match state", e))?;
+    // A (re)parse changes the data goals are evaluated on
+    clear_match_goal_evaluations(conn, match_id)?;
 
     Ok(())
 }
 
 /// Update match role (lane position 1-5, 0 = unknown)
-pub fn update_match_role(conn: &Connection, match_id: i64, role: i32) -> Result<(), String> {
+pub fn update_match_role(conn: &Connection, match_id: i64, role: i32) -> Result<(), DotaKeeperError> {
     conn.execute(
         "UPDATE matches SET role = ?1 WHERE match_id = ?2",
         params![role, match_id],
-    ).map_err(|e| format!("Failed to update match role: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to update match role", e))?;
 
     Ok(())
 }
@@ -1369,7 +2885,7 @@ pub fn update_match_stats(
     hero_damage: Option<i32>,
     tower_damage: Option<i32>,
     hero_healing: Option<i32>,
-) -> Result<(), String> {
+) -> Result<(), DotaKeeperError> {
     conn.execute(
         "UPDATE matches SET
             xp_per_min    = CASE WHEN xp_per_min    = 0 AND ?1 IS NOT NULL THEN ?1 ELSE xp_per_min    END,
@@ -1381,50 +2897,60 @@ pub fn update_match_stats(
             hero_healing  = CASE WHEN hero_healing   = 0 AND ?7 IS NOT NULL THEN ?7 ELSE hero_healing  END
          WHERE match_id = ?8",
         params![xp_per_min, gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, match_id],
-    ).map_err(|e| format!("Failed to update match stats: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to update match stats", e))?;
 
     Ok(())
 }
 
 /// Store the lane partner's player_slot for a match (None if no partner / not a support)
-pub fn update_match_partner_slot(conn: &Connection, match_id: i64, partner_slot: Option<i32>) -> Result<(), String> {
+pub fn update_match_partner_slot(conn: &Connection, match_id: i64, partner_slot: Option<i32>) -> Result<(), DotaKeeperError> {
     conn.execute(
         "UPDATE matches SET partner_slot = ?1 WHERE match_id = ?2",
         params![partner_slot, match_id],
-    ).map_err(|e| format!("Failed to update partner slot: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to update partner slot", e))?;
+
+    Ok(())
+}
+
+/// Record which data provider produced a match's parsed data
+pub fn update_match_parsed_by(conn: &Connection, match_id: i64, provider: &str) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "UPDATE matches SET parsed_by = ?1 WHERE match_id = ?2",
+        params![provider, match_id],
+    ).map_err(|e| DotaKeeperError::db("Failed to update parsed_by", e))?;
 
     Ok(())
 }
 
 /// Get the stored lane partner player_slot for a match
-pub fn get_partner_slot(conn: &Connection, match_id: i64) -> Result<Option<i32>, String> {
+pub fn get_partner_slot(conn: &Connection, match_id: i64) -> Result<Option<i32>, DotaKeeperError> {
     conn.query_row(
         "SELECT partner_slot FROM matches WHERE match_id = ?1",
         params![match_id],
         |row| row.get(0),
-    ).map_err(|e| format!("Failed to query partner slot: {}", e))
+    ).map_err(|e| DotaKeeperError::db("Failed to query partner slot", e))
 }
 
 /// Bulk insert per-minute networth for a single player in a match
-pub fn insert_player_networth(conn: &Connection, match_id: i64, player_slot: i32, nw_t: &[i32]) -> Result<(), String> {
+pub fn insert_player_networth(conn: &Connection, match_id: i64, player_slot: i32, nw_t: &[i32]) -> Result<(), DotaKeeperError> {
     // Delete existing data for this player in this match first
     conn.execute(
         "DELETE FROM player_networth WHERE match_id = ?1 AND player_slot = ?2",
         params![match_id, player_slot],
-    ).map_err(|e| format!("Failed to delete existing networth data: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to delete existing networth data", e))?;
 
     for (minute, &networth) in nw_t.iter().enumerate() {
         conn.execute(
             "INSERT INTO player_networth (match_id, player_slot, minute, networth) VALUES (?1, ?2, ?3, ?4)",
             params![match_id, player_slot, minute as i32, networth],
-        ).map_err(|e| format!("Failed to insert player networth: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to insert player networth", e))?;
     }
 
     Ok(())
 }
 
 /// Get a player's networth at a specific minute
-pub fn get_partner_networth_at_minute(conn: &Connection, match_id: i64, player_slot: i32, minute: i32) -> Result<Option<i32>, String> {
+pub fn get_partner_networth_at_minute(conn: &Connection, match_id: i64, player_slot: i32, minute: i32) -> Result<Option<i32>, DotaKeeperError> {
     match conn.query_row(
         "SELECT networth FROM player_networth WHERE match_id = ?1 AND player_slot = ?2 AND minute = ?3",
         params![match_id, player_slot, minute],
@@ -1432,8 +2958,359 @@ pub fn get_partner_networth_at_minute(conn: &Connection, match_id: i64, player_s
     ) {
         Ok(v) => Ok(Some(v)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(format!("Failed to query partner networth: {}", e)),
+        Err(e) => Err(DotaKeeperError::db("Failed to query partner networth", e)),
+    }
+}
+
+/// One player's end-of-game line from a parsed match (stored for all ten players)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MatchPlayer {
+    pub match_id: i64,
+    pub player_slot: i32,
+    pub account_id: Option<u32>,  // None for anonymous players
+    pub hero_id: i32,
+    pub lane_role: i32,  // 0=unknown, 1=carry, 2=mid, 3=offlane, 4=soft support, 5=hard support
+    pub kills: Option<i32>,
+    pub deaths: Option<i32>,
+    pub assists: Option<i32>,
+    pub last_hits: Option<i32>,
+    pub denies: Option<i32>,
+    pub xp_per_min: Option<i32>,
+    pub gold_per_min: Option<i32>,
+    pub hero_damage: Option<i32>,
+    pub tower_damage: Option<i32>,
+    pub hero_healing: Option<i32>,
+}
+
+/// A player's cumulative last hits, denies and XP at one minute of a match
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MatchPlayerMinute {
+    pub minute: i32,
+    pub last_hits: Option<i32>,
+    pub denies: Option<i32>,
+    pub xp: Option<i32>,
+}
+
+/// An item purchase by any player in a match
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MatchPlayerItem {
+    pub player_slot: i32,
+    pub item_id: i32,
+    pub timing_seconds: i32,
+}
+
+/// Store (or replace) a player's end-of-game line
+pub fn upsert_match_player(conn: &Connection, player: &MatchPlayer) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO match_players (
+            match_id, player_slot, account_id, hero_id, lane_role, kills, deaths, assists,
+            last_hits, denies, xp_per_min, gold_per_min, hero_damage, tower_damage, hero_healing
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![
+            player.match_id,
+            player.player_slot,
+            player.account_id,
+            player.hero_id,
+            player.lane_role,
+            player.kills,
+            player.deaths,
+            player.assists,
+            player.last_hits,
+            player.denies,
+            player.xp_per_min,
+            player.gold_per_min,
+            player.hero_damage,
+            player.tower_damage,
+            player.hero_healing,
+        ],
+    ).map_err(|e| DotaKeeperError::db("Failed to insert match player", e))?;
+
+    Ok(())
+}
+
+/// Store a player's per-minute last hits, denies and XP (index = minute), replacing any existing rows
+pub fn insert_match_player_timeline(
+    conn: &Connection,
+    match_id: i64,
+    player_slot: i32,
+    lh_t: &[i32],
+    dn_t: &[i32],
+    xp_t: &[i32],
+) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "DELETE FROM match_player_timelines WHERE match_id = ?1 AND player_slot = ?2",
+        params![match_id, player_slot],
+    ).map_err(|e| DotaKeeperError::db("Failed to delete existing player timeline", e))?;
+
+    let minutes = lh_t.len().max(dn_t.len()).max(xp_t.len());
+    for minute in 0..minutes {
+        conn.execute(
+            "INSERT INTO match_player_timelines (match_id, player_slot, minute, last_hits, denies, xp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                match_id,
+                player_slot,
+                minute as i32,
+                lh_t.get(minute),
+                dn_t.get(minute),
+                xp_t.get(minute),
+            ],
+        ).map_err(|e| DotaKeeperError::db("Failed to insert player timeline", e))?;
+    }
+
+    Ok(())
+}
+
+/// Store a player's item purchases as (item_id, timing_seconds), replacing any existing rows
+pub fn insert_match_player_items(
+    conn: &Connection,
+    match_id: i64,
+    player_slot: i32,
+    purchases: &[(i32, i32)],
+) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "DELETE FROM match_player_items WHERE match_id = ?1 AND player_slot = ?2",
+        params![match_id, player_slot],
+    ).map_err(|e| DotaKeeperError::db("Failed to delete existing player items", e))?;
+
+    for &(item_id, timing_seconds) in purchases {
+        conn.execute(
+            "INSERT INTO match_player_items (match_id, player_slot, item_id, timing_seconds) VALUES (?1, ?2, ?3, ?4)",
+            params![match_id, player_slot, item_id, timing_seconds],
+        ).map_err(|e| DotaKeeperError::db("Failed to insert player item", e))?;
+    }
+
+    Ok(())
+}
+
+/// Get all stored players for a match, ordered by player_slot (Radiant first)
+pub fn get_match_players(conn: &Connection, match_id: i64) -> Result<Vec<MatchPlayer>, DotaKeeperError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT match_id, player_slot, account_id, hero_id, lane_role, kills, deaths, assists,
+                    last_hits, denies, xp_per_min, gold_per_min, hero_damage, tower_damage, hero_healing
+             FROM match_players
+             WHERE match_id = ?1
+             ORDER BY player_slot ASC",
+        )
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
+
+    let players = stmt
+        .query_map(params![match_id], |row| {
+            Ok(MatchPlayer {
+                match_id: row.get(0)?,
+                player_slot: row.get(1)?,
+                account_id: row.get(2)?,
+                hero_id: row.get(3)?,
+                lane_role: row.get(4)?,
+                kills: row.get(5)?,
+                deaths: row.get(6)?,
+                assists: row.get(7)?,
+                last_hits: row.get(8)?,
+                denies: row.get(9)?,
+                xp_per_min: row.get(10)?,
+                gold_per_min: row.get(11)?,
+                hero_damage: row.get(12)?,
+                tower_damage: row.get(13)?,
+                hero_healing: row.get(14)?,
+            })
+        })
+        .map_err(|e| DotaKeeperError::db("Failed to query match players", e))?;
+
+    let mut result = Vec::new();
+    for player in players {
+        result.push(player.map_err(|e| DotaKeeperError::db("Failed to read match player", e))?);
+    }
+
+    Ok(result)
+}
+
+/// Get one player's per-minute timeline for a match
+pub fn get_match_player_timeline(conn: &Connection, match_id: i64, player_slot: i32) -> Result<Vec<MatchPlayerMinute>, DotaKeeperError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT minute, last_hits, denies, xp
+             FROM match_player_timelines
+             WHERE match_id = ?1 AND player_slot = ?2
+             ORDER BY minute ASC",
+        )
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
+
+    let minutes = stmt
+        .query_map(params![match_id, player_slot], |row| {
+            Ok(MatchPlayerMinute {
+                minute: row.get(0)?,
+                last_hits: row.get(1)?,
+                denies: row.get(2)?,
+                xp: row.get(3)?,
+            })
+        })
+        .map_err(|e| DotaKeeperError::db("Failed to query player timeline", e))?;
+
+    let mut result = Vec::new();
+    for minute in minutes {
+        result.push(minute.map_err(|e| DotaKeeperError::db("Failed to read player timeline", e))?);
+    }
+
+    Ok(result)
+}
+
+/// Get every player's item purchases for a match, in purchase order
+pub fn get_match_player_items(conn: &Connection, match_id: i64) -> Result<Vec<MatchPlayerItem>, DotaKeeperError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT player_slot, item_id, timing_seconds
+             FROM match_player_items
+             WHERE match_id = ?1
+             ORDER BY player_slot ASC, timing_seconds ASC",
+        )
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
+
+    let items = stmt
+        .query_map(params![match_id], |row| {
+            Ok(MatchPlayerItem {
+                player_slot: row.get(0)?,
+                item_id: row.get(1)?,
+                timing_seconds: row.get(2)?,
+            })
+        })
+        .map_err(|e| DotaKeeperError::db("Failed to query player items", e))?;
+
+    let mut result = Vec::new();
+    for item in items {
+        result.push(item.map_err(|e| DotaKeeperError::db("Failed to read player item", e))?);
+    }
+
+    Ok(result)
+}
+
+/// Minutes at which lane outcomes are measured
+pub const LANE_OUTCOME_MINUTES: [i32; 2] = [5, 10];
+
+/// Positions sharing a lane: the safe lane (1 + 5), mid (2) and the off lane (3 + 4).
+fn lane_positions(role: i32) -> &'static [i32] {
+    match role {
+        1 | 5 => &[1, 5],
+        2 => &[2],
+        3 | 4 => &[3, 4],
+        _ => &[],
+    }
+}
+
+/// Split a match's players, given as (player_slot, lane_role), into the player's side of
+/// the lane (the player plus allies in it) and the enemies they laned against. The safe
+/// lane faces the enemy off lane and mid faces mid. Returns None when the player's role
+/// is unknown or no enemy was in the lane.
+pub fn lane_sides(player_slot: i32, role: i32, players: &[(i32, i32)]) -> Option<(Vec<i32>, Vec<i32>)> {
+    let own_lane = lane_positions(role);
+    let facing_lane = lane_positions(match role {
+        1 | 5 => 3,
+        3 | 4 => 1,
+        other => other,
+    });
+    let is_radiant = player_slot < 128;
+
+    let mut allies = vec![player_slot];
+    let mut enemies = Vec::new();
+    for &(slot, lane_role) in players {
+        if (slot < 128) != is_radiant {
+            if facing_lane.contains(&lane_role) {
+                enemies.push(slot);
+            }
+        } else if slot != player_slot && own_lane.contains(&lane_role) {
+            allies.push(slot);
+        }
+    }
+    (!enemies.is_empty()).then_some((allies, enemies))
+}
+
+/// Differentials of the player's side of the lane against the enemy laners at one minute,
+/// summed over each side's heroes (positive = ahead)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LaneSnapshot {
+    pub minute: i32,
+    pub last_hits_diff: i32,
+    pub denies_diff: i32,
+    pub networth_diff: i32,
+}
+
+/// How the player's lane went against the enemy laners
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LaneOutcome {
+    pub match_id: i64,
+    /// The player and any allies who shared their lane
+    pub ally_slots: Vec<i32>,
+    /// Every enemy who laned against them
+    pub opponent_slots: Vec<i32>,
+    pub opponent_hero_ids: Vec<i32>,
+    pub snapshots: Vec<LaneSnapshot>,  // One per LANE_OUTCOME_MINUTES entry with data for every laner
+}
+
+/// A player's (last hits, denies, networth) at a minute, from the all-player tables
+fn player_lane_stats(conn: &Connection, match_id: i64, player_slot: i32, minute: i32) -> Result<Option<(i32, i32, i32)>, DotaKeeperError> {
+    conn.query_row(
+        "SELECT COALESCE(t.last_hits, 0), COALESCE(t.denies, 0), n.networth
+         FROM match_player_timelines t
+         JOIN player_networth n
+           ON n.match_id = t.match_id AND n.player_slot = t.player_slot AND n.minute = t.minute
+         WHERE t.match_id = ?1 AND t.player_slot = ?2 AND t.minute = ?3",
+        params![match_id, player_slot, minute],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .optional()
+    .map_err(|e| DotaKeeperError::db("Failed to query lane stats", e))
+}
+
+/// Summed (last hits, denies, networth) of `slots` at a minute; None if any is missing
+fn lane_side_stats(conn: &Connection, match_id: i64, slots: &[i32], minute: i32) -> Result<Option<(i32, i32, i32)>, DotaKeeperError> {
+    let mut total = (0, 0, 0);
+    for &slot in slots {
+        let Some((lh, dn, nw)) = player_lane_stats(conn, match_id, slot, minute)? else {
+            return Ok(None);
+        };
+        total = (total.0 + lh, total.1 + dn, total.2 + nw);
+    }
+    Ok(Some(total))
+}
+
+/// Compare the player's side of the lane against every enemy laner at 5 and 10 minutes.
+/// Returns None when the match has no known lane opponents (unparsed, or role unknown).
+pub fn get_lane_outcome(conn: &Connection, match_id: i64) -> Result<Option<LaneOutcome>, DotaKeeperError> {
+    let Some(m) = get_match_by_id(conn, match_id)? else {
+        return Ok(None);
+    };
+    let players = get_match_players(conn, match_id)?;
+    let lanes: Vec<(i32, i32)> = players.iter().map(|p| (p.player_slot, p.lane_role)).collect();
+    let Some((ally_slots, opponent_slots)) = lane_sides(m.player_slot, m.role, &lanes) else {
+        return Ok(None);
+    };
+    let opponent_hero_ids = players
+        .iter()
+        .filter(|p| opponent_slots.contains(&p.player_slot))
+        .map(|p| p.hero_id)
+        .collect();
+
+    let mut snapshots = Vec::new();
+    for minute in LANE_OUTCOME_MINUTES {
+        let own = lane_side_stats(conn, match_id, &ally_slots, minute)?;
+        let theirs = lane_side_stats(conn, match_id, &opponent_slots, minute)?;
+        if let (Some((lh, dn, nw)), Some((opp_lh, opp_dn, opp_nw))) = (own, theirs) {
+            snapshots.push(LaneSnapshot {
+                minute,
+                last_hits_diff: lh - opp_lh,
+                denies_diff: dn - opp_dn,
+                networth_diff: nw - opp_nw,
+            });
+        }
     }
+
+    Ok(Some(LaneOutcome {
+        match_id,
+        ally_slots,
+        opponent_slots,
+        opponent_hero_ids,
+        snapshots,
+    }))
 }
 
 /// Match CS data at a specific minute
@@ -1446,12 +3323,12 @@ pub struct MatchCS {
 }
 
 /// Insert CS data for all minutes in a match
-pub fn insert_match_cs_data(conn: &Connection, match_id: i64, lh_t: &[i32], dn_t: &[i32]) -> Result<(), String> {
+pub fn insert_match_cs_data(conn: &Connection, match_id: i64, lh_t: &[i32], dn_t: &[i32]) -> Result<(), DotaKeeperError> {
     // Delete existing data for this match first
     conn.execute(
         "DELETE FROM match_cs WHERE match_id = ?1",
         params![match_id],
-    ).map_err(|e| format!("Failed to delete existing CS data: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to delete existing CS data", e))?;
 
     // Insert data for each minute
     // OpenDota's lh_t array uses 0-based indexing:
@@ -1465,14 +3342,14 @@ pub fn insert_match_cs_data(conn: &Connection, match_id: i64, lh_t: &[i32], dn_t
         conn.execute(
             "INSERT INTO match_cs (match_id, minute, last_hits, denies) VALUES (?1, ?2, ?3, ?4)",
             params![match_id, minute, lh_t[i], dn_t[i]],
-        ).map_err(|e| format!("Failed to insert CS data: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to insert CS data", e))?;
     }
 
     Ok(())
 }
 
 /// Get CS data for a match at a specific minute
-pub fn get_match_cs_at_minute(conn: &Connection, match_id: i64, minute: i32) -> Result<Option<MatchCS>, String> {
+pub fn get_match_cs_at_minute(conn: &Connection, match_id: i64, minute: i32) -> Result<Option<MatchCS>, DotaKeeperError> {
     let result = conn.query_row(
         "SELECT match_id, minute, last_hits, denies FROM match_cs WHERE match_id = ?1 AND minute = ?2",
         params![match_id, minute],
@@ -1489,17 +3366,17 @@ pub fn get_match_cs_at_minute(conn: &Connection, match_id: i64, minute: i32) ->
     match result {
         Ok(cs) => Ok(Some(cs)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(format!("Failed to query CS data: {}", e)),
+        Err(e) => Err(DotaKeeperError::db("Failed to query CS data", e)),
     }
 }
 
 /// Get all CS data for a match ordered by minute
-pub fn get_match_cs_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchCS>, String> {
+pub fn get_match_cs_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchCS>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT match_id, minute, last_hits, denies FROM match_cs WHERE match_id = ?1 ORDER BY minute ASC",
         )
-        .map_err(|e| format!("Failed to prepare CS data query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare CS data query", e))?;
 
     let rows = stmt
         .query_map(params![match_id], |row| {
@@ -1510,10 +3387,10 @@ pub fn get_match_cs_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchCS
                 denies: row.get(3)?,
             })
         })
-        .map_err(|e| format!("Failed to query CS data: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query CS data", e))?;
 
     rows.collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect CS data: {}", e))
+        .map_err(|e| DotaKeeperError::db("Failed to collect CS data", e))
 }
 
 /// Per-minute CS stats (best and average at each minute) for a hero across matches of a given game mode.
@@ -1528,7 +3405,7 @@ pub struct HeroCsStats {
 
 /// Get best + average CS per minute for a hero, filtered by game mode (22=ranked, 23=turbo).
 /// Best is per-minute (the highest last_hits any match achieved at that minute), same for average.
-pub fn get_hero_cs_stats(conn: &Connection, hero_id: i32, game_mode: i32, exclude_match_id: i64) -> Result<Vec<HeroCsStats>, String> {
+pub fn get_hero_cs_stats(conn: &Connection, hero_id: i32, game_mode: i32, exclude_match_id: i64) -> Result<Vec<HeroCsStats>, DotaKeeperError> {
     let mut stmt = conn.prepare(
         "SELECT mc.minute,
                 MAX(mc.last_hits) as best_lh,
@@ -1540,7 +3417,7 @@ pub fn get_hero_cs_stats(conn: &Connection, hero_id: i32, game_mode: i32, exclud
          WHERE m.hero_id = ?1 AND m.game_mode = ?2 AND mc.match_id != ?3
          GROUP BY mc.minute
          ORDER BY mc.minute ASC"
-    ).map_err(|e| format!("Failed to prepare hero cs stats query: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to prepare hero cs stats query", e))?;
 
     let rows = stmt.query_map(params![hero_id, game_mode, exclude_match_id], |row| {
         Ok(HeroCsStats {
@@ -1550,10 +3427,151 @@ pub fn get_hero_cs_stats(conn: &Connection, hero_id: i32, game_mode: i32, exclud
             avg_last_hits: row.get(3)?,
             avg_denies: row.get(4)?,
         })
-    }).map_err(|e| format!("Failed to query hero cs stats: {}", e))?;
+    }).map_err(|e| DotaKeeperError::db("Failed to query hero cs stats", e))?;
 
     rows.collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect hero cs stats: {}", e))
+        .map_err(|e| DotaKeeperError::db("Failed to collect hero cs stats", e))
+}
+
+/// Default minimum number of shared games before a matchup is reported
+pub const MIN_MATCHUP_GAMES: i32 = 3;
+
+/// Minute at which matchup lane CS diffs are taken
+const MATCHUP_LANE_MINUTE: i32 = 10;
+
+/// The player's results on one hero in games that featured another hero (as enemy or ally)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HeroMatchup {
+    pub hero_id: i32,
+    pub games: i32,
+    pub wins: i32,
+    pub win_rate: f64,
+    pub avg_kills: f64,
+    pub avg_deaths: f64,
+    pub avg_assists: f64,
+    pub avg_lane_cs_diff: Option<f64>,  // Lane side's last hits vs the enemy laners' at 10 min, where known
+}
+
+/// Matchup breakdown for one of the player's heroes
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HeroMatchups {
+    pub hero_id: i32,
+    pub enemies: Vec<HeroMatchup>,
+    pub allies: Vec<HeroMatchup>,
+}
+
+/// Win rate, KDA and lane CS diff on `hero_id` broken down by every enemy and allied hero
+/// seen in stored match_players data. Heroes met in fewer than `min_games` games are left
+/// out; both lists are ordered by games played.
+pub fn get_hero_matchups(conn: &Connection, hero_id: i32, min_games: i32) -> Result<HeroMatchups, DotaKeeperError> {
+    struct Game {
+        player_slot: i32,
+        role: i32,
+        won: bool,
+        kills: i32,
+        deaths: i32,
+        assists: i32,
+    }
+
+    #[derive(Default)]
+    struct Totals {
+        games: i32,
+        wins: i32,
+        kills: i32,
+        deaths: i32,
+        assists: i32,
+        lane_cs_diff: i32,
+        lane_games: i32,  // Games the lane CS diff is known for
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT match_id, player_slot, role, radiant_win, kills, deaths, assists FROM matches WHERE hero_id = ?1"
+    ).map_err(|e| DotaKeeperError::db("Failed to prepare hero matchups query", e))?;
+    let games: HashMap<i64, Game> = stmt
+        .query_map(params![hero_id], |row| {
+            let player_slot: i32 = row.get(1)?;
+            Ok((row.get(0)?, Game {
+                player_slot,
+                role: row.get(2)?,
+                won: (player_slot < 128) == row.get::<_, bool>(3)?,
+                kills: row.get(4)?,
+                deaths: row.get(5)?,
+                assists: row.get(6)?,
+            }))
+        })
+        .and_then(|rows| rows.collect())
+        .map_err(|e| DotaKeeperError::db("Failed to query hero matchups", e))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT mp.match_id, mp.player_slot, mp.hero_id, mp.lane_role
+         FROM match_players mp JOIN matches m ON m.match_id = mp.match_id
+         WHERE m.hero_id = ?1
+         ORDER BY mp.match_id, mp.player_slot"
+    ).map_err(|e| DotaKeeperError::db("Failed to prepare matchup players query", e))?;
+    let mut players: HashMap<i64, Vec<(i32, i32, i32)>> = HashMap::new();
+    let rows = stmt
+        .query_map(params![hero_id], |row| Ok((row.get::<_, i64>(0)?, (row.get(1)?, row.get(2)?, row.get(3)?))))
+        .map_err(|e| DotaKeeperError::db("Failed to query matchup players", e))?;
+    for row in rows {
+        let (match_id, player) = row.map_err(|e| DotaKeeperError::db("Failed to read matchup player", e))?;
+        players.entry(match_id).or_default().push(player);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT t.match_id, t.player_slot, COALESCE(t.last_hits, 0)
+         FROM match_player_timelines t JOIN matches m ON m.match_id = t.match_id
+         WHERE m.hero_id = ?1 AND t.minute = ?2"
+    ).map_err(|e| DotaKeeperError::db("Failed to prepare matchup lane query", e))?;
+    let last_hits: HashMap<(i64, i32), i32> = stmt
+        .query_map(params![hero_id, MATCHUP_LANE_MINUTE], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))
+        .and_then(|rows| rows.collect())
+        .map_err(|e| DotaKeeperError::db("Failed to query matchup lane data", e))?;
+
+    let mut totals: HashMap<(i32, bool), Totals> = HashMap::new();  // Keyed by (hero, is_ally)
+    for (match_id, match_players) in &players {
+        let Some(game) = games.get(match_id) else { continue };
+        let lanes: Vec<(i32, i32)> = match_players.iter().map(|&(slot, _, lane_role)| (slot, lane_role)).collect();
+        let side_last_hits = |slots: &[i32]| slots.iter().map(|slot| last_hits.get(&(*match_id, *slot))).sum::<Option<i32>>();
+        let lane_cs_diff = lane_sides(game.player_slot, game.role, &lanes)
+            .and_then(|(allies, enemies)| Some(side_last_hits(&allies)? - side_last_hits(&enemies)?));
+
+        for &(slot, other_hero, _) in match_players.iter().filter(|p| p.0 != game.player_slot) {
+            let entry = totals.entry((other_hero, (slot < 128) == (game.player_slot < 128))).or_default();
+            entry.games += 1;
+            entry.wins += game.won as i32;
+            entry.kills += game.kills;
+            entry.deaths += game.deaths;
+            entry.assists += game.assists;
+            if let Some(diff) = lane_cs_diff {
+                entry.lane_cs_diff += diff;
+                entry.lane_games += 1;
+            }
+        }
+    }
+
+    let mut rows: Vec<_> = totals.into_iter().filter(|(_, t)| t.games >= min_games).collect();
+    rows.sort_by_key(|((hero, _), t)| (std::cmp::Reverse(t.games), *hero));
+    let mut matchups = HeroMatchups { hero_id, enemies: Vec::new(), allies: Vec::new() };
+    for ((other_hero, is_ally), t) in rows {
+        let games = t.games as f64;
+        let matchup = HeroMatchup {
+            hero_id: other_hero,
+            games: t.games,
+            wins: t.wins,
+            win_rate: t.wins as f64 / games * 100.0,
+            avg_kills: t.kills as f64 / games,
+            avg_deaths: t.deaths as f64 / games,
+            avg_assists: t.assists as f64 / games,
+            avg_lane_cs_diff: (t.lane_games > 0).then(|| t.lane_cs_diff as f64 / t.lane_games as f64),
+        };
+        if is_ally {
+            matchups.allies.push(matchup);
+        } else {
+            matchups.enemies.push(matchup);
+        }
+    }
+
+    Ok(matchups)
 }
 
 /// Per-minute networth data point for the player's own networth chart
@@ -1565,18 +3583,18 @@ pub struct MatchNW {
 
 /// Get per-minute networth for the current player in a match.
 /// Looks up the player's own slot from the matches table and queries player_networth.
-pub fn get_match_networth_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchNW>, String> {
+pub fn get_match_networth_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchNW>, DotaKeeperError> {
     let player_slot: i32 = conn.query_row(
         "SELECT player_slot FROM matches WHERE match_id = ?1",
         params![match_id],
         |row| row.get(0),
-    ).map_err(|e| format!("Failed to get player_slot for match {}: {}", match_id, e))?;
+    ).map_err(|e| DotaKeeperError::db(format!("Failed to get player_slot for match {}", match_id), e))?;
 
     let mut stmt = conn
         .prepare(
             "SELECT minute, networth FROM player_networth WHERE match_id = ?1 AND player_slot = ?2 ORDER BY minute ASC",
         )
-        .map_err(|e| format!("Failed to prepare networth query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare networth query", e))?;
 
     let rows = stmt
         .query_map(params![match_id, player_slot], |row| {
@@ -1585,10 +3603,10 @@ pub fn get_match_networth_data(conn: &Connection, match_id: i64) -> Result<Vec<M
                 networth: row.get(1)?,
             })
         })
-        .map_err(|e| format!("Failed to query networth data: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query networth data", e))?;
 
     rows.collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect networth data: {}", e))
+        .map_err(|e| DotaKeeperError::db("Failed to collect networth data", e))
 }
 
 /// Per-minute XP data point for the XP/Level charts
@@ -1599,29 +3617,29 @@ pub struct MatchXP {
 }
 
 /// Insert per-minute XP data for a match (replaces any existing data)
-pub fn insert_match_xp_data(conn: &Connection, match_id: i64, xp_t: &[i32]) -> Result<(), String> {
+pub fn insert_match_xp_data(conn: &Connection, match_id: i64, xp_t: &[i32]) -> Result<(), DotaKeeperError> {
     conn.execute(
         "DELETE FROM match_xp WHERE match_id = ?1",
         params![match_id],
-    ).map_err(|e| format!("Failed to delete existing XP data: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to delete existing XP data", e))?;
 
     for (minute, &xp) in xp_t.iter().enumerate() {
         conn.execute(
             "INSERT INTO match_xp (match_id, minute, xp) VALUES (?1, ?2, ?3)",
             params![match_id, minute as i32, xp],
-        ).map_err(|e| format!("Failed to insert XP data: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to insert XP data", e))?;
     }
 
     Ok(())
 }
 
 /// Get all per-minute XP data for a match ordered by minute
-pub fn get_match_xp_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchXP>, String> {
+pub fn get_match_xp_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchXP>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT minute, xp FROM match_xp WHERE match_id = ?1 ORDER BY minute ASC",
         )
-        .map_err(|e| format!("Failed to prepare XP data query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare XP data query", e))?;
 
     let rows = stmt
         .query_map(params![match_id], |row| {
@@ -1630,10 +3648,94 @@ pub fn get_match_xp_data(conn: &Connection, match_id: i64) -> Result<Vec<MatchXP
                 xp: row.get(1)?,
             })
         })
-        .map_err(|e| format!("Failed to query XP data: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query XP data", e))?;
 
     rows.collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect XP data: {}", e))
+        .map_err(|e| DotaKeeperError::db("Failed to collect XP data", e))
+}
+
+/// Timestamped per-match event for the player
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub enum MatchEventType {
+    Kill,
+    Death,
+}
+
+impl MatchEventType {
+    fn to_string(&self) -> &'static str {
+        match self {
+            MatchEventType::Kill => "kill",
+            MatchEventType::Death => "death",
+        }
+    }
+}
+
+/// Insert the player's kill and death timestamps (game time in seconds) for a match,
+/// replacing any existing events
+pub fn insert_match_events(conn: &Connection, match_id: i64, kill_times: &[i32], death_times: &[i32]) -> Result<(), DotaKeeperError> {
+    conn.execute(
+        "DELETE FROM match_events WHERE match_id = ?1",
+        params![match_id],
+    ).map_err(|e| DotaKeeperError::db("Failed to delete existing match events", e))?;
+
+    let events = kill_times.iter().map(|&t| (MatchEventType::Kill, t))
+        .chain(death_times.iter().map(|&t| (MatchEventType::Death, t)));
+    for (event_type, time_seconds) in events {
+        conn.execute(
+            "INSERT INTO match_events (match_id, event_type, time_seconds) VALUES (?1, ?2, ?3)",
+            params![match_id, event_type.to_string(), time_seconds],
+        ).map_err(|e| DotaKeeperError::db("Failed to insert match event", e))?;
+    }
+
+    Ok(())
+}
+
+/// Count the player's events of one type up to the end of a given minute.
+/// Returns None when no events of that type are stored for the match.
+pub fn get_match_events_at_minute(conn: &Connection, match_id: i64, event_type: &MatchEventType, minute: i32) -> Result<Option<i32>, DotaKeeperError> {
+    let (total, at_minute): (i32, i32) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(CASE WHEN time_seconds <= ?3 THEN 1 ELSE 0 END), 0)
+         FROM match_events WHERE match_id = ?1 AND event_type = ?2",
+        params![match_id, event_type.to_string(), minute * 60],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).map_err(|e| DotaKeeperError::db("Failed to query match events", e))?;
+
+    Ok(if total > 0 { Some(at_minute) } else { None })
+}
+
+/// Resolve a kill/death count at `target_minutes` given the end-of-game total and the
+/// count from the stored event timeline (None when the match has no timeline).
+/// Returns None when the value can't be known exactly — never estimates.
+fn events_at_minute(total: i32, duration_seconds: i32, target_minutes: i32, timeline: Option<i32>) -> Option<i32> {
+    if total == 0 || duration_seconds <= target_minutes * 60 {
+        // Nothing to place on the timeline, or the game ended before the target minute
+        return Some(total);
+    }
+    timeline
+}
+
+/// Dota 2 cumulative XP thresholds per level (index = level - 1, value = total XP needed).
+/// Kept in sync with the table behind the Level chart on the match detail page.
+const XP_PER_LEVEL: [i32; 30] = [
+    0, 230, 630, 1130, 1730, 2430, 3230, 4130, 5130, 6330,
+    7630, 9030, 10530, 12130, 13830, 15630, 17630, 19830, 22230, 24630,
+    27430, 30630, 34030, 37830, 42030, 46830, 52230, 58230, 64830, 72030,
+];
+
+/// Convert cumulative XP into a hero level (1-30)
+pub fn xp_to_level(xp: i32) -> i32 {
+    XP_PER_LEVEL.iter().take_while(|&&threshold| xp >= threshold).count().max(1) as i32
+}
+
+/// Get the player's cumulative XP at a specific minute
+pub fn get_match_xp_at_minute(conn: &Connection, match_id: i64, minute: i32) -> Result<Option<i32>, DotaKeeperError> {
+    conn.query_row(
+        "SELECT xp FROM match_xp WHERE match_id = ?1 AND minute = ?2",
+        params![match_id, minute],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| DotaKeeperError::db("Failed to query XP data", e))
 }
 
 /// Daily goal progress data
@@ -1652,8 +3754,8 @@ pub struct GoalWithDailyProgress {
 }
 
 /// Get goal progress by day for the last N days
-pub fn get_goals_with_daily_progress(conn: &Connection, days: i32) -> Result<Vec<GoalWithDailyProgress>, String> {
-    let goals = get_all_goals(conn)?;
+pub fn get_goals_with_daily_progress(conn: &Connection, days: i32) -> Result<Vec<GoalWithDailyProgress>, DotaKeeperError> {
+    let goals = get_active_goals(conn)?;
     let matches = get_all_matches(conn)?;
 
     // Get current timestamp
@@ -1732,11 +3834,30 @@ pub struct MatchDataPoint {
 }
 
 /// Get match data for a specific goal (for histogram visualization)
-pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchDataPoint>, String> {
+pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchDataPoint>, DotaKeeperError> {
     let goal = get_goal_by_id(conn, goal_id)?;
     let matches = get_all_matches(conn)?;
     let target_minutes = goal.target_time_minutes;
 
+    // Compound goals span several metrics; evaluate them match by match
+    if !goal.conditions.is_empty() {
+        return Ok(matches
+            .iter()
+            .filter_map(|m| {
+                let evaluation = evaluate_goal(conn, &goal, m)?;
+                Some(MatchDataPoint {
+                    match_id: m.match_id,
+                    hero_id: m.hero_id,
+                    start_time: m.start_time,
+                    value: evaluation.actual_value,
+                    achieved: evaluation.achieved,
+                    won: m.is_win(),
+                    game_mode: m.game_mode,
+                })
+            })
+            .collect());
+    }
+
     // Batch-load all metric data up front to avoid N+1 per-match queries.
 
     // Batch load CS data (collect Vec first to satisfy borrow checker before stmt drops)
@@ -1744,12 +3865,12 @@ pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchD
         GoalMetric::LastHits | GoalMetric::Denies => {
             let mut stmt = conn
                 .prepare("SELECT match_id, last_hits, denies FROM match_cs WHERE minute = ?1")
-                .map_err(|e| format!("Failed to prepare CS query: {}", e))?;
+                .map_err(|e| DotaKeeperError::db("Failed to prepare CS query", e))?;
             let rows: Vec<(i64, i32, i32)> = stmt
                 .query_map(params![target_minutes], |row| {
                     Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?))
                 })
-                .map_err(|e| format!("Failed to query CS data: {}", e))?
+                .map_err(|e| DotaKeeperError::db("Failed to query CS data", e))?
                 .filter_map(|r| r.ok())
                 .collect();
             rows.into_iter().map(|(mid, lh, dn)| (mid, (lh, dn))).collect()
@@ -1758,15 +3879,15 @@ pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchD
     };
 
     let nw_map: HashMap<(i64, i32), i32> = match &goal.metric {
-        GoalMetric::Networth | GoalMetric::PartnerNetworth => {
+        GoalMetric::Networth | GoalMetric::PartnerNetworth | GoalMetric::LaneNetworthLead => {
             let mut stmt = conn
                 .prepare("SELECT match_id, player_slot, networth FROM player_networth WHERE minute = ?1")
-                .map_err(|e| format!("Failed to prepare NW query: {}", e))?;
+                .map_err(|e| DotaKeeperError::db("Failed to prepare NW query", e))?;
             let rows: Vec<(i64, i32, i32)> = stmt
                 .query_map(params![target_minutes], |row| {
                     Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?))
                 })
-                .map_err(|e| format!("Failed to query NW data: {}", e))?
+                .map_err(|e| DotaKeeperError::db("Failed to query NW data", e))?
                 .filter_map(|r| r.ok())
                 .collect();
             rows.into_iter().map(|(mid, slot, nw)| ((mid, slot), nw)).collect()
@@ -1778,12 +3899,50 @@ pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchD
         GoalMetric::PartnerNetworth => {
             let mut stmt = conn
                 .prepare("SELECT match_id, partner_slot FROM matches WHERE partner_slot IS NOT NULL")
-                .map_err(|e| format!("Failed to prepare partner slot query: {}", e))?;
+                .map_err(|e| DotaKeeperError::db("Failed to prepare partner slot query", e))?;
             let rows: Vec<(i64, i32)> = stmt
                 .query_map([], |row| {
                     Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?))
                 })
-                .map_err(|e| format!("Failed to query partner slots: {}", e))?
+                .map_err(|e| DotaKeeperError::db("Failed to query partner slots", e))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows.into_iter().collect()
+        }
+        _ => HashMap::new(),
+    };
+
+    let events_map: HashMap<i64, i32> = match &goal.metric {
+        GoalMetric::Kills | GoalMetric::Deaths => {
+            let event_type = if goal.metric == GoalMetric::Kills { MatchEventType::Kill } else { MatchEventType::Death };
+            let mut stmt = conn
+                .prepare(
+                    "SELECT match_id, SUM(CASE WHEN time_seconds <= ?2 THEN 1 ELSE 0 END)
+                     FROM match_events WHERE event_type = ?1 GROUP BY match_id",
+                )
+                .map_err(|e| DotaKeeperError::db("Failed to prepare match events query", e))?;
+            let rows: Vec<(i64, i32)> = stmt
+                .query_map(params![event_type.to_string(), target_minutes * 60], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?))
+                })
+                .map_err(|e| DotaKeeperError::db("Failed to query match events", e))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows.into_iter().collect()
+        }
+        _ => HashMap::new(),
+    };
+
+    let xp_map: HashMap<i64, i32> = match &goal.metric {
+        GoalMetric::Level => {
+            let mut stmt = conn
+                .prepare("SELECT match_id, xp FROM match_xp WHERE minute = ?1")
+                .map_err(|e| DotaKeeperError::db("Failed to prepare XP query", e))?;
+            let rows: Vec<(i64, i32)> = stmt
+                .query_map(params![target_minutes], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?))
+                })
+                .map_err(|e| DotaKeeperError::db("Failed to query XP data", e))?
                 .filter_map(|r| r.ok())
                 .collect();
             rows.into_iter().collect()
@@ -1791,17 +3950,38 @@ pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchD
         _ => HashMap::new(),
     };
 
+    let lane_roles_map: HashMap<i64, Vec<(i32, i32)>> = match &goal.metric {
+        GoalMetric::LaneNetworthLead => {
+            let mut stmt = conn
+                .prepare("SELECT match_id, player_slot, lane_role FROM match_players")
+                .map_err(|e| DotaKeeperError::db("Failed to prepare lane roles query", e))?;
+            let rows: Vec<(i64, i32, i32)> = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?))
+                })
+                .map_err(|e| DotaKeeperError::db("Failed to query lane roles", e))?
+                .filter_map(|r| r.ok())
+                .collect();
+            let mut map: HashMap<i64, Vec<(i32, i32)>> = HashMap::new();
+            for (match_id, slot, lane_role) in rows {
+                map.entry(match_id).or_default().push((slot, lane_role));
+            }
+            map
+        }
+        _ => HashMap::new(),
+    };
+
     let item_timing_map: HashMap<i64, i32> = match &goal.metric {
         GoalMetric::ItemTiming => {
             if let Some(item_id) = goal.item_id {
                 let mut stmt = conn
                     .prepare("SELECT match_id, timing_seconds FROM item_timings WHERE item_id = ?1")
-                    .map_err(|e| format!("Failed to prepare item timing query: {}", e))?;
+                    .map_err(|e| DotaKeeperError::db("Failed to prepare item timing query", e))?;
                 let rows: Vec<(i64, i32)> = stmt
                     .query_map(params![item_id], |row| {
                         Ok((row.get::<_, i64>(0)?, row.get::<_, i32>(1)?))
                     })
-                    .map_err(|e| format!("Failed to query item timings: {}", e))?
+                    .map_err(|e| DotaKeeperError::db("Failed to query item timings", e))?
                     .filter_map(|r| r.ok())
                     .collect();
                 rows.into_iter().collect()
@@ -1818,39 +3998,16 @@ pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchD
         if match_data.parse_state == MatchState::Unparsed {
             continue;
         }
-
-        let hero_matches = match goal.hero_scope.as_deref() {
-            Some("any_carry")   => match_data.role == 1,
-            Some("any_core")    => matches!(match_data.role, 1 | 2 | 3),
-            Some("any_support") => matches!(match_data.role, 4 | 5),
-            _ => match goal.hero_id {
-                Some(id) => id == match_data.hero_id,
-                None => true,
-            },
-        };
-        if !hero_matches { continue; }
-
-        let mode_matches = match goal.game_mode {
-            GoalGameMode::Ranked => match_data.game_mode == 22,
-            GoalGameMode::Turbo  => match_data.game_mode == 23,
-            GoalGameMode::All    => true,
-        };
-        if !mode_matches { continue; }
-
-        let duration_minutes = match_data.duration / 60;
-        let actual_value = match &goal.metric {
-            GoalMetric::Kills => {
-                if duration_minutes <= target_minutes {
-                    match_data.kills
-                } else {
-                    ((match_data.kills as f32 / duration_minutes as f32) * target_minutes as f32) as i32
-                }
-            }
-            GoalMetric::Deaths => {
-                if duration_minutes <= target_minutes {
-                    match_data.deaths
-                } else {
-                    ((match_data.deaths as f32 / duration_minutes as f32) * target_minutes as f32) as i32
+
+        if !goal_applies_to_match(&goal, match_data) { continue; }
+
+        let actual_value = match &goal.metric {
+            GoalMetric::Kills | GoalMetric::Deaths => {
+                let total = if goal.metric == GoalMetric::Kills { match_data.kills } else { match_data.deaths };
+                let timeline = events_map.get(&match_data.match_id).copied();
+                match events_at_minute(total, match_data.duration, target_minutes, timeline) {
+                    Some(v) => v,
+                    None => continue,
                 }
             }
             GoalMetric::LastHits => {
@@ -1881,13 +4038,31 @@ pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchD
                     None => continue,
                 }
             }
+            GoalMetric::LaneNetworthLead => {
+                let players = lane_roles_map.get(&match_data.match_id).map_or(&[][..], Vec::as_slice);
+                let Some((allies, enemies)) = lane_sides(match_data.player_slot, match_data.role, players) else {
+                    continue;
+                };
+                let total = |slots: &[i32]| -> Option<i32> {
+                    slots.iter().map(|&slot| nw_map.get(&(match_data.match_id, slot)).copied()).sum()
+                };
+                match (total(&allies), total(&enemies)) {
+                    (Some(own), Some(theirs)) => own - theirs,
+                    _ => continue,
+                }
+            }
             GoalMetric::ItemTiming => {
                 match item_timing_map.get(&match_data.match_id) {
                     Some(&timing) => timing,
                     None => continue,
                 }
             }
-            GoalMetric::Level => continue,
+            GoalMetric::Level => {
+                match xp_map.get(&match_data.match_id) {
+                    Some(&xp) => xp_to_level(xp),
+                    None => continue,
+                }
+            }
         };
 
         let achieved = match &goal.metric {
@@ -1909,30 +4084,230 @@ pub fn get_goal_match_data(conn: &Connection, goal_id: i64) -> Result<Vec<MatchD
     Ok(data_points)
 }
 
-/// Get a single goal by ID
-pub fn get_goal_by_id(conn: &Connection, goal_id: i64) -> Result<Goal, String> {
+/// The recent games a goal's status is judged over.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum GoalWindow {
+    /// The last N games the goal applies to
+    LastGames(u32),
+    /// Games started in the last N days
+    LastDays(u32),
+}
+
+impl Default for GoalWindow {
+    fn default() -> Self {
+        GoalWindow::LastGames(20)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum GoalVerdict {
+    OnTrack,
+    OffTrack,
+    /// No games in the window that the goal applies to
+    NotEnoughData,
+}
+
+/// 95% confidence interval
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct ConfidenceInterval {
+    pub low: f64,
+    pub high: f64,
+}
+
+/// Whether a goal is being met over a window of games, judged by its frequency type:
+/// JustOnce needs one achieved game, OnAverage compares the mean value with the target,
+/// and Pct50/75/90 need that share of games achieved.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GoalStatus {
+    pub goal: Goal,
+    pub window: GoalWindow,
+    pub games: i32,
+    pub achieved: i32,
+    /// Share of games achieved (0.0-1.0)
+    pub achievement_rate: f64,
+    /// Wilson score interval of the achievement rate
+    pub achievement_rate_ci: ConfidenceInterval,
+    /// Share of games the frequency type asks for (None for JustOnce and OnAverage)
+    pub required_rate: Option<f64>,
+    pub average_value: f64,
+    pub average_value_ci: ConfidenceInterval,
+    /// Least-squares change in the value per game, oldest game first
+    pub trend_per_game: f64,
+    /// Whether the trend moves towards the target (downwards for item timings and deaths)
+    pub improving: bool,
+    pub verdict: GoalVerdict,
+}
+
+/// Judge a goal over `window` from its per-match data points (see `get_goal_match_data`).
+pub fn get_goal_status(conn: &Connection, goal_id: i64, window: GoalWindow) -> Result<GoalStatus, DotaKeeperError> {
+    let goal = get_goal_by_id(conn, goal_id)?;
+    let points = get_goal_match_data(conn, goal_id)?;
+    Ok(goal_status_from_points(goal, window, points, chrono::Utc::now().timestamp()))
+}
+
+fn goal_status_from_points(goal: Goal, window: GoalWindow, mut points: Vec<MatchDataPoint>, now: i64) -> GoalStatus {
+    // Newest first, then cut to the window
+    points.sort_by_key(|p| std::cmp::Reverse(p.start_time));
+    match window {
+        GoalWindow::LastGames(n) => points.truncate(n as usize),
+        GoalWindow::LastDays(days) => points.retain(|p| p.start_time >= now - days as i64 * 86400),
+    }
+
+    let n = points.len() as f64;
+    let achieved = points.iter().filter(|p| p.achieved).count();
+    let rate = if points.is_empty() { 0.0 } else { achieved as f64 / n };
+    let values: Vec<f64> = points.iter().rev().map(|p| p.value as f64).collect();
+    let mean = if points.is_empty() { 0.0 } else { values.iter().sum::<f64>() / n };
+
+    const Z: f64 = 1.96;
+    let achievement_rate_ci = if points.is_empty() {
+        ConfidenceInterval { low: 0.0, high: 1.0 }
+    } else {
+        let denominator = 1.0 + Z * Z / n;
+        let center = (rate + Z * Z / (2.0 * n)) / denominator;
+        let half = Z * (rate * (1.0 - rate) / n + Z * Z / (4.0 * n * n)).sqrt() / denominator;
+        ConfidenceInterval { low: (center - half).max(0.0), high: (center + half).min(1.0) }
+    };
+    let average_value_ci = if values.len() < 2 {
+        ConfidenceInterval { low: mean, high: mean }
+    } else {
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let half = Z * (variance / n).sqrt();
+        ConfidenceInterval { low: mean - half, high: mean + half }
+    };
+
+    let trend_per_game = if values.len() < 2 {
+        0.0
+    } else {
+        let x_mean = (n - 1.0) / 2.0;
+        let (num, den) = values.iter().enumerate().fold((0.0, 0.0), |(num, den), (x, y)| {
+            let dx = x as f64 - x_mean;
+            (num + dx * (y - mean), den + dx * dx)
+        });
+        num / den
+    };
+
+    let lower_is_better = matches!(goal.metric, GoalMetric::ItemTiming | GoalMetric::Deaths);
+    let improving = if lower_is_better { trend_per_game < 0.0 } else { trend_per_game > 0.0 };
+    let required_rate = match goal.frequency_type {
+        FrequencyType::JustOnce | FrequencyType::OnAverage => None,
+        FrequencyType::Pct50 => Some(0.5),
+        FrequencyType::Pct75 => Some(0.75),
+        FrequencyType::Pct90 => Some(0.9),
+    };
+
+    let met = match goal.frequency_type {
+        FrequencyType::JustOnce => achieved > 0,
+        FrequencyType::OnAverage if lower_is_better => mean <= goal.target_value as f64,
+        FrequencyType::OnAverage => mean >= goal.target_value as f64,
+        _ => rate >= required_rate.unwrap_or(0.75),
+    };
+    let verdict = if points.is_empty() {
+        GoalVerdict::NotEnoughData
+    } else if met {
+        GoalVerdict::OnTrack
+    } else {
+        GoalVerdict::OffTrack
+    };
+
+    GoalStatus {
+        goal,
+        window,
+        games: points.len() as i32,
+        achieved: achieved as i32,
+        achievement_rate: rate,
+        achievement_rate_ci,
+        required_rate,
+        average_value: mean,
+        average_value_ci,
+        trend_per_game,
+        improving,
+        verdict,
+    }
+}
+
+/// A retired goal with the stats it had when it was achieved or archived.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RetiredGoal {
+    pub goal: Goal,
+    pub final_stats: Option<GoalStatus>,
+}
+
+/// Move a goal to `state`. Retiring it (achieved / archived) snapshots its status over
+/// `stats_window` as the final stats; bringing it back clears them. Returns the updated goal.
+pub fn set_goal_state(conn: &Connection, goal_id: i64, state: GoalState, stats_window: GoalWindow) -> Result<Goal, DotaKeeperError> {
+    let goal = get_goal_by_id(conn, goal_id)?;
+    if goal.state == state {
+        return Ok(goal);
+    }
+    let now = chrono::Utc::now().timestamp();
+    let final_stats = if state.is_retired() {
+        let status = get_goal_status(conn, goal_id, stats_window)?;
+        Some(serde_json::to_string(&status).map_err(|e| DotaKeeperError::Database(format!("Failed to serialize goal stats: {}", e)))?)
+    } else {
+        None
+    };
+    let achieved_at = if state == GoalState::Achieved { Some(now) } else { goal.achieved_at };
+
+    conn.execute(
+        "UPDATE goals SET state = ?1, state_changed_at = ?2, achieved_at = ?3, final_stats = ?4 WHERE id = ?5",
+        params![state.to_string(), now, achieved_at, final_stats, goal_id],
+    ).map_err(|e| DotaKeeperError::db("Failed to update goal state", e))?;
+
+    get_goal_by_id(conn, goal_id)
+}
+
+/// Achieved and archived goals, most recently retired first.
+pub fn get_retired_goals(conn: &Connection) -> Result<Vec<RetiredGoal>, DotaKeeperError> {
     let mut stmt = conn
-        .prepare("SELECT id, hero_id, metric, target_value, target_time_minutes, game_mode, created_at, item_id, hero_scope, frequency_type FROM goals WHERE id = ?1")
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
-
-    stmt.query_row(params![goal_id], |row| {
-        let metric_str: String = row.get(2)?;
-        let game_mode_str: String = row.get(5)?;
-        let freq_str: String = row.get(9).unwrap_or_else(|_| "pct_75".to_string());
-        Ok(Goal {
-            id: row.get(0)?,
-            hero_id: row.get(1)?,
-            metric: GoalMetric::from_string(&metric_str).unwrap_or(GoalMetric::Networth),
-            target_value: row.get(3)?,
-            target_time_minutes: row.get(4)?,
-            game_mode: GoalGameMode::from_string(&game_mode_str).unwrap_or(GoalGameMode::Ranked),
-            created_at: row.get(6)?,
-            item_id: row.get(7)?,
-            hero_scope: row.get(8).unwrap_or(None),
-            frequency_type: FrequencyType::from_string(&freq_str),
+        .prepare(&format!(
+            "SELECT {}, final_stats FROM goals WHERE state IN ('achieved', 'archived') ORDER BY state_changed_at DESC",
+            GOAL_COLUMNS
+        ))
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            let final_stats: Option<String> = row.get(16)?;
+            Ok(RetiredGoal {
+                goal: goal_from_row(row)?,
+                final_stats: final_stats.and_then(|json| serde_json::from_str(&json).ok()),
+            })
         })
-    })
-    .map_err(|e| format!("Failed to get goal: {}", e))
+        .map_err(|e| DotaKeeperError::db("Failed to query retired goals", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| DotaKeeperError::db("Failed to read retired goal", e))
+}
+
+/// Mark active goals achieved once their frequency criterion is met over the last `games`
+/// games played since the goal (last) became active: a JustOnce goal needs one achieved
+/// game, the others a full window of `games` games judged on track. Returns the goals promoted.
+pub fn promote_achieved_goals(conn: &Connection, games: u32) -> Result<Vec<Goal>, DotaKeeperError> {
+    let window = GoalWindow::LastGames(games);
+    let now = chrono::Utc::now().timestamp();
+    let mut promoted = Vec::new();
+    for goal in get_active_goals(conn)? {
+        // Games from before a goal was created or revived never complete it
+        let Some(active_since) = goal.state_changed_at else { continue };
+        let mut points = get_goal_match_data(conn, goal.id)?;
+        points.retain(|p| p.start_time >= active_since);
+        let (goal_id, just_once) = (goal.id, goal.frequency_type == FrequencyType::JustOnce);
+        let status = goal_status_from_points(goal, window, points, now);
+        let enough_games = just_once || status.games >= games as i32;
+        if enough_games && status.verdict == GoalVerdict::OnTrack {
+            promoted.push(set_goal_state(conn, goal_id, GoalState::Achieved, window)?);
+        }
+    }
+    Ok(promoted)
+}
+
+/// Get a single goal by ID
+pub fn get_goal_by_id(conn: &Connection, goal_id: i64) -> Result<Goal, DotaKeeperError> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT {} FROM goals WHERE id = ?1", GOAL_COLUMNS))
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
+
+    stmt.query_row(params![goal_id], goal_from_row)
+        .map_err(|e| DotaKeeperError::db("Failed to get goal", e))
 }
 
 /// Last hits analysis data point
@@ -1985,7 +4360,7 @@ pub fn get_last_hits_analysis(
     window_size: usize,
     hero_id_filter: Option<i32>,
     game_mode_filter: Option<i32>,
-) -> Result<LastHitsAnalysis, String> {
+) -> Result<LastHitsAnalysis, DotaKeeperError> {
     // Build the query with filters
     let mut query = String::from(
         "SELECT m.match_id, m.hero_id, m.start_time, m.game_mode, mc.last_hits,
@@ -2011,7 +4386,7 @@ pub fn get_last_hits_analysis(
 
     let mut stmt = conn
         .prepare(&query)
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     // Build params vector
     let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(time_minutes)];
@@ -2044,11 +4419,11 @@ pub fn get_last_hits_analysis(
                 won,
             })
         })
-        .map_err(|e| format!("Failed to query matches: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query matches", e))?;
 
     let mut data_points = Vec::new();
     for row in rows {
-        data_points.push(row.map_err(|e| format!("Failed to read row: {}", e))?);
+        data_points.push(row.map_err(|e| DotaKeeperError::db("Failed to read row", e))?);
     }
 
     // Calculate current period stats (last N games)
@@ -2219,7 +4594,7 @@ fn calculate_hero_trend(hero_id: i32, all_data: &[LastHitsDataPoint], window_siz
 }
 
 /// Toggle hero favorite status
-pub fn toggle_hero_favorite(conn: &Connection, hero_id: i32) -> Result<bool, String> {
+pub fn toggle_hero_favorite(conn: &Connection, hero_id: i32) -> Result<bool, DotaKeeperError> {
     // Check if hero is currently favorited
     let is_favorite = is_hero_favorite(conn, hero_id)?;
 
@@ -2228,51 +4603,51 @@ pub fn toggle_hero_favorite(conn: &Connection, hero_id: i32) -> Result<bool, Str
         conn.execute(
             "DELETE FROM hero_favorites WHERE hero_id = ?1",
             params![hero_id],
-        ).map_err(|e| format!("Failed to remove hero from favorites: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to remove hero from favorites", e))?;
         Ok(false)
     } else {
         // Add to favorites
         conn.execute(
             "INSERT INTO hero_favorites (hero_id) VALUES (?1)",
             params![hero_id],
-        ).map_err(|e| format!("Failed to add hero to favorites: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to add hero to favorites", e))?;
         Ok(true)
     }
 }
 
 /// Check if a hero is favorited
-pub fn is_hero_favorite(conn: &Connection, hero_id: i32) -> Result<bool, String> {
+pub fn is_hero_favorite(conn: &Connection, hero_id: i32) -> Result<bool, DotaKeeperError> {
     let count: i32 = conn
         .query_row(
             "SELECT COUNT(*) FROM hero_favorites WHERE hero_id = ?1",
             params![hero_id],
             |row| row.get(0),
         )
-        .map_err(|e| format!("Failed to check if hero is favorite: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to check if hero is favorite", e))?;
 
     Ok(count > 0)
 }
 
 /// Get all favorite hero IDs
-pub fn get_favorite_hero_ids(conn: &Connection) -> Result<Vec<i32>, String> {
+pub fn get_favorite_hero_ids(conn: &Connection) -> Result<Vec<i32>, DotaKeeperError> {
     let mut stmt = conn
         .prepare("SELECT hero_id FROM hero_favorites")
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let hero_ids = stmt
         .query_map([], |row| row.get(0))
-        .map_err(|e| format!("Failed to query favorite heroes: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query favorite heroes", e))?;
 
     let mut result = Vec::new();
     for id in hero_ids {
-        result.push(id.map_err(|e| format!("Failed to read hero ID: {}", e))?);
+        result.push(id.map_err(|e| DotaKeeperError::db("Failed to read hero ID", e))?);
     }
 
     Ok(result)
 }
 
 /// Get current hero goal suggestion if it exists and is less than 7 days old
-pub fn get_current_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, String> {
+pub fn get_current_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, DotaKeeperError> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -2307,22 +4682,22 @@ pub fn get_current_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalS
             }
         }
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(format!("Failed to query hero suggestion: {}", e)),
+        Err(e) => Err(DotaKeeperError::db("Failed to query hero suggestion", e)),
     }
 }
 
 /// Generate a new hero goal suggestion based on recent gameplay
-pub fn generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, String> {
+pub fn generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, DotaKeeperError> {
     // Get last 20 matches ordered by start_time
     let mut stmt = conn
         .prepare("SELECT hero_id FROM matches ORDER BY start_time DESC LIMIT 20")
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let hero_ids: Vec<i32> = stmt
         .query_map([], |row| row.get(0))
-        .map_err(|e| format!("Failed to query recent matches: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query recent matches", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect hero IDs: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to collect hero IDs", e))?;
 
     if hero_ids.is_empty() {
         return Ok(None);
@@ -2337,7 +4712,7 @@ pub fn generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSugg
                 params![hero_id],
                 |row| row.get(0),
             )
-            .map_err(|e| format!("Failed to count games for hero {}: {}", hero_id, e))?;
+            .map_err(|e| DotaKeeperError::db(format!("Failed to count games for hero {}", hero_id), e))?;
 
         hero_game_counts.insert(hero_id, count);
     }
@@ -2371,13 +4746,13 @@ pub fn generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSugg
              ORDER BY m.start_time DESC
              LIMIT 5"
         )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let last_hits_values: Vec<i32> = stmt
         .query_map(params![selected_hero], |row| row.get::<_, i32>(2))
-        .map_err(|e| format!("Failed to query last hits: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query last hits", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect last hits: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to collect last hits", e))?;
 
     if last_hits_values.is_empty() {
         return Ok(None);
@@ -2415,7 +4790,7 @@ pub fn generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSugg
 }
 
 /// Save a hero goal suggestion to the database
-pub fn save_hero_suggestion(conn: &Connection, suggestion: &HeroGoalSuggestion) -> Result<(), String> {
+pub fn save_hero_suggestion(conn: &Connection, suggestion: &HeroGoalSuggestion) -> Result<(), DotaKeeperError> {
     conn.execute(
         "INSERT OR REPLACE INTO hero_goal_suggestions
          (id, hero_id, suggested_last_hits, current_average, created_at, games_analyzed)
@@ -2428,13 +4803,13 @@ pub fn save_hero_suggestion(conn: &Connection, suggestion: &HeroGoalSuggestion)
             suggestion.games_analyzed,
         ],
     )
-    .map_err(|e| format!("Failed to save hero suggestion: {}", e))?;
+    .map_err(|e| DotaKeeperError::db("Failed to save hero suggestion", e))?;
 
     Ok(())
 }
 
 /// Get or generate a hero goal suggestion
-pub fn get_or_generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, String> {
+pub fn get_or_generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, DotaKeeperError> {
     // Check if we have a current valid suggestion
     if let Some(suggestion) = get_current_hero_suggestion(conn)? {
         return Ok(Some(suggestion));
@@ -2450,7 +4825,7 @@ pub fn get_or_generate_hero_suggestion(conn: &Connection) -> Result<Option<HeroG
 }
 
 /// Force regenerate a new hero goal suggestion (ignores current suggestion age)
-pub fn regenerate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, String> {
+pub fn regenerate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSuggestion>, DotaKeeperError> {
     // Generate a new suggestion
     if let Some(suggestion) = generate_hero_suggestion(conn)? {
         save_hero_suggestion(conn, &suggestion)?;
@@ -2461,14 +4836,14 @@ pub fn regenerate_hero_suggestion(conn: &Connection) -> Result<Option<HeroGoalSu
 }
 
 /// Remove all item timings for a match (called before re-inserting on parse/re-parse)
-pub fn clear_item_timings_for_match(conn: &Connection, match_id: i64) -> Result<(), String> {
+pub fn clear_item_timings_for_match(conn: &Connection, match_id: i64) -> Result<(), DotaKeeperError> {
     conn.execute("DELETE FROM item_timings WHERE match_id = ?1", params![match_id])
-        .map_err(|e| format!("Failed to clear item timings: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to clear item timings", e))?;
     Ok(())
 }
 
 /// Insert a single item timing for a match
-pub fn insert_item_timing(conn: &Connection, timing: &NewItemTiming) -> Result<(), String> {
+pub fn insert_item_timing(conn: &Connection, timing: &NewItemTiming) -> Result<(), DotaKeeperError> {
     conn.execute(
         "INSERT INTO item_timings (match_id, item_id, timing_seconds)
          VALUES (?1, ?2, ?3)",
@@ -2477,13 +4852,13 @@ pub fn insert_item_timing(conn: &Connection, timing: &NewItemTiming) -> Result<(
             timing.item_id,
             timing.timing_seconds,
         ],
-    ).map_err(|e| format!("Failed to insert item timing: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to insert item timing", e))?;
 
     Ok(())
 }
 
 /// Get item timings for a specific match
-pub fn get_item_timings_for_match(conn: &Connection, match_id: i64) -> Result<Vec<ItemTiming>, String> {
+pub fn get_item_timings_for_match(conn: &Connection, match_id: i64) -> Result<Vec<ItemTiming>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT id, match_id, item_id, timing_seconds
@@ -2491,7 +4866,7 @@ pub fn get_item_timings_for_match(conn: &Connection, match_id: i64) -> Result<Ve
              WHERE match_id = ?1
              ORDER BY timing_seconds ASC",
         )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let timings = stmt
         .query_map(params![match_id], |row| {
@@ -2502,18 +4877,18 @@ pub fn get_item_timings_for_match(conn: &Connection, match_id: i64) -> Result<Ve
                 timing_seconds: row.get(3)?,
             })
         })
-        .map_err(|e| format!("Failed to query item timings: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query item timings", e))?;
 
     let mut result = Vec::new();
     for timing in timings {
-        result.push(timing.map_err(|e| format!("Failed to read item timing: {}", e))?);
+        result.push(timing.map_err(|e| DotaKeeperError::db("Failed to read item timing", e))?);
     }
 
     Ok(result)
 }
 
 /// Get timing for a specific item in a match (returns None if item wasn't purchased)
-pub fn get_item_timing(conn: &Connection, match_id: i64, item_id: i32) -> Result<Option<i32>, String> {
+pub fn get_item_timing(conn: &Connection, match_id: i64, item_id: i32) -> Result<Option<i32>, DotaKeeperError> {
     let result = conn.query_row(
         "SELECT timing_seconds FROM item_timings WHERE match_id = ?1 AND item_id = ?2",
         params![match_id, item_id],
@@ -2523,7 +4898,7 @@ pub fn get_item_timing(conn: &Connection, match_id: i64, item_id: i32) -> Result
     match result {
         Ok(timing) => Ok(Some(timing)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(format!("Failed to query item timing: {}", e)),
+        Err(e) => Err(DotaKeeperError::db("Failed to query item timing", e)),
     }
 }
 
@@ -2595,24 +4970,25 @@ fn row_to_match(row: &rusqlite::Row) -> rusqlite::Result<Match> {
         role: row.get(19).unwrap_or(0),
         rank_tier: row.get(20).ok(),
         patch: row.get(21).ok(),
+        parsed_by: row.get(22).ok(),
     })
 }
 
 /// Get all matches since a given timestamp
-fn get_matches_since(conn: &Connection, since_timestamp: i64) -> Result<Vec<Match>, String> {
+fn get_matches_since(conn: &Connection, since_timestamp: i64) -> Result<Vec<Match>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT match_id, hero_id, start_time, duration, game_mode, lobby_type,
                     radiant_win, player_slot, kills, deaths, assists, xp_per_min,
-                    gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch
+                    gold_per_min, last_hits, denies, hero_damage, tower_damage, hero_healing, parse_state, role, rank_tier, patch, parsed_by
              FROM matches WHERE start_time >= ?1 ORDER BY start_time DESC",
         )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let result = stmt.query_map(params![since_timestamp], row_to_match)
-        .map_err(|e| format!("Failed to query matches: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query matches", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to read match: {}", e));
+        .map_err(|e| DotaKeeperError::db("Failed to read match", e));
     result
 }
 
@@ -2717,7 +5093,7 @@ fn pick_unfamiliar_hero(conn: &Connection) -> Option<i32> {
 }
 
 /// Archive any active daily challenges from past days as failed
-pub fn archive_expired_daily_challenges(conn: &Connection) -> Result<(), String> {
+pub fn archive_expired_daily_challenges(conn: &Connection) -> Result<(), DotaKeeperError> {
     let today = get_today_date_string();
 
     // Find active challenges before today
@@ -2727,29 +5103,29 @@ pub fn archive_expired_daily_challenges(conn: &Connection) -> Result<(), String>
              FROM daily_challenges
              WHERE status = 'active' AND challenge_date < ?1",
         )
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let expired: Vec<(i64, String, String)> = stmt
         .query_map(params![today], |row| {
             Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
         })
-        .map_err(|e| format!("Failed to query expired challenges: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query expired challenges", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect expired challenges: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to collect expired challenges", e))?;
 
     for (id, date, description) in &expired {
         conn.execute(
             "UPDATE daily_challenges SET status = 'failed' WHERE id = ?1",
             params![id],
         )
-        .map_err(|e| format!("Failed to update challenge status: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to update challenge status", e))?;
 
         conn.execute(
             "INSERT INTO challenge_history (challenge_type, period_start_date, challenge_description, status, completed_at, target_achieved)
              VALUES ('daily', ?1, ?2, 'failed', NULL, NULL)",
             params![date, description],
         )
-        .map_err(|e| format!("Failed to archive challenge: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to archive challenge", e))?;
     }
 
     Ok(())
@@ -2759,7 +5135,7 @@ pub fn archive_expired_daily_challenges(conn: &Connection) -> Result<(), String>
 fn generate_daily_challenge_for_date(
     conn: &Connection,
     date: &str,
-) -> Result<Option<DailyChallenge>, String> {
+) -> Result<Option<DailyChallenge>, DotaKeeperError> {
     use rand::Rng;
     let mut rng = rand::thread_rng();
 
@@ -2935,7 +5311,7 @@ fn generate_daily_challenge_for_date(
             now,
         ],
     )
-    .map_err(|e| format!("Failed to insert daily challenge: {}", e))?;
+    .map_err(|e| DotaKeeperError::db("Failed to insert daily challenge", e))?;
 
     // Read back to get id
     conn.query_row(
@@ -2946,11 +5322,11 @@ fn generate_daily_challenge_for_date(
         row_to_daily_challenge,
     )
     .map(Some)
-    .map_err(|e| format!("Failed to read back daily challenge: {}", e))
+    .map_err(|e| DotaKeeperError::db("Failed to read back daily challenge", e))
 }
 
 /// Get or generate the daily challenge for today
-pub fn get_or_generate_daily_challenge(conn: &Connection) -> Result<Option<DailyChallenge>, String> {
+pub fn get_or_generate_daily_challenge(conn: &Connection) -> Result<Option<DailyChallenge>, DotaKeeperError> {
     let today = get_today_date_string();
 
     // Archive any expired active challenges
@@ -2968,7 +5344,7 @@ pub fn get_or_generate_daily_challenge(conn: &Connection) -> Result<Option<Daily
     match existing {
         Ok(challenge) => Ok(Some(challenge)),
         Err(rusqlite::Error::QueryReturnedNoRows) => generate_daily_challenge_for_date(conn, &today),
-        Err(e) => Err(format!("Failed to query daily challenge: {}", e)),
+        Err(e) => Err(DotaKeeperError::db("Failed to query daily challenge", e)),
     }
 }
 
@@ -2976,7 +5352,7 @@ pub fn get_or_generate_daily_challenge(conn: &Connection) -> Result<Option<Daily
 pub fn evaluate_daily_challenge_progress(
     conn: &Connection,
     challenge: &DailyChallenge,
-) -> Result<DailyChallengeProgress, String> {
+) -> Result<DailyChallengeProgress, DotaKeeperError> {
     // If already completed, return complete state
     if challenge.status == "completed" {
         return Ok(DailyChallengeProgress {
@@ -3055,7 +5431,7 @@ pub fn evaluate_daily_challenge_progress(
             "UPDATE daily_challenges SET status = 'completed', completed_at = ?1 WHERE id = ?2",
             params![now, challenge.id],
         )
-        .map_err(|e| format!("Failed to mark challenge complete: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to mark challenge complete", e))?;
 
         conn.execute(
             "INSERT INTO challenge_history
@@ -3068,7 +5444,7 @@ pub fn evaluate_daily_challenge_progress(
                 current_value,
             ],
         )
-        .map_err(|e| format!("Failed to archive completed challenge: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to archive completed challenge", e))?;
     }
 
     Ok(DailyChallengeProgress {
@@ -3083,7 +5459,7 @@ pub fn evaluate_daily_challenge_progress(
 /// Get or generate today's challenge and evaluate its progress
 pub fn get_daily_challenge_progress(
     conn: &Connection,
-) -> Result<Option<DailyChallengeProgress>, String> {
+) -> Result<Option<DailyChallengeProgress>, DotaKeeperError> {
     match get_or_generate_daily_challenge(conn)? {
         Some(challenge) => Ok(Some(evaluate_daily_challenge_progress(conn, &challenge)?)),
         None => Ok(None),
@@ -3091,7 +5467,7 @@ pub fn get_daily_challenge_progress(
 }
 
 /// Count consecutive days (ending yesterday) where the daily challenge was completed
-pub fn get_daily_streak(conn: &Connection) -> Result<i32, String> {
+pub fn get_daily_streak(conn: &Connection) -> Result<i32, DotaKeeperError> {
     use chrono::{Duration, Local};
 
     let mut streak = 0i32;
@@ -3399,7 +5775,7 @@ fn pick_3_diverse_options(candidates: Vec<WeeklyCandidate>, _reroll_gen: i32) ->
 }
 
 /// Get or generate the 3 weekly challenge options for the current week
-pub fn get_weekly_challenge_options(conn: &Connection) -> Result<Vec<ChallengeOption>, String> {
+pub fn get_weekly_challenge_options(conn: &Connection) -> Result<Vec<ChallengeOption>, DotaKeeperError> {
     let week_start = get_week_start_date();
 
     // Check existing options for this week
@@ -3407,13 +5783,13 @@ pub fn get_weekly_challenge_options(conn: &Connection) -> Result<Vec<ChallengeOp
         "SELECT id, week_start_date, challenge_type, challenge_description, challenge_target,
                 challenge_target_games, hero_id, metric, option_index, reroll_generation
          FROM challenge_options WHERE week_start_date = ?1 ORDER BY option_index",
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let existing: Vec<ChallengeOption> = stmt
         .query_map(params![week_start], row_to_challenge_option)
-        .map_err(|e| format!("Failed to query options: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query options", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect options: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to collect options", e))?;
 
     if !existing.is_empty() {
         return Ok(existing);
@@ -3423,7 +5799,7 @@ pub fn get_weekly_challenge_options(conn: &Connection) -> Result<Vec<ChallengeOp
     generate_and_save_weekly_options(conn, &week_start, 0)
 }
 
-fn generate_and_save_weekly_options(conn: &Connection, week_start: &str, reroll_gen: i32) -> Result<Vec<ChallengeOption>, String> {
+fn generate_and_save_weekly_options(conn: &Connection, week_start: &str, reroll_gen: i32) -> Result<Vec<ChallengeOption>, DotaKeeperError> {
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
@@ -3436,7 +5812,7 @@ fn generate_and_save_weekly_options(conn: &Connection, week_start: &str, reroll_
     conn.execute(
         "DELETE FROM challenge_options WHERE week_start_date = ?1",
         params![week_start],
-    ).map_err(|e| format!("Failed to delete old options: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to delete old options", e))?;
 
     for (i, c) in chosen.iter().enumerate() {
         conn.execute(
@@ -3456,7 +5832,7 @@ fn generate_and_save_weekly_options(conn: &Connection, week_start: &str, reroll_
                 reroll_gen,
                 now,
             ],
-        ).map_err(|e| format!("Failed to insert option: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to insert option", e))?;
     }
 
     // Re-read from DB to get proper IDs
@@ -3464,17 +5840,17 @@ fn generate_and_save_weekly_options(conn: &Connection, week_start: &str, reroll_
         "SELECT id, week_start_date, challenge_type, challenge_description, challenge_target,
                 challenge_target_games, hero_id, metric, option_index, reroll_generation
          FROM challenge_options WHERE week_start_date = ?1 ORDER BY option_index",
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let result = stmt.query_map(params![week_start], row_to_challenge_option)
-        .map_err(|e| format!("Failed to query options: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query options", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect options: {}", e));
+        .map_err(|e| DotaKeeperError::db("Failed to collect options", e));
     result
 }
 
 /// Reroll weekly challenge options (max 2 rerolls per week)
-pub fn reroll_weekly_challenges(conn: &Connection) -> Result<Vec<ChallengeOption>, String> {
+pub fn reroll_weekly_challenges(conn: &Connection) -> Result<Vec<ChallengeOption>, DotaKeeperError> {
     let week_start = get_week_start_date();
 
     // Check if there's already an accepted challenge for this week
@@ -3484,7 +5860,7 @@ pub fn reroll_weekly_challenges(conn: &Connection) -> Result<Vec<ChallengeOption
         |row| row.get(0),
     );
     if accepted.is_ok() {
-        return Err("Cannot reroll after accepting a challenge".to_string());
+        return Err(DotaKeeperError::InvalidInput("Cannot reroll after accepting a challenge".to_string()));
     }
 
     // Check current reroll count
@@ -3495,14 +5871,14 @@ pub fn reroll_weekly_challenges(conn: &Connection) -> Result<Vec<ChallengeOption
     ).unwrap_or(0);
 
     if current_gen >= 2 {
-        return Err("Maximum rerolls (2) used for this week".to_string());
+        return Err(DotaKeeperError::InvalidInput("Maximum rerolls (2) used for this week".to_string()));
     }
 
     generate_and_save_weekly_options(conn, &week_start, current_gen + 1)
 }
 
 /// Skip the weekly challenge (mark week as skipped, no options)
-pub fn skip_weekly_challenge(conn: &Connection) -> Result<(), String> {
+pub fn skip_weekly_challenge(conn: &Connection) -> Result<(), DotaKeeperError> {
     let week_start = get_week_start_date();
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -3515,13 +5891,13 @@ pub fn skip_weekly_challenge(conn: &Connection) -> Result<(), String> {
           metric, status, accepted_at)
          VALUES (?1, 'skipped', 'Skipped this week', 0, 'skipped', 'skipped', ?2)",
         params![week_start, now],
-    ).map_err(|e| format!("Failed to skip challenge: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to skip challenge", e))?;
 
     Ok(())
 }
 
 /// Accept a weekly challenge option
-pub fn accept_weekly_challenge(conn: &Connection, option_id: i64) -> Result<WeeklyChallenge, String> {
+pub fn accept_weekly_challenge(conn: &Connection, option_id: i64) -> Result<WeeklyChallenge, DotaKeeperError> {
     let week_start = get_week_start_date();
 
     // Check no challenge accepted yet
@@ -3531,7 +5907,7 @@ pub fn accept_weekly_challenge(conn: &Connection, option_id: i64) -> Result<Week
         |row| row.get(0),
     );
     if existing.is_ok() {
-        return Err("A challenge has already been accepted for this week".to_string());
+        return Err(DotaKeeperError::InvalidInput("A challenge has already been accepted for this week".to_string()));
     }
 
     // Get the option
@@ -3541,7 +5917,7 @@ pub fn accept_weekly_challenge(conn: &Connection, option_id: i64) -> Result<Week
          FROM challenge_options WHERE id = ?1",
         params![option_id],
         row_to_challenge_option,
-    ).map_err(|e| format!("Option not found: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Option not found", e))?;
 
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -3571,7 +5947,7 @@ pub fn accept_weekly_challenge(conn: &Connection, option_id: i64) -> Result<Week
             now,
             reroll_count,
         ],
-    ).map_err(|e| format!("Failed to accept challenge: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to accept challenge", e))?;
 
     conn.query_row(
         "SELECT id, week_start_date, challenge_type, challenge_description, challenge_target,
@@ -3579,11 +5955,11 @@ pub fn accept_weekly_challenge(conn: &Connection, option_id: i64) -> Result<Week
          FROM weekly_challenges WHERE week_start_date = ?1 AND status = 'active'",
         params![week_start],
         row_to_weekly_challenge,
-    ).map_err(|e| format!("Failed to read back weekly challenge: {}", e))
+    ).map_err(|e| DotaKeeperError::db("Failed to read back weekly challenge", e))
 }
 
 /// Get the active weekly challenge for the current week (if accepted)
-pub fn get_active_weekly_challenge(conn: &Connection) -> Result<Option<WeeklyChallenge>, String> {
+pub fn get_active_weekly_challenge(conn: &Connection) -> Result<Option<WeeklyChallenge>, DotaKeeperError> {
     let week_start = get_week_start_date();
 
     // Archive expired weekly challenges from previous weeks
@@ -3598,12 +5974,12 @@ pub fn get_active_weekly_challenge(conn: &Connection) -> Result<Option<WeeklyCha
     ) {
         Ok(c) => Ok(Some(c)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(format!("Failed to query weekly challenge: {}", e)),
+        Err(e) => Err(DotaKeeperError::db("Failed to query weekly challenge", e)),
     }
 }
 
 /// Evaluate progress of the active weekly challenge
-pub fn get_weekly_challenge_progress(conn: &Connection) -> Result<Option<WeeklyChallengeProgress>, String> {
+pub fn get_weekly_challenge_progress(conn: &Connection) -> Result<Option<WeeklyChallengeProgress>, DotaKeeperError> {
     let challenge = match get_active_weekly_challenge(conn)? {
         Some(c) => c,
         None => return Ok(None),
@@ -3705,14 +6081,14 @@ pub fn get_weekly_challenge_progress(conn: &Connection) -> Result<Option<WeeklyC
         conn.execute(
             "UPDATE weekly_challenges SET status = 'completed', completed_at = ?1 WHERE id = ?2",
             params![now, challenge.id],
-        ).map_err(|e| format!("Failed to mark weekly complete: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to mark weekly complete", e))?;
 
         conn.execute(
             "INSERT OR IGNORE INTO challenge_history
              (challenge_type, period_start_date, challenge_description, status, completed_at, target_achieved)
              VALUES ('weekly', ?1, ?2, 'completed', ?3, ?4)",
             params![challenge.week_start_date, challenge.challenge_description, now, current_value],
-        ).map_err(|e| format!("Failed to archive weekly challenge: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to archive weekly challenge", e))?;
     }
 
     Ok(Some(WeeklyChallengeProgress {
@@ -3726,34 +6102,34 @@ pub fn get_weekly_challenge_progress(conn: &Connection) -> Result<Option<WeeklyC
 }
 
 /// Archive any active weekly challenges from past weeks as failed
-fn archive_expired_weekly_challenges(conn: &Connection) -> Result<(), String> {
+fn archive_expired_weekly_challenges(conn: &Connection) -> Result<(), DotaKeeperError> {
     let week_start = get_week_start_date();
 
     let mut stmt = conn.prepare(
         "SELECT id, week_start_date, challenge_description FROM weekly_challenges
          WHERE status = 'active' AND week_start_date < ?1",
-    ).map_err(|e| format!("Failed to prepare query: {}", e))?;
+    ).map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let expired: Vec<(i64, String, String)> = stmt
         .query_map(params![week_start], |row| {
             Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
         })
-        .map_err(|e| format!("Failed to query expired weekly challenges: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query expired weekly challenges", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect expired: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to collect expired", e))?;
 
     for (id, wstart, desc) in &expired {
         conn.execute(
             "UPDATE weekly_challenges SET status = 'failed' WHERE id = ?1",
             params![id],
-        ).map_err(|e| format!("Failed to update weekly status: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to update weekly status", e))?;
 
         conn.execute(
             "INSERT OR IGNORE INTO challenge_history
              (challenge_type, period_start_date, challenge_description, status, completed_at, target_achieved)
              VALUES ('weekly', ?1, ?2, 'failed', NULL, NULL)",
             params![wstart, desc],
-        ).map_err(|e| format!("Failed to archive weekly challenge: {}", e))?;
+        ).map_err(|e| DotaKeeperError::db("Failed to archive weekly challenge", e))?;
     }
 
     Ok(())
@@ -3764,7 +6140,7 @@ pub fn get_challenge_history(
     conn: &Connection,
     challenge_type_filter: Option<String>,
     limit: i32,
-) -> Result<Vec<ChallengeHistoryItem>, String> {
+) -> Result<Vec<ChallengeHistoryItem>, DotaKeeperError> {
     let query = match challenge_type_filter.as_deref() {
         Some("weekly") => {
             "SELECT id, challenge_type, period_start_date, challenge_description, status, completed_at, target_achieved
@@ -3784,12 +6160,12 @@ pub fn get_challenge_history(
     };
 
     let mut stmt = conn.prepare(query)
-        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare query", e))?;
 
     let result = stmt.query_map(params![limit], row_to_history_item)
-        .map_err(|e| format!("Failed to query history: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query history", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect history: {}", e));
+        .map_err(|e| DotaKeeperError::db("Failed to collect history", e));
     result
 }
 
@@ -3811,7 +6187,7 @@ pub struct MedalStats {
 }
 
 /// Get all matches that have rank_tier data, ordered chronologically
-pub fn get_medal_history(conn: &Connection) -> Result<Vec<MedalEntry>, String> {
+pub fn get_medal_history(conn: &Connection) -> Result<Vec<MedalEntry>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT match_id, start_time, rank_tier
@@ -3819,7 +6195,7 @@ pub fn get_medal_history(conn: &Connection) -> Result<Vec<MedalEntry>, String> {
              WHERE rank_tier IS NOT NULL
              ORDER BY start_time ASC",
         )
-        .map_err(|e| format!("Failed to prepare medal history query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare medal history query", e))?;
 
     let entries = stmt
         .query_map([], |row| {
@@ -3829,15 +6205,15 @@ pub fn get_medal_history(conn: &Connection) -> Result<Vec<MedalEntry>, String> {
                 rank_tier: row.get(2)?,
             })
         })
-        .map_err(|e| format!("Failed to query medal history: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query medal history", e))?
         .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to read medal entry: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to read medal entry", e))?;
 
     Ok(entries)
 }
 
 /// Get current and peak medal stats
-pub fn get_medal_stats(conn: &Connection) -> Result<MedalStats, String> {
+pub fn get_medal_stats(conn: &Connection) -> Result<MedalStats, DotaKeeperError> {
     let current_rank_tier: Option<i32> = conn
         .query_row(
             "SELECT rank_tier FROM matches WHERE rank_tier IS NOT NULL ORDER BY start_time DESC LIMIT 1",
@@ -3845,7 +6221,7 @@ pub fn get_medal_stats(conn: &Connection) -> Result<MedalStats, String> {
             |row| row.get(0),
         )
         .optional()
-        .map_err(|e| format!("Failed to query current medal: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query current medal", e))?
         .flatten();
 
     let peak_rank_tier: Option<i32> = conn
@@ -3855,7 +6231,7 @@ pub fn get_medal_stats(conn: &Connection) -> Result<MedalStats, String> {
             |row| row.get(0),
         )
         .optional()
-        .map_err(|e| format!("Failed to query peak medal: {}", e))?
+        .map_err(|e| DotaKeeperError::db("Failed to query peak medal", e))?
         .flatten();
 
     Ok(MedalStats {
@@ -3904,21 +6280,21 @@ pub struct BenchmarkResult {
 }
 
 /// Upsert benchmark rows parsed from the CSV into the database.
-pub fn upsert_benchmarks(conn: &Connection, rows: &[HeroBenchmarkRow]) -> Result<(), String> {
+pub fn upsert_benchmarks(conn: &Connection, rows: &[HeroBenchmarkRow]) -> Result<(), DotaKeeperError> {
     let tx = conn
         .unchecked_transaction()
-        .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to begin transaction", e))?;
 
     // Clear old data and insert fresh
     tx.execute("DELETE FROM hero_benchmarks", [])
-        .map_err(|e| format!("Failed to clear hero_benchmarks: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to clear hero_benchmarks", e))?;
 
     let mut stmt = tx
         .prepare(
             "INSERT INTO hero_benchmarks (hero_id, mode, bracket, stat_name, mean, std_dev, data_date, sample_size, ideal_match_id_avg, ideal_match_id_top)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
         )
-        .map_err(|e| format!("Failed to prepare benchmark insert: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare benchmark insert", e))?;
 
     for row in rows {
         stmt.execute(params![
@@ -3933,34 +6309,34 @@ pub fn upsert_benchmarks(conn: &Connection, rows: &[HeroBenchmarkRow]) -> Result
             row.ideal_match_id_avg,
             row.ideal_match_id_top,
         ])
-        .map_err(|e| format!("Failed to insert benchmark row: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to insert benchmark row", e))?;
     }
     drop(stmt);
 
     tx.commit()
-        .map_err(|e| format!("Failed to commit benchmarks: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to commit benchmarks", e))?;
     Ok(())
 }
 
 /// Store the last-fetched date for benchmarks.
-pub fn set_benchmark_metadata(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+pub fn set_benchmark_metadata(conn: &Connection, key: &str, value: &str) -> Result<(), DotaKeeperError> {
     conn.execute(
         "INSERT OR REPLACE INTO benchmark_metadata (key, value) VALUES (?1, ?2)",
         params![key, value],
     )
-    .map_err(|e| format!("Failed to set benchmark metadata: {}", e))?;
+    .map_err(|e| DotaKeeperError::db("Failed to set benchmark metadata", e))?;
     Ok(())
 }
 
 /// Get a benchmark metadata value.
-pub fn get_benchmark_metadata(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+pub fn get_benchmark_metadata(conn: &Connection, key: &str) -> Result<Option<String>, DotaKeeperError> {
     conn.query_row(
         "SELECT value FROM benchmark_metadata WHERE key = ?1",
         params![key],
         |row| row.get(0),
     )
     .optional()
-    .map_err(|e| format!("Failed to get benchmark metadata: {}", e))
+    .map_err(|e| DotaKeeperError::db("Failed to get benchmark metadata", e))
 }
 
 /// Query benchmarks for a given hero + mode + stat, and compute z-scores for a user value.
@@ -3970,7 +6346,7 @@ pub fn get_benchmark_comparison(
     mode: &str,
     stat_name: &str,
     user_value: f64,
-) -> Result<BenchmarkResult, String> {
+) -> Result<BenchmarkResult, DotaKeeperError> {
     let bracket_order = [
         "herald", "guardian", "crusader", "archon", "legend", "ancient", "divine", "immortal",
     ];
@@ -3981,7 +6357,7 @@ pub fn get_benchmark_comparison(
              FROM hero_benchmarks
              WHERE hero_id = ?1 AND mode = ?2 AND stat_name = ?3"
         )
-        .map_err(|e| format!("Failed to prepare benchmark query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare benchmark query", e))?;
 
     let rows_iter = stmt
         .query_map(params![hero_id, mode, stat_name], |row| {
@@ -3995,11 +6371,11 @@ pub fn get_benchmark_comparison(
                 row.get::<_, Option<i64>>(6)?,
             ))
         })
-        .map_err(|e| format!("Failed to query benchmarks: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query benchmarks", e))?;
 
     let mut bracket_data: HashMap<String, (f64, f64, String, i32, Option<i64>, Option<i64>)> = HashMap::new();
     for row in rows_iter {
-        let (bracket, mean, std_dev, data_date, sample_size, avg_id, top_id) = row.map_err(|e| format!("Row error: {}", e))?;
+        let (bracket, mean, std_dev, data_date, sample_size, avg_id, top_id) = row.map_err(|e| DotaKeeperError::db("Row error", e))?;
         bracket_data.insert(bracket, (mean, std_dev, data_date, sample_size, avg_id, top_id));
     }
 
@@ -4074,7 +6450,7 @@ pub fn get_user_stat_std_dev(
     game_mode: Option<i32>,
     time_minutes: i32,
     window_size: usize,
-) -> Result<Option<f64>, String> {
+) -> Result<Option<f64>, DotaKeeperError> {
     let mut query = String::from(
         "SELECT mc.last_hits
          FROM matches m
@@ -4096,7 +6472,7 @@ pub fn get_user_stat_std_dev(
 
     let mut stmt = conn
         .prepare(&query)
-        .map_err(|e| format!("Failed to prepare user SD query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare user SD query", e))?;
 
     let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(time_minutes)];
     if let Some(h) = hero_id {
@@ -4110,7 +6486,7 @@ pub fn get_user_stat_std_dev(
 
     let rows = stmt
         .query_map(&params_refs[..], |row| row.get::<_, i32>(0))
-        .map_err(|e| format!("Failed to query user stats: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query user stats", e))?;
 
     let values: Vec<f64> = rows
         .filter_map(|r| r.ok())
@@ -4132,7 +6508,7 @@ pub fn get_user_stat_std_dev(
 pub fn get_user_lh_at_minute_history(
     conn: &Connection,
     minute: i32,
-) -> Result<Vec<(i64, i32, i32, i32)>, String> {
+) -> Result<Vec<(i64, i32, i32, i32)>, DotaKeeperError> {
     let mut stmt = conn
         .prepare(
             "SELECT m.match_id, m.hero_id, m.game_mode, mc.last_hits
@@ -4141,7 +6517,7 @@ pub fn get_user_lh_at_minute_history(
              WHERE m.parse_state = 'parsed' AND mc.last_hits IS NOT NULL
              ORDER BY m.start_time DESC",
         )
-        .map_err(|e| format!("Failed to prepare LH history query: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to prepare LH history query", e))?;
 
     let rows = stmt
         .query_map([minute], |row| {
@@ -4152,7 +6528,7 @@ pub fn get_user_lh_at_minute_history(
                 row.get::<_, i32>(3)?,
             ))
         })
-        .map_err(|e| format!("Failed to query LH history: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to query LH history", e))?;
 
     let values: Vec<(i64, i32, i32, i32)> = rows.filter_map(|r| r.ok()).collect();
     Ok(values)
@@ -4171,9 +6547,960 @@ fn interpret_z_score(z: f64) -> String {
 }
 
 /// Check if we have any benchmark data loaded.
-pub fn has_benchmark_data(conn: &Connection) -> Result<bool, String> {
+pub fn has_benchmark_data(conn: &Connection) -> Result<bool, DotaKeeperError> {
     let count: i32 = conn
         .query_row("SELECT COUNT(*) FROM hero_benchmarks", [], |row| row.get(0))
-        .map_err(|e| format!("Failed to count benchmarks: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to count benchmarks", e))?;
     Ok(count > 0)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table_exists(conn: &Connection, table: &str) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![table],
+            |row| row.get::<_, i32>(0),
+        ).unwrap() > 0
+    }
+
+    fn column_exists(conn: &Connection, table: &str, column: &str) -> bool {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM pragma_table_info('{}') WHERE name = ?1", table),
+            params![column],
+            |row| row.get::<_, i32>(0),
+        ).unwrap() > 0
+    }
+
+    /// A database as shipped by the earliest releases: no role/rank/patch columns,
+    /// goals without item or scope support, and item_timings still carrying its
+    /// UNIQUE(match_id, item_id) constraint.
+    const SNAPSHOT_EARLY: &str = "
+        CREATE TABLE matches (
+            match_id INTEGER PRIMARY KEY,
+            hero_id INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            duration INTEGER NOT NULL,
+            game_mode INTEGER NOT NULL,
+            lobby_type INTEGER NOT NULL,
+            radiant_win INTEGER NOT NULL,
+            player_slot INTEGER NOT NULL,
+            kills INTEGER NOT NULL,
+            deaths INTEGER NOT NULL,
+            assists INTEGER NOT NULL,
+            xp_per_min INTEGER NOT NULL,
+            gold_per_min INTEGER NOT NULL,
+            last_hits INTEGER NOT NULL,
+            denies INTEGER NOT NULL,
+            hero_damage INTEGER NOT NULL,
+            tower_damage INTEGER NOT NULL,
+            hero_healing INTEGER NOT NULL
+        );
+        CREATE TABLE goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            hero_id INTEGER,
+            metric TEXT NOT NULL,
+            target_value INTEGER NOT NULL,
+            target_time_minutes INTEGER NOT NULL,
+            game_mode TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+        CREATE TABLE item_timings (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            match_id INTEGER NOT NULL,
+            item_id INTEGER NOT NULL,
+            timing_seconds INTEGER NOT NULL,
+            FOREIGN KEY (match_id) REFERENCES matches(match_id),
+            UNIQUE(match_id, item_id)
+        );
+        INSERT INTO matches VALUES (7001, 1, 1700000000, 2400, 22, 7, 1, 0, 8, 2, 10, 600, 550, 250, 12, 20000, 3000, 0);
+        INSERT INTO goals (hero_id, metric, target_value, target_time_minutes, game_mode, created_at)
+            VALUES (1, 'last_hits', 60, 10, 'ranked', 1700000000);
+        INSERT INTO item_timings (match_id, item_id, timing_seconds) VALUES (7001, 63, 840);
+    ";
+
+    /// A database from the last release before migrations were tracked: full schema,
+    /// reparse_v1 already applied and a match currently mid-parse.
+    const SNAPSHOT_PRE_MIGRATIONS: &str = "
+        CREATE TABLE matches (
+            match_id INTEGER PRIMARY KEY,
+            hero_id INTEGER NOT NULL,
+            start_time INTEGER NOT NULL,
+            duration INTEGER NOT NULL,
+            game_mode INTEGER NOT NULL,
+            lobby_type INTEGER NOT NULL,
+            radiant_win INTEGER NOT NULL,
+            player_slot INTEGER NOT NULL,
+            kills INTEGER NOT NULL,
+            deaths INTEGER NOT NULL,
+            assists INTEGER NOT NULL,
+            xp_per_min INTEGER NOT NULL,
+            gold_per_min INTEGER NOT NULL,
+            last_hits INTEGER NOT NULL,
+            denies INTEGER NOT NULL,
+            hero_damage INTEGER NOT NULL,
+            tower_damage INTEGER NOT NULL,
+            hero_healing INTEGER NOT NULL,
+            parse_state TEXT NOT NULL DEFAULT 'unparsed',
+            role INTEGER NOT NULL DEFAULT 0,
+            rank_tier INTEGER,
+            patch TEXT,
+            partner_slot INTEGER
+        );
+        CREATE TABLE app_metadata (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO app_metadata VALUES ('reparse_v1', '1');
+        INSERT INTO app_metadata VALUES ('reparse_dirty', '0');
+        INSERT INTO matches VALUES (8001, 2, 1710000000, 1800, 22, 7, 0, 130, 3, 5, 7, 450, 400, 120, 4, 9000, 500, 0, 'parsed', 3, 45, '7.35', NULL);
+        INSERT INTO matches VALUES (8002, 2, 1710005000, 1800, 22, 7, 0, 130, 3, 5, 7, 450, 400, 120, 4, 9000, 500, 0, 'parsing', 3, 45, '7.35', NULL);
+        CREATE TABLE hero_benchmarks (hero_id INTEGER NOT NULL, stat_name TEXT NOT NULL, mean REAL NOT NULL);
+    ";
+
+    fn latest_schema_version() -> i64 {
+        MIGRATIONS.last().unwrap().version
+    }
+
+    fn migrate(conn: &mut Connection) -> Result<(), DotaKeeperError> {
+        run_migrations(conn, MIGRATIONS)?;
+        reset_startup_state(conn)
+    }
+
+    fn migrated_db() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn
+    }
+
+    fn parsed_match(match_id: i64) -> Match {
+        Match {
+            match_id,
+            hero_id: 1,
+            start_time: 1_720_000_000,
+            duration: 2400,
+            game_mode: 22,
+            lobby_type: 7,
+            radiant_win: true,
+            player_slot: 0,
+            kills: 6,
+            deaths: 3,
+            assists: 9,
+            xp_per_min: 600,
+            gold_per_min: 550,
+            last_hits: 240,
+            denies: 10,
+            hero_damage: 20000,
+            tower_damage: 3000,
+            hero_healing: 0,
+            parse_state: MatchState::Parsed,
+            role: 1,
+            rank_tier: None,
+            patch: None,
+            parsed_by: None,
+        }
+    }
+
+    fn goal(metric: GoalMetric, target_value: i32, target_time_minutes: i32) -> Goal {
+        Goal {
+            id: 1,
+            hero_id: None,
+            hero_scope: None,
+            metric,
+            target_value,
+            target_time_minutes,
+            item_id: None,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            created_at: 0,
+            conditions: Vec::new(),
+            combinator: GoalCombinator::And,
+            scope: GoalScope::default(),
+            state: GoalState::Active,
+            state_changed_at: None,
+            achieved_at: None,
+        }
+    }
+
+    #[test]
+    fn fresh_database_reaches_latest_version() {
+        let conn = migrated_db();
+
+        assert_eq!(get_schema_version(&conn).unwrap(), latest_schema_version());
+        for table in ["matches", "goals", "match_cs", "item_timings", "player_networth", "match_xp",
+                      "daily_challenges", "weekly_challenges", "mood_checkins", "hero_benchmarks"] {
+            assert!(table_exists(&conn, table), "missing table {}", table);
+        }
+    }
+
+    #[test]
+    fn migrations_are_idempotent_across_restarts() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+
+        let applied: i64 = conn
+            .query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(applied, MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn every_match_sort_pages_through_an_index() {
+        let conn = migrated_db();
+        for sort in [
+            MatchSortField::StartTime, MatchSortField::Duration, MatchSortField::Kills,
+            MatchSortField::Deaths, MatchSortField::Assists, MatchSortField::LastHits,
+            MatchSortField::GoldPerMin, MatchSortField::XpPerMin, MatchSortField::HeroDamage,
+        ] {
+            let plan: Vec<String> = conn
+                .prepare(&format!(
+                    "EXPLAIN QUERY PLAN SELECT m.match_id FROM matches m ORDER BY m.{c} DESC, m.match_id DESC LIMIT 51",
+                    c = sort.column()
+                ))
+                .unwrap()
+                .query_map([], |row| row.get::<_, String>(3))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+            let expected = format!("INDEX idx_matches_{}", sort.column());
+            assert!(plan.iter().any(|step| step.contains(&expected)), "{:?} plan: {:?}", sort, plan);
+        }
+    }
+
+    #[test]
+    fn early_snapshot_is_migrated_forward() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SNAPSHOT_EARLY).unwrap();
+        assert_eq!(get_schema_version(&conn).unwrap(), 0);
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(get_schema_version(&conn).unwrap(), latest_schema_version());
+        for column in ["parse_state", "role", "rank_tier", "patch", "partner_slot", "parsed_by"] {
+            assert!(column_exists(&conn, "matches", column), "missing matches.{}", column);
+        }
+        for column in ["item_id", "hero_scope", "frequency_type"] {
+            assert!(column_exists(&conn, "goals", column), "missing goals.{}", column);
+        }
+
+        // Existing rows survive and pick up column defaults.
+        let matches = get_all_matches(&conn).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].match_id, 7001);
+        assert_eq!(matches[0].parse_state, MatchState::Unparsed);
+        let goals = get_all_goals(&conn).unwrap();
+        assert_eq!(goals.len(), 1);
+        assert_eq!(goals[0].frequency_type, FrequencyType::Pct75);
+
+        // The UNIQUE constraint on item_timings is gone, so repeat purchases can be stored.
+        conn.execute(
+            "INSERT INTO item_timings (match_id, item_id, timing_seconds) VALUES (7001, 63, 1500)",
+            [],
+        ).unwrap();
+        let timings: i64 = conn
+            .query_row("SELECT COUNT(*) FROM item_timings WHERE match_id = 7001", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(timings, 2);
+    }
+
+    #[test]
+    fn pre_migration_snapshot_keeps_parse_state() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SNAPSHOT_PRE_MIGRATIONS).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        // reparse_v1 was already applied, so parsed matches must not be reset;
+        // the interrupted parse goes back to the queue.
+        let states: Vec<(i64, MatchState)> = get_all_matches(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|m| (m.match_id, m.parse_state))
+            .collect();
+        assert!(states.contains(&(8001, MatchState::Parsed)));
+        assert!(states.contains(&(8002, MatchState::Unparsed)));
+
+        // The outdated benchmark cache layout is rebuilt.
+        assert!(column_exists(&conn, "hero_benchmarks", "bracket"));
+    }
+
+    #[test]
+    fn newer_database_is_refused() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, 'from_the_future', 0)",
+            params![latest_schema_version() + 1],
+        ).unwrap();
+
+        let err = run_migrations(&mut conn, MIGRATIONS).unwrap_err();
+        assert_eq!(err.code(), "schema");
+        assert!(err.to_string().contains("newer"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn failed_migration_rolls_back() {
+        fn create_then_fail(conn: &Connection) -> Result<(), DotaKeeperError> {
+            conn.execute("CREATE TABLE half_done (id INTEGER)", [])?;
+            Err(DotaKeeperError::Other("boom".to_string()))
+        }
+        let migrations = [
+            Migration { version: 1, name: "baseline", up: migrate_v1_baseline },
+            Migration { version: 2, name: "broken", up: create_then_fail },
+        ];
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        let err = run_migrations(&mut conn, &migrations).unwrap_err();
+
+        assert_eq!(err.code(), "schema");
+        assert!(err.to_string().contains("broken"), "unexpected error: {}", err);
+        assert_eq!(get_schema_version(&conn).unwrap(), 1);
+        assert!(!table_exists(&conn, "half_done"));
+    }
+
+    #[test]
+    fn xp_maps_to_level_thresholds() {
+        assert_eq!(xp_to_level(0), 1);
+        assert_eq!(xp_to_level(229), 1);
+        assert_eq!(xp_to_level(230), 2);
+        assert_eq!(xp_to_level(6330), 10);
+        assert_eq!(xp_to_level(100_000), 30);
+    }
+
+    #[test]
+    fn level_goal_evaluates_from_match_xp() {
+        let conn = migrated_db();
+        let m = parsed_match(9001);
+        insert_match(&conn, &m).unwrap();
+        let xp_t: Vec<i32> = (0..=12).map(|minute| minute * 640).collect();
+        insert_match_xp_data(&conn, m.match_id, &xp_t).unwrap();
+
+        // 6400 XP at 10:00 is level 10
+        let eval = evaluate_goal(&conn, &goal(GoalMetric::Level, 10, 10), &m).unwrap();
+        assert_eq!(eval.actual_value, 10);
+        assert!(eval.achieved);
+
+        let eval = evaluate_goal(&conn, &goal(GoalMetric::Level, 11, 10), &m).unwrap();
+        assert!(!eval.achieved);
+
+        // No XP recorded past minute 12
+        assert!(evaluate_goal(&conn, &goal(GoalMetric::Level, 10, 20), &m).is_none());
+    }
+
+    #[test]
+    fn kill_and_death_goals_use_event_timeline() {
+        let conn = migrated_db();
+        let m = parsed_match(9002); // 6 kills, 3 deaths, 40 minutes
+        insert_match(&conn, &m).unwrap();
+
+        // Nothing stored yet: the value at 10:00 is unknown
+        assert!(evaluate_goal(&conn, &goal(GoalMetric::Kills, 2, 10), &m).is_none());
+
+        insert_match_events(&conn, m.match_id, &[120, 540, 600, 1500, 1800, 2100], &[300, 1700, 2200]).unwrap();
+
+        let kills = evaluate_goal(&conn, &goal(GoalMetric::Kills, 3, 10), &m).unwrap();
+        assert_eq!(kills.actual_value, 3);
+        assert!(kills.achieved);
+
+        let deaths = evaluate_goal(&conn, &goal(GoalMetric::Deaths, 0, 10), &m).unwrap();
+        assert_eq!(deaths.actual_value, 1);
+        assert!(!deaths.achieved);
+
+        // Past the end of the game the final totals apply
+        let late = evaluate_goal(&conn, &goal(GoalMetric::Kills, 6, 45), &m).unwrap();
+        assert_eq!(late.actual_value, 6);
+    }
+
+    #[test]
+    fn goal_status_judges_window_by_frequency_type() {
+        let conn = migrated_db();
+        // Eight games a day apart, CS at 10:00 rising from 40 to 75
+        for (i, lh) in (40..=75).step_by(5).enumerate() {
+            let m = Match { start_time: 1_720_000_000 + i as i64 * 86400, ..parsed_match(9100 + i as i64) };
+            insert_match(&conn, &m).unwrap();
+            let lh_t: Vec<i32> = (0..=10).map(|minute| lh * minute / 10).collect();
+            insert_match_cs_data(&conn, m.match_id, &lh_t, &[0; 11]).unwrap();
+        }
+        let mut g = insert_goal(&conn, &NewGoal {
+            hero_id: None,
+            hero_scope: None,
+            metric: GoalMetric::LastHits,
+            target_value: 60,
+            target_time_minutes: 10,
+            item_id: None,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            conditions: Vec::new(),
+            combinator: GoalCombinator::And,
+            scope: GoalScope::default(),
+        }).unwrap();
+
+        let status = get_goal_status(&conn, g.id, GoalWindow::LastGames(20)).unwrap();
+        assert_eq!((status.games, status.achieved), (8, 4));
+        assert_eq!(status.verdict, GoalVerdict::OffTrack);
+        assert_eq!(status.average_value, 57.5);
+        assert!((status.trend_per_game - 5.0).abs() < 1e-9);
+        assert!(status.improving);
+        let ci = status.achievement_rate_ci;
+        assert!(ci.low < 0.5 && ci.high > 0.5 && ci.high < 1.0);
+
+        // The last four games all hit 60+
+        let recent = get_goal_status(&conn, g.id, GoalWindow::LastGames(4)).unwrap();
+        assert_eq!((recent.verdict, recent.achievement_rate), (GoalVerdict::OnTrack, 1.0));
+
+        for (frequency_type, verdict) in [
+            (FrequencyType::Pct50, GoalVerdict::OnTrack),
+            (FrequencyType::OnAverage, GoalVerdict::OffTrack),
+            (FrequencyType::JustOnce, GoalVerdict::OnTrack),
+        ] {
+            g.frequency_type = frequency_type;
+            update_goal(&conn, &g).unwrap();
+            assert_eq!(get_goal_status(&conn, g.id, GoalWindow::LastGames(20)).unwrap().verdict, verdict);
+        }
+
+        let points = get_goal_match_data(&conn, g.id).unwrap();
+        let newest = points.iter().map(|p| p.start_time).max().unwrap();
+        let last_days = goal_status_from_points(g.clone(), GoalWindow::LastDays(3), points.clone(), newest + 3600);
+        assert_eq!(last_days.games, 3);
+        let none = goal_status_from_points(g, GoalWindow::LastDays(1), points, newest + 10 * 86400);
+        assert_eq!(none.verdict, GoalVerdict::NotEnoughData);
+    }
+
+    #[test]
+    fn compound_goals_combine_conditions_with_and_or() {
+        let conn = migrated_db();
+        let m = parsed_match(9201);
+        insert_match(&conn, &m).unwrap();
+        let lh_t: Vec<i32> = (0..=15).map(|minute| minute * 6).collect(); // 60 LH at 10:00
+        insert_match_cs_data(&conn, m.match_id, &lh_t, &[0; 16]).unwrap();
+        insert_match_events(&conn, m.match_id, &[], &[300, 540, 1500]).unwrap(); // 2 deaths by 10:00
+
+        let lh_50 = GoalCondition { metric: GoalMetric::LastHits, target_value: 50, target_time_minutes: 10, item_id: None };
+        let max_one_death = GoalCondition { metric: GoalMetric::Deaths, target_value: 1, target_time_minutes: 10, item_id: None };
+        let bfury_by_15 = GoalCondition { metric: GoalMetric::ItemTiming, target_value: 900, target_time_minutes: 0, item_id: Some(145) };
+        let new_goal = |first: &GoalCondition, rest: Vec<GoalCondition>, combinator| NewGoal {
+            hero_id: None,
+            hero_scope: None,
+            metric: first.metric.clone(),
+            target_value: first.target_value,
+            target_time_minutes: first.target_time_minutes,
+            item_id: first.item_id,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            conditions: rest,
+            combinator,
+            scope: GoalScope::default(),
+        };
+
+        // 60 LH but 2 deaths: AND fails, OR passes
+        let and_goal = insert_goal(&conn, &new_goal(&lh_50, vec![max_one_death.clone()], GoalCombinator::And)).unwrap();
+        let eval = evaluate_goal(&conn, &and_goal, &m).unwrap();
+        assert!(!eval.achieved);
+        assert_eq!(eval.actual_value, 60);
+        assert_eq!(eval.conditions.iter().map(|c| (c.achieved, c.actual_value)).collect::<Vec<_>>(), [(true, Some(60)), (false, Some(2))]);
+
+        let or_goal = insert_goal(&conn, &new_goal(&lh_50, vec![max_one_death], GoalCombinator::Or)).unwrap();
+        assert!(evaluate_goal(&conn, &or_goal, &m).unwrap().achieved);
+
+        // Battle Fury never bought: unmet for OR, and an AND goal can't be judged
+        let or_item = insert_goal(&conn, &new_goal(&lh_50, vec![bfury_by_15.clone()], GoalCombinator::Or)).unwrap();
+        let eval = evaluate_goal(&conn, &or_item, &m).unwrap();
+        assert!(eval.achieved);
+        assert_eq!(eval.conditions[1].actual_value, None);
+        let and_item = insert_goal(&conn, &new_goal(&lh_50, vec![bfury_by_15], GoalCombinator::And)).unwrap();
+        assert!(evaluate_goal(&conn, &and_item, &m).is_none());
+
+        // Conditions survive the round trip and flow into the per-goal and per-match views
+        let stored = get_goal_by_id(&conn, and_goal.id).unwrap();
+        assert_eq!((stored.conditions.len(), stored.combinator), (1, GoalCombinator::And));
+
+        // Re-saving from an edit form that doesn't send the conditions keeps them
+        let mut form = serde_json::to_value(&stored).unwrap();
+        form.as_object_mut().unwrap().retain(|key, _| !matches!(key.as_str(), "conditions" | "combinator"));
+        form["target_value"] = 55.into();
+        let edit: GoalEdit = serde_json::from_value(form).unwrap();
+        update_goal(&conn, &edit.apply_to(stored)).unwrap();
+        let stored = get_goal_by_id(&conn, and_goal.id).unwrap();
+        assert_eq!((stored.target_value, stored.conditions.len(), stored.combinator), (55, 1, GoalCombinator::And));
+        let points = get_goal_match_data(&conn, or_goal.id).unwrap();
+        assert_eq!(points.len(), 1);
+        assert!(points[0].achieved);
+        let mwg = get_matches_with_goals(&conn).unwrap();
+        assert_eq!((mwg[0].goals_achieved, mwg[0].goals_applicable), (2, 3));
+    }
+
+    #[test]
+    fn database_account_guards_writes_from_other_profiles() {
+        let conn = migrated_db();
+        // Unclaimed databases (fresh installs, tests) accept any account
+        assert_eq!(get_db_account(&conn).unwrap(), None);
+        assert!(ensure_db_account(&conn, 111).is_ok());
+
+        set_db_account(&conn, 111).unwrap();
+        assert_eq!(get_db_account(&conn).unwrap(), Some(111));
+        assert!(ensure_db_account(&conn, 111).is_ok());
+        assert!(ensure_db_account(&conn, 222).is_err());
+
+        // Factory reset releases the file for whichever account logs in next
+        factory_reset_db(&conn).unwrap();
+        assert_eq!(get_db_account(&conn).unwrap(), None);
+    }
+
+    #[test]
+    fn factory_reset_from_secondary_profile_wipes_every_profile() {
+        let dir = std::env::temp_dir().join(format!("dota_keeper_test_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // First profile owns the original file; the second profile is active at reset time
+        let first = open_db(&db_path_in(&dir, None), Some(111)).unwrap();
+        insert_match(&first, &parsed_match(9101)).unwrap();
+        drop(first);
+        let active = open_db(&db_path_in(&dir, Some("222")), Some(222)).unwrap();
+        insert_match(&active, &parsed_match(9102)).unwrap();
+
+        factory_reset_profiles(&active, &dir, Some("222"), &[None, Some("222")]).unwrap();
+        assert_eq!(get_db_account(&active).unwrap(), None);
+        assert!(get_all_matches(&active).unwrap().is_empty());
+        assert!(!db_path_in(&dir, None).exists());
+
+        // Whoever logs in next gets a clean, unclaimed original database
+        let reopened = open_db(&db_path_in(&dir, None), Some(333)).unwrap();
+        assert_eq!(get_db_account(&reopened).unwrap(), Some(333));
+        assert!(get_all_matches(&reopened).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_queue_retries_with_backoff_until_exhausted() {
+        let conn = migrated_db();
+        let mut m = parsed_match(9003);
+        m.parse_state = MatchState::Unparsed;
+        insert_match(&conn, &m).unwrap();
+        let now = 1_720_000_000;
+
+        assert_eq!(enqueue_unparsed_matches(&conn, now).unwrap(), 1);
+        assert_eq!(enqueue_unparsed_matches(&conn, now).unwrap(), 0);
+        assert_eq!(next_due_parse_job(&conn, now).unwrap(), Some(9003));
+
+        // First failure: retry after one minute
+        record_parse_failure(&conn, 9003, "Too many requests", now).unwrap();
+        assert_eq!(next_due_parse_job(&conn, now + 59).unwrap(), None);
+        assert_eq!(next_due_parse_job(&conn, now + 60).unwrap(), Some(9003));
+
+        // Second failure doubles the delay
+        record_parse_failure(&conn, 9003, "Too many requests", now).unwrap();
+        assert_eq!(next_due_parse_job(&conn, now + 60).unwrap(), None);
+        assert_eq!(next_due_parse_job(&conn, now + 120).unwrap(), Some(9003));
+
+        for _ in 2..MAX_PARSE_ATTEMPTS {
+            record_parse_failure(&conn, 9003, "still failing", now).unwrap();
+        }
+        assert_eq!(count_pending_parse_jobs(&conn).unwrap(), 0);
+        assert_eq!(next_due_parse_job(&conn, i64::MAX).unwrap(), None);
+        assert_eq!(parse_retry_delay_secs(20), 6 * 60 * 60);
+
+        remove_from_parse_queue(&conn, 9003).unwrap();
+        update_match_state(&conn, 9003, MatchState::Parsed).unwrap();
+        assert_eq!(enqueue_unparsed_matches(&conn, now).unwrap(), 0);
+    }
+
+    #[test]
+    fn gsi_parsed_matches_queue_behind_unparsed_ones_for_a_provider_parse() {
+        let conn = migrated_db();
+        let now = 1_720_000_000;
+        insert_match(&conn, &Match { start_time: now, ..parsed_match(9005) }).unwrap();
+        update_match_parsed_by(&conn, 9005, "gsi").unwrap();
+        let older = Match { start_time: now - 86400, parse_state: MatchState::Unparsed, ..parsed_match(9006) };
+        insert_match(&conn, &older).unwrap();
+        insert_match(&conn, &parsed_match(9007)).unwrap();
+
+        assert_eq!(enqueue_unparsed_matches(&conn, now).unwrap(), 2);
+        assert_eq!(next_due_parse_job(&conn, now).unwrap(), Some(9006));
+        remove_from_parse_queue(&conn, 9006).unwrap();
+        update_match_state(&conn, 9006, MatchState::Parsed).unwrap();
+        assert_eq!(next_due_parse_job(&conn, now).unwrap(), Some(9005));
+
+        // Once a provider has parsed it there is nothing left to fetch
+        update_match_parsed_by(&conn, 9005, "opendota").unwrap();
+        assert_eq!(next_due_parse_job(&conn, now).unwrap(), None);
+    }
+
+    #[test]
+    fn reparse_request_revives_an_exhausted_parse_job() {
+        let conn = migrated_db();
+        let mut m = parsed_match(9004);
+        m.parse_state = MatchState::Failed;
+        insert_match(&conn, &m).unwrap();
+        let now = 1_720_000_000;
+
+        for _ in 0..MAX_PARSE_ATTEMPTS {
+            record_parse_failure(&conn, 9004, "still failing", now).unwrap();
+        }
+        assert_eq!(next_due_parse_job(&conn, i64::MAX).unwrap(), None);
+
+        reset_parse_job(&conn, 9004, now + 10).unwrap();
+        assert_eq!(count_pending_parse_jobs(&conn).unwrap(), 1);
+        assert_eq!(next_due_parse_job(&conn, now + 10).unwrap(), Some(9004));
+
+        // In flight or already parsed: the background parser leaves it alone
+        update_match_state(&conn, 9004, MatchState::Parsing).unwrap();
+        assert_eq!(next_due_parse_job(&conn, now + 10).unwrap(), None);
+        update_match_state(&conn, 9004, MatchState::Parsed).unwrap();
+        assert_eq!(next_due_parse_job(&conn, now + 10).unwrap(), None);
+    }
+
+    fn match_player(match_id: i64, player_slot: i32, hero_id: i32) -> MatchPlayer {
+        MatchPlayer {
+            match_id,
+            player_slot,
+            account_id: None,
+            hero_id,
+            lane_role: 0,
+            kills: None,
+            deaths: None,
+            assists: None,
+            last_hits: None,
+            denies: None,
+            xp_per_min: None,
+            gold_per_min: None,
+            hero_damage: None,
+            tower_damage: None,
+            hero_healing: None,
+        }
+    }
+
+    #[test]
+    fn hero_matchups_group_by_enemy_and_ally() {
+        let conn = migrated_db();
+        for (match_id, radiant_win) in [(9101, true), (9102, true), (9103, false)] {
+            let mut m = parsed_match(match_id); // hero 1, radiant slot 0, 6/3/9
+            m.radiant_win = radiant_win;
+            insert_match(&conn, &m).unwrap();
+            upsert_match_player(&conn, &match_player(match_id, 0, 1)).unwrap();
+            upsert_match_player(&conn, &match_player(match_id, 128, 2)).unwrap();
+        }
+        // Lane CS diff is only known for the first game: the safe lane (carry and support
+        // 5, 40 + 5 last hits at 10:00) against the off lane (31 + 4); enemy mid doesn't count
+        for (slot, hero, lane_role, lh) in [(0, 1, 1, 40), (1, 5, 5, 5), (128, 2, 3, 31), (129, 3, 4, 4), (130, 4, 2, 60)] {
+            upsert_match_player(&conn, &MatchPlayer { lane_role, ..match_player(9101, slot, hero) }).unwrap();
+            insert_match_player_timeline(&conn, 9101, slot, &[lh; 11], &[], &[]).unwrap();
+        }
+
+        let matchups = get_hero_matchups(&conn, 1, 1).unwrap();
+        assert_eq!(matchups.enemies.iter().map(|e| e.hero_id).collect::<Vec<_>>(), [2, 3, 4]);
+        let axe = &matchups.enemies[0];
+        assert_eq!((axe.hero_id, axe.games, axe.wins), (2, 3, 2));
+        assert!((axe.win_rate - 66.666).abs() < 0.01);
+        assert_eq!(axe.avg_kills, 6.0);
+        assert_eq!(axe.avg_lane_cs_diff, Some(10.0));
+        assert_eq!(matchups.allies.len(), 1);
+        assert_eq!((matchups.allies[0].hero_id, matchups.allies[0].avg_lane_cs_diff), (5, Some(10.0)));
+
+        // Below the sample threshold the ally drops out
+        let matchups = get_hero_matchups(&conn, 1, MIN_MATCHUP_GAMES).unwrap();
+        assert_eq!(matchups.enemies.len(), 1);
+        assert!(matchups.allies.is_empty());
+    }
+
+    #[test]
+    fn goals_are_promoted_retired_and_kept_in_history() {
+        let conn = migrated_db();
+        // Four games with 60+ CS at 10:00
+        for i in 0..4 {
+            let m = Match { start_time: 1_720_000_000 + i * 86400, ..parsed_match(9300 + i) };
+            insert_match(&conn, &m).unwrap();
+            let lh_t: Vec<i32> = (0..=10).map(|minute| minute * 7).collect();
+            insert_match_cs_data(&conn, m.match_id, &lh_t, &[0; 11]).unwrap();
+        }
+        let cs_goal = |target_value, frequency_type| NewGoal {
+            hero_id: None,
+            hero_scope: None,
+            metric: GoalMetric::LastHits,
+            target_value,
+            target_time_minutes: 10,
+            item_id: None,
+            game_mode: GoalGameMode::All,
+            frequency_type,
+            conditions: Vec::new(),
+            combinator: GoalCombinator::And,
+            scope: GoalScope::default(),
+        };
+        let steady = insert_goal(&conn, &cs_goal(60, FrequencyType::Pct75)).unwrap();
+        let out_of_reach = insert_goal(&conn, &cs_goal(100, FrequencyType::JustOnce)).unwrap();
+        let paused = insert_goal(&conn, &cs_goal(60, FrequencyType::Pct75)).unwrap();
+        assert_eq!(steady.state, GoalState::Active);
+        // Games from before a goal became active don't count towards completing it
+        let early_bird = insert_goal(&conn, &cs_goal(60, FrequencyType::JustOnce)).unwrap();
+        assert!(promote_achieved_goals(&conn, 4).unwrap().is_empty());
+        delete_goal(&conn, early_bird.id).unwrap();
+        conn.execute("UPDATE goals SET state_changed_at = 1700000000 WHERE state = 'active'", []).unwrap();
+        set_goal_state(&conn, paused.id, GoalState::Paused, GoalWindow::default()).unwrap();
+
+        // Not enough games yet for a window of five
+        assert!(promote_achieved_goals(&conn, 5).unwrap().is_empty());
+        let promoted = promote_achieved_goals(&conn, 4).unwrap();
+        assert_eq!(promoted.iter().map(|g| g.id).collect::<Vec<_>>(), vec![steady.id]);
+        assert_eq!(promoted[0].state, GoalState::Achieved);
+        assert!(promoted[0].achieved_at.is_some());
+
+        // Only active goals are evaluated
+        let active: Vec<i64> = get_active_goals(&conn).unwrap().iter().map(|g| g.id).collect();
+        assert_eq!(active, vec![out_of_reach.id]);
+        assert_eq!(get_all_goals(&conn).unwrap().len(), 3);
+        let m = get_all_matches(&conn).unwrap().remove(0);
+        let evals = evaluate_match_goals(&conn, &m).unwrap();
+        assert_eq!(evals.iter().map(|e| e.goal.id).collect::<Vec<_>>(), vec![out_of_reach.id]);
+
+        let history = get_retired_goals(&conn).unwrap();
+        assert_eq!(history.len(), 1);
+        let stats = history[0].final_stats.as_ref().unwrap();
+        assert_eq!((stats.games, stats.achieved, stats.verdict), (4, 4, GoalVerdict::OnTrack));
+
+        set_goal_state(&conn, paused.id, GoalState::Archived, GoalWindow::default()).unwrap();
+        assert_eq!(get_retired_goals(&conn).unwrap().len(), 2);
+
+        // Reviving a goal clears its final stats but remembers when it was achieved
+        let revived = set_goal_state(&conn, steady.id, GoalState::Active, GoalWindow::default()).unwrap();
+        assert_eq!(revived.state, GoalState::Active);
+        assert!(revived.achieved_at.is_some());
+        let history = get_retired_goals(&conn).unwrap();
+        assert_eq!(history.iter().map(|r| r.goal.id).collect::<Vec<_>>(), vec![paused.id]);
+
+        // The games that achieved it the first time don't immediately re-achieve it...
+        assert!(promote_achieved_goals(&conn, 4).unwrap().is_empty());
+        // ...but a fresh window played after the revival does
+        let revived_at = revived.state_changed_at.unwrap();
+        for i in 0..4 {
+            let m = Match { start_time: revived_at + i * 3600, ..parsed_match(9310 + i) };
+            insert_match(&conn, &m).unwrap();
+            let lh_t: Vec<i32> = (0..=10).map(|minute| minute * 7).collect();
+            insert_match_cs_data(&conn, m.match_id, &lh_t, &[0; 11]).unwrap();
+        }
+        let promoted = promote_achieved_goals(&conn, 4).unwrap();
+        assert_eq!(promoted.iter().map(|g| g.id).collect::<Vec<_>>(), vec![steady.id]);
+    }
+
+    #[test]
+    fn goal_scope_filters_by_role_lobby_patch_and_creation() {
+        let conn = migrated_db();
+        let mut g = insert_goal(&conn, &NewGoal {
+            hero_id: None,
+            hero_scope: None,
+            metric: GoalMetric::LastHits,
+            target_value: 60,
+            target_time_minutes: 10,
+            item_id: None,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            conditions: Vec::new(),
+            combinator: GoalCombinator::And,
+            scope: GoalScope {
+                roles: vec![2, 3],
+                lobby_types: vec![7],
+                patch_from: Some("7.35".to_string()),
+                patch_to: Some("7.36".to_string()),
+                since_created: false,
+            },
+        }).unwrap();
+        assert_eq!(get_goal_by_id(&conn, g.id).unwrap().scope, g.scope);
+
+        // An edit form that doesn't send the scope leaves it alone
+        let mut form = serde_json::to_value(&g).unwrap();
+        form.as_object_mut().unwrap().remove("scope");
+        let edit: GoalEdit = serde_json::from_value(form).unwrap();
+        update_goal(&conn, &edit.apply_to(get_goal_by_id(&conn, g.id).unwrap())).unwrap();
+        assert_eq!(get_goal_by_id(&conn, g.id).unwrap().scope, g.scope);
+
+        let mid = Match { role: 2, patch: Some("7.36c".to_string()), ..parsed_match(9400) };
+        assert!(goal_applies_to_match(&g, &mid));
+        assert!(!goal_applies_to_match(&g, &Match { role: 1, ..mid.clone() }));
+        assert!(!goal_applies_to_match(&g, &Match { lobby_type: 0, ..mid.clone() }));
+        assert!(!goal_applies_to_match(&g, &Match { patch: Some("7.34e".to_string()), ..mid.clone() }));
+        assert!(!goal_applies_to_match(&g, &Match { patch: Some("7.37".to_string()), ..mid.clone() }));
+        assert!(!goal_applies_to_match(&g, &Match { patch: None, ..mid.clone() }));
+
+        g.scope = GoalScope { since_created: true, ..GoalScope::default() };
+        update_goal(&conn, &g).unwrap();
+        let g = get_goal_by_id(&conn, g.id).unwrap();
+        assert!(!goal_applies_to_match(&g, &Match { start_time: g.created_at - 1, ..mid.clone() }));
+        assert!(goal_applies_to_match(&g, &Match { start_time: g.created_at + 60, patch: None, ..mid }));
+    }
+
+    #[test]
+    fn goal_evaluations_are_cached_until_goal_or_match_changes() {
+        let conn = migrated_db();
+        let m = parsed_match(9500);
+        insert_match(&conn, &m).unwrap();
+        insert_match_cs_data(&conn, m.match_id, &(0..=10).map(|minute| minute * 6).collect::<Vec<_>>(), &[0; 11]).unwrap();
+        insert_match(&conn, &Match { parse_state: MatchState::Unparsed, ..parsed_match(9501) }).unwrap();
+        let mut g = insert_goal(&conn, &NewGoal {
+            hero_id: None,
+            hero_scope: None,
+            metric: GoalMetric::LastHits,
+            target_value: 50,
+            target_time_minutes: 10,
+            item_id: None,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            conditions: Vec::new(),
+            combinator: GoalCombinator::And,
+            scope: GoalScope::default(),
+        }).unwrap();
+        let cached_rows = || conn.query_row("SELECT COUNT(*) FROM goal_evaluations", [], |row| row.get::<_, i64>(0)).unwrap();
+        let achieved = || {
+            let list = get_matches_with_goals(&conn).unwrap();
+            let mwg = list.iter().find(|mwg| mwg.match_data.match_id == 9500).unwrap();
+            (mwg.goals_achieved, mwg.goals_applicable)
+        };
+
+        assert_eq!(achieved(), (1, 1));
+        assert_eq!(cached_rows(), 1); // the unparsed match isn't cached
+
+        // New data is only picked up once the match is (re)parsed
+        insert_match_cs_data(&conn, m.match_id, &[0; 11], &[0; 11]).unwrap();
+        assert_eq!(achieved(), (1, 1));
+        update_match_state(&conn, m.match_id, MatchState::Parsed).unwrap();
+        assert_eq!(cached_rows(), 0);
+        assert_eq!(achieved(), (0, 1));
+
+        // Editing the goal drops its cached results
+        g.target_value = 0;
+        update_goal(&conn, &g).unwrap();
+        assert!(evaluate_match_goals(&conn, &m).unwrap()[0].achieved);
+        g.hero_id = Some(99);
+        update_goal(&conn, &g).unwrap();
+        assert_eq!(achieved(), (0, 0));
+        assert_eq!(cached_rows(), 1);
+
+        delete_goal(&conn, g.id).unwrap();
+        assert_eq!(cached_rows(), 0);
+    }
+
+    #[test]
+    fn query_matches_filters_sorts_and_pages_in_sql() {
+        let conn = migrated_db();
+        // Five games a day apart; heroes alternate 1/2, even games won, kills 10..6,
+        // the last two in turbo and each on its own patch
+        let patches = ["7.39d", "7.40", "7.40a", "7.40b", "7.41"];
+        for i in 0..5 {
+            let m = Match {
+                hero_id: 1 + (i as i32 % 2),
+                start_time: 1_720_000_000 + i * 86400,
+                radiant_win: i % 2 == 0,
+                kills: 10 - i as i32,
+                game_mode: if i < 3 { 22 } else { 23 },
+                patch: Some(patches[i as usize].to_string()),
+                ..parsed_match(9600 + i)
+            };
+            insert_match(&conn, &m).unwrap();
+            let lh_per_minute = if i < 2 { 6 } else { 3 };
+            insert_match_cs_data(&conn, m.match_id, &(0..=10).map(|minute| minute * lh_per_minute).collect::<Vec<_>>(), &[0; 11]).unwrap();
+        }
+        let ids = |page: &MatchPage| page.matches.iter().map(|m| m.match_data.match_id).collect::<Vec<_>>();
+
+        // Newest first, two at a time
+        let mut query = MatchQuery { limit: Some(2), ..MatchQuery::default() };
+        let mut seen = Vec::new();
+        loop {
+            let page = query_matches(&conn, &query).unwrap();
+            assert_eq!(page.total, 5);
+            seen.extend(ids(&page));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, vec![9604, 9603, 9602, 9601, 9600]);
+
+        let page = query_matches(&conn, &MatchQuery { hero_id: Some(1), won: Some(true), ..MatchQuery::default() }).unwrap();
+        assert_eq!(ids(&page), vec![9604, 9602, 9600]);
+        let page = query_matches(&conn, &MatchQuery { start_before: Some(1_720_000_000 + 2 * 86400), ascending: true, ..MatchQuery::default() }).unwrap();
+        assert_eq!(ids(&page), vec![9600, 9601]);
+        let page = query_matches(&conn, &MatchQuery { game_modes: vec![21, 23], ..MatchQuery::default() }).unwrap();
+        assert_eq!(ids(&page), vec![9604, 9603]);
+        let page = query_matches(&conn, &MatchQuery { patch: Some("7.40".to_string()), ..MatchQuery::default() }).unwrap();
+        assert_eq!(ids(&page), vec![9603, 9602, 9601]);
+        let page = query_matches(&conn, &MatchQuery { patch: Some("7.40a".to_string()), ..MatchQuery::default() }).unwrap();
+        assert_eq!(ids(&page), vec![9602]);
+
+        let options = get_match_filter_options(&conn).unwrap();
+        assert_eq!(options.hero_ids, vec![1, 2]);
+        assert_eq!(options.patches, vec!["7.41", "7.40b", "7.40a", "7.40", "7.39d"]);
+
+        let page = query_matches(&conn, &MatchQuery { sort: MatchSortField::Kills, ascending: true, limit: Some(3), ..MatchQuery::default() }).unwrap();
+        assert_eq!(ids(&page), vec![9604, 9603, 9602]);
+        let rest = query_matches(&conn, &MatchQuery {
+            sort: MatchSortField::Kills,
+            ascending: true,
+            cursor: page.next_cursor,
+            ..MatchQuery::default()
+        }).unwrap();
+        assert_eq!(ids(&rest), vec![9601, 9600]);
+
+        // 50 LH at 10 is only reached in the first two games
+        let goal = insert_goal(&conn, &NewGoal {
+            hero_id: None,
+            hero_scope: None,
+            metric: GoalMetric::LastHits,
+            target_value: 50,
+            target_time_minutes: 10,
+            item_id: None,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            conditions: Vec::new(),
+            combinator: GoalCombinator::And,
+            scope: GoalScope::default(),
+        }).unwrap();
+        let outcome = |achieved| MatchQuery {
+            goal_outcome: Some(GoalOutcomeFilter { goal_id: Some(goal.id), achieved }),
+            ..MatchQuery::default()
+        };
+        let achieved = query_matches(&conn, &outcome(true)).unwrap();
+        assert_eq!(ids(&achieved), vec![9601, 9600]);
+        assert_eq!(achieved.matches[0].goals_achieved, 1);
+        assert_eq!(query_matches(&conn, &outcome(false)).unwrap().total, 3);
+
+        let bad_cursor = MatchQuery { cursor: Some("nope".to_string()), ..MatchQuery::default() };
+        assert!(query_matches(&conn, &bad_cursor).is_err());
+    }
+
+    #[test]
+    fn backfill_state_pages_to_the_cap_and_survives_restarts() {
+        let conn = migrated_db();
+        assert_eq!(get_backfill_state(&conn).unwrap(), None);
+        let day = 86400;
+        let now = 1_720_000_000;
+        let page = |newest: i64, count: i64| -> Vec<Match> {
+            (0..count).map(|i| Match { start_time: now - (newest + i) * day, ..parsed_match(9700 + newest + i) }).collect()
+        };
+
+        // Capped at 5 days back, pages of 3 games a day apart
+        let mut state = BackfillState::new(Some(now - 5 * day), None, now);
+        assert_eq!(state.advance(page(1, 3), 3).len(), 3);
+        assert_eq!((state.offset, state.finished), (3, false));
+        assert_eq!(state.oldest_start_time, Some(now - 3 * day));
+        assert!((state.progress().unwrap() - 0.6).abs() < 1e-9);
+        save_backfill_state(&conn, &state).unwrap();
+
+        // Resumes from the saved cursor; games older than the cap are left out
+        let mut state = get_backfill_state(&conn).unwrap().unwrap();
+        let kept = state.advance(page(4, 3), 3);
+        assert_eq!(kept.iter().map(|m| m.start_time).collect::<Vec<_>>(), vec![now - 4 * day, now - 5 * day]);
+        assert!(state.finished);
+        assert_eq!((state.offset, state.matches_seen, state.progress()), (6, 6, Some(1.0)));
+
+        // Without a cap, a short page means the first match was reached
+        let mut uncapped = BackfillState::new(None, None, now);
+        uncapped.advance(page(1, 3), 3);
+        assert!(!uncapped.finished && uncapped.progress().is_none());
+        // ...and progress is measured against the player's match count once it's known
+        uncapped.total_matches = Some(4);
+        assert_eq!(uncapped.progress(), Some(0.75));
+        uncapped.advance(page(4, 1), 3);
+        assert!(uncapped.finished);
+        assert_eq!(uncapped.progress(), Some(1.0));
+
+        save_backfill_state(&conn, &uncapped).unwrap();
+        clear_all_matches(&conn).unwrap();
+        assert_eq!(get_backfill_state(&conn).unwrap(), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! The error type shared by the database, API and command layers.
+//!
+//! Each variant has a stable `code` the frontend and retry logic branch on, and a
+//! user-facing `message`. Commands serialize it as `{ code, message, retryable,
+//! retry_after_secs }`.
+
+use std::fmt;
+use std::time::Duration;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use crate::http::HttpError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DotaKeeperError {
+    /// The provider answered 429; `retry_after` is its `Retry-After`, if it sent one
+    RateLimited { provider: &'static str, retry_after: Option<Duration> },
+    /// The provider is down (5xx)
+    ProviderUnavailable { provider: &'static str },
+    /// The provider couldn't be reached (offline, DNS, timeout)
+    Offline { provider: &'static str },
+    /// Any other transport failure talking to the provider
+    Network { provider: &'static str },
+    /// The API key was rejected or isn't configured
+    AuthFailed { provider: &'static str },
+    /// Nothing exists for what was asked (unknown match, player or goal)
+    NotFound(String),
+    /// The match exists but hasn't been parsed yet; try again later
+    NotParsedYet(String),
+    /// The provider answered with something unexpected
+    Api(String),
+    /// SQLite is busy or locked by another connection
+    DbBusy,
+    /// Creating or migrating the database schema failed
+    Schema(String),
+    /// Any other database failure
+    Database(String),
+    /// The request itself is invalid (bad setting, wrong file, unknown option)
+    InvalidInput(String),
+    Other(String),
+}
+
+impl DotaKeeperError {
+    /// Map a failed request to `provider` (e.g. "OpenDota") onto the matching variant.
+    pub fn from_http(provider: &'static str, err: HttpError) -> Self {
+        match err {
+            HttpError::Unreachable => DotaKeeperError::Offline { provider },
+            HttpError::Network(_) => DotaKeeperError::Network { provider },
+            HttpError::RateLimited { retry_after } => DotaKeeperError::RateLimited { provider, retry_after },
+            HttpError::Status(401 | 403) => DotaKeeperError::AuthFailed { provider },
+            HttpError::Status(404) => DotaKeeperError::NotFound(format!(
+                "No data found for this request. The match may not be tracked by {} yet.",
+                provider
+            )),
+            HttpError::Status(500..=599) => DotaKeeperError::ProviderUnavailable { provider },
+            HttpError::Status(code) => DotaKeeperError::Api(format!(
+                "{} returned an unexpected error (HTTP {}). Will retry later.",
+                provider, code
+            )),
+        }
+    }
+
+    /// A database error with what was being done, e.g. `db("Failed to insert match", e)`.
+    pub fn db(context: impl fmt::Display, err: rusqlite::Error) -> Self {
+        if is_busy(&err) {
+            DotaKeeperError::DbBusy
+        } else {
+            DotaKeeperError::Database(format!("{}: {}", context, err))
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            DotaKeeperError::RateLimited { .. } => "rate_limited",
+            DotaKeeperError::ProviderUnavailable { .. } => "provider_unavailable",
+            DotaKeeperError::Offline { .. } => "offline",
+            DotaKeeperError::Network { .. } => "network",
+            DotaKeeperError::AuthFailed { .. } => "auth_failed",
+            DotaKeeperError::NotFound(_) => "not_found",
+            DotaKeeperError::NotParsedYet(_) => "not_parsed_yet",
+            DotaKeeperError::Api(_) => "api_error",
+            DotaKeeperError::DbBusy => "db_busy",
+            DotaKeeperError::Schema(_) => "schema",
+            DotaKeeperError::Database(_) => "database",
+            DotaKeeperError::InvalidInput(_) => "invalid_input",
+            DotaKeeperError::Other(_) => "other",
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            DotaKeeperError::RateLimited { provider, .. } => {
+                format!("Too many requests to {}. Will try again shortly.", provider)
+            }
+            DotaKeeperError::ProviderUnavailable { provider } => format!(
+                "{} is unavailable right now. Your matches will sync automatically when it comes back.",
+                provider
+            ),
+            DotaKeeperError::Offline { provider } => format!(
+                "Couldn't reach {} — check your internet connection. Matches will sync automatically when you're back online.",
+                provider
+            ),
+            DotaKeeperError::Network { provider } => {
+                format!("Network error while contacting {}. Will retry automatically.", provider)
+            }
+            DotaKeeperError::AuthFailed { provider } => {
+                format!("{} API key is invalid or missing. Check the API key in Settings.", provider)
+            }
+            DotaKeeperError::DbBusy => "The database is busy. Try again in a moment.".to_string(),
+            DotaKeeperError::NotFound(msg)
+            | DotaKeeperError::NotParsedYet(msg)
+            | DotaKeeperError::Api(msg)
+            | DotaKeeperError::Schema(msg)
+            | DotaKeeperError::Database(msg)
+            | DotaKeeperError::InvalidInput(msg)
+            | DotaKeeperError::Other(msg) => msg.clone(),
+        }
+    }
+
+    /// How long the provider asked us to wait, if it was rate limited and said.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            DotaKeeperError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Rate limited or down: worth retrying later or on another provider.
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self, DotaKeeperError::RateLimited { .. } | DotaKeeperError::ProviderUnavailable { .. })
+    }
+
+    /// Transient: the same call may succeed later without the user changing anything.
+    pub fn is_retryable(&self) -> bool {
+        self.is_unavailable()
+            || matches!(
+                self,
+                DotaKeeperError::Offline { .. }
+                    | DotaKeeperError::Network { .. }
+                    | DotaKeeperError::NotParsedYet(_)
+                    | DotaKeeperError::DbBusy
+            )
+    }
+}
+
+fn is_busy(err: &rusqlite::Error) -> bool {
+    matches!(
+        err.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+impl fmt::Display for DotaKeeperError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for DotaKeeperError {}
+
+impl Serialize for DotaKeeperError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let retry_after_secs = self.retry_after().map(|d| d.as_secs());
+        let mut s = serializer.serialize_struct("DotaKeeperError", 4)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.message())?;
+        s.serialize_field("retryable", &self.is_retryable())?;
+        s.serialize_field("retry_after_secs", &retry_after_secs)?;
+        s.end()
+    }
+}
+
+impl From<rusqlite::Error> for DotaKeeperError {
+    fn from(err: rusqlite::Error) -> Self {
+        DotaKeeperError::db("Database error", err)
+    }
+}
@@ -0,0 +1,243 @@
+//! Match exports for analysis outside the app (spreadsheets, Python).
+//!
+//! Every match is written with its goal evaluations, item timings and per-minute CS,
+//! either as NDJSON (one JSON object per match) or as CSV (one row per match, with a
+//! pair of columns per goal and the nested lists packed into single cells).
+
+use std::io::Write;
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::database::{self, GoalEvaluation, Match, MatchCS, MatchState};
+use crate::error::DotaKeeperError;
+use crate::items;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn from_string(s: &str) -> Option<Self> {
+        match s {
+            "csv" => Some(ExportFormat::Csv),
+            "ndjson" | "json" => Some(ExportFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Which matches to export. Every field is optional; an empty filter exports everything.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MatchExportFilters {
+    #[serde(default)]
+    pub hero_id: Option<i32>,
+    /// Only matches that started at or after this Unix timestamp
+    #[serde(default)]
+    pub start_after: Option<i64>,
+    /// Only matches that started before this Unix timestamp
+    #[serde(default)]
+    pub start_before: Option<i64>,
+    /// Skip matches without parsed (per-minute) data
+    #[serde(default)]
+    pub parsed_only: bool,
+}
+
+impl MatchExportFilters {
+    fn matches(&self, m: &Match) -> bool {
+        self.hero_id.is_none_or(|h| m.hero_id == h)
+            && self.start_after.is_none_or(|t| m.start_time >= t)
+            && self.start_before.is_none_or(|t| m.start_time < t)
+            && (!self.parsed_only || m.parse_state == MatchState::Parsed)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedItemTiming {
+    item_id: i32,
+    item_name: Option<&'static str>,
+    timing_seconds: i32,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportedMinute {
+    minute: i32,
+    last_hits: i32,
+    denies: i32,
+}
+
+/// One exported match (an NDJSON line).
+#[derive(Debug, Serialize)]
+struct MatchExportRow {
+    #[serde(flatten)]
+    match_data: Match,
+    won: bool,
+    goals_achieved: i32,
+    goals_applicable: i32,
+    goal_evaluations: Vec<GoalEvaluation>,
+    item_timings: Vec<ExportedItemTiming>,
+    cs: Vec<ExportedMinute>,
+}
+
+/// Write every match passing `filters` to `dest`. Returns the number of matches written.
+pub fn export_matches(
+    conn: &Connection,
+    format: ExportFormat,
+    filters: &MatchExportFilters,
+    dest: &Path,
+) -> Result<usize, DotaKeeperError> {
+    let goals = database::get_active_goals(conn)?;
+    let matches: Vec<Match> = database::get_all_matches(conn)?
+        .into_iter()
+        .filter(|m| filters.matches(m))
+        .collect();
+    // One pass over the evaluation cache for every exported match
+    let mut evaluations_by_match = database::cached_goal_evaluations(conn, &goals, &matches)?;
+
+    let mut rows = Vec::new();
+    for match_data in matches {
+        let goal_evaluations = evaluations_by_match.remove(&match_data.match_id).unwrap_or_default();
+        let item_timings = database::get_item_timings_for_match(conn, match_data.match_id)?
+            .into_iter()
+            .map(|t| ExportedItemTiming {
+                item_id: t.item_id,
+                item_name: items::get_item_name(t.item_id),
+                timing_seconds: t.timing_seconds,
+            })
+            .collect();
+        let cs = database::get_match_cs_data(conn, match_data.match_id)?
+            .into_iter()
+            .map(|MatchCS { minute, last_hits, denies, .. }| ExportedMinute { minute, last_hits, denies })
+            .collect();
+        rows.push(MatchExportRow {
+            won: match_data.is_win(),
+            match_data,
+            goals_achieved: goal_evaluations.iter().filter(|e| e.achieved).count() as i32,
+            goals_applicable: goal_evaluations.len() as i32,
+            goal_evaluations,
+            item_timings,
+            cs,
+        });
+    }
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| DotaKeeperError::Other(format!("Failed to create export directory: {}", e)))?;
+    }
+    let file = std::fs::File::create(dest).map_err(|e| DotaKeeperError::Other(format!("Failed to create export file: {}", e)))?;
+    let mut out = std::io::BufWriter::new(file);
+    let written = match format {
+        ExportFormat::Ndjson => write_ndjson(&mut out, &rows),
+        ExportFormat::Csv => write_csv(&mut out, &rows, &goals.iter().map(|g| g.id).collect::<Vec<_>>()),
+    };
+    written
+        .and_then(|()| out.flush())
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to write export file: {}", e)))?;
+
+    Ok(rows.len())
+}
+
+fn write_ndjson(out: &mut impl Write, rows: &[MatchExportRow]) -> std::io::Result<()> {
+    for row in rows {
+        serde_json::to_writer(&mut *out, row)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+const CSV_MATCH_COLUMNS: &[&str] = &[
+    "match_id", "hero_id", "start_time", "duration", "game_mode", "lobby_type", "radiant_win",
+    "player_slot", "won", "kills", "deaths", "assists", "xp_per_min", "gold_per_min", "last_hits",
+    "denies", "hero_damage", "tower_damage", "hero_healing", "parse_state", "role", "rank_tier",
+    "patch", "parsed_by", "goals_achieved", "goals_applicable",
+];
+
+/// CSV with one row per match. Each goal gets `goal_<id>_achieved` / `goal_<id>_value`
+/// columns (blank when the goal doesn't apply); item timings are packed as
+/// `item:seconds;...` and CS as `;`-separated per-minute values.
+fn write_csv(out: &mut impl Write, rows: &[MatchExportRow], goal_ids: &[i64]) -> std::io::Result<()> {
+    let mut header: Vec<String> = CSV_MATCH_COLUMNS.iter().map(|c| c.to_string()).collect();
+    for id in goal_ids {
+        header.push(format!("goal_{}_achieved", id));
+        header.push(format!("goal_{}_value", id));
+    }
+    header.extend(["item_timings", "last_hits_per_minute", "denies_per_minute"].map(String::from));
+    write_csv_record(out, &header)?;
+
+    for row in rows {
+        let m = &row.match_data;
+        let mut record = vec![
+            m.match_id.to_string(),
+            m.hero_id.to_string(),
+            m.start_time.to_string(),
+            m.duration.to_string(),
+            m.game_mode.to_string(),
+            m.lobby_type.to_string(),
+            m.radiant_win.to_string(),
+            m.player_slot.to_string(),
+            row.won.to_string(),
+            m.kills.to_string(),
+            m.deaths.to_string(),
+            m.assists.to_string(),
+            m.xp_per_min.to_string(),
+            m.gold_per_min.to_string(),
+            m.last_hits.to_string(),
+            m.denies.to_string(),
+            m.hero_damage.to_string(),
+            m.tower_damage.to_string(),
+            m.hero_healing.to_string(),
+            format!("{:?}", m.parse_state),
+            m.role.to_string(),
+            m.rank_tier.map(|r| r.to_string()).unwrap_or_default(),
+            m.patch.clone().unwrap_or_default(),
+            m.parsed_by.clone().unwrap_or_default(),
+            row.goals_achieved.to_string(),
+            row.goals_applicable.to_string(),
+        ];
+        for id in goal_ids {
+            match row.goal_evaluations.iter().find(|e| e.goal.id == *id) {
+                Some(e) => {
+                    record.push(e.achieved.to_string());
+                    record.push(e.actual_value.to_string());
+                }
+                None => record.extend([String::new(), String::new()]),
+            }
+        }
+        record.push(
+            row.item_timings
+                .iter()
+                .map(|t| format!("{}:{}", t.item_name.unwrap_or("unknown"), t.timing_seconds))
+                .collect::<Vec<_>>()
+                .join(";"),
+        );
+        record.push(row.cs.iter().map(|c| c.last_hits.to_string()).collect::<Vec<_>>().join(";"));
+        record.push(row.cs.iter().map(|c| c.denies.to_string()).collect::<Vec<_>>().join(";"));
+        write_csv_record(out, &record)?;
+    }
+    Ok(())
+}
+
+fn write_csv_record(out: &mut impl Write, fields: &[String]) -> std::io::Result<()> {
+    let line = fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(",");
+    out.write_all(line.as_bytes())?;
+    out.write_all(b"\r\n")
+}
+
+/// Quote a field if it contains a delimiter, quote or line break (RFC 4180).
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
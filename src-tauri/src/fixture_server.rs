@@ -0,0 +1,120 @@
+//! In-process fake of the OpenDota and Stratz APIs for offline tests.
+//!
+//! Serves recorded JSON from `tests/fixtures/`:
+//! - `GET|POST /opendota/<path>` → `opendota/<path>.json` (query string ignored)
+//! - `POST /stratz` → `stratz/<OperationName>/<matchId|steamAccountId>.json`
+//!
+//! A `.status` file in place of the `.json` one (e.g. `matches/123.status`
+//! containing `503`) answers with that HTTP status instead. Anything without a
+//! fixture file gets a 404, which is how OpenDota reports unparsed matches.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static SERVER_URL: OnceLock<String> = OnceLock::new();
+
+/// Start the fixture server (once per test binary) and point both API clients at it.
+/// Returns the server's base URL.
+pub fn start() -> &'static str {
+    SERVER_URL.get_or_init(|| {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind fixture server");
+        let url = format!("http://{}", listener.local_addr().expect("Failed to read fixture server address"));
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                std::thread::spawn(move || {
+                    let _ = handle(stream);
+                });
+            }
+        });
+        crate::opendota::set_api_base(format!("{}/opendota", url));
+        crate::stratz::set_api_base(format!("{}/stratz", url));
+        crate::http::disable_rate_limits();
+        url
+    })
+}
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures")
+}
+
+fn handle(stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let fixture = fixture_path(&path, &body);
+    let status = fixture
+        .as_ref()
+        .and_then(|p| std::fs::read_to_string(p.with_extension("status")).ok())
+        .and_then(|s| s.trim().parse::<u16>().ok());
+    let response = match (status, fixture.and_then(|p| std::fs::read(p).ok())) {
+        (Some(code), _) => {
+            format!("HTTP/1.1 {} Fixture\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", code).into_bytes()
+        }
+        (None, Some(json)) => {
+            let mut r = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                json.len()
+            )
+            .into_bytes();
+            r.extend(json);
+            r
+        }
+        (None, None) => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec(),
+    };
+
+    let mut stream = stream;
+    stream.write_all(&response)?;
+    stream.flush()
+}
+
+/// Map a request onto the fixture file that answers it.
+fn fixture_path(path: &str, body: &[u8]) -> Option<PathBuf> {
+    let path = path.split('?').next().unwrap_or(path);
+
+    if let Some(rest) = path.strip_prefix("/opendota/") {
+        return Some(fixtures_dir().join("opendota").join(format!("{}.json", rest)));
+    }
+
+    if path == "/stratz" {
+        let request: serde_json::Value = serde_json::from_slice(body).ok()?;
+        let query = request["query"].as_str()?;
+        let operation = query
+            .split("query ")
+            .nth(1)?
+            .split('(')
+            .next()?
+            .trim();
+        let variables = &request["variables"];
+        let key = variables
+            .get("matchId")
+            .or_else(|| variables.get("steamAccountId"))?;
+        return Some(
+            fixtures_dir()
+                .join("stratz")
+                .join(operation)
+                .join(format!("{}.json", key)),
+        );
+    }
+
+    None
+}
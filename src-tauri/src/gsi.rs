@@ -0,0 +1,761 @@
+//! Game State Integration (GSI): Dota 2 POSTs the live game state as JSON to a local HTTP
+//! endpoint named in a `gamestate_integration_*.cfg` file in the game's cfg folder. The
+//! listener here tracks the current game and evaluates goals against it while it's played.
+//!
+//! Payloads are applied to a `LiveTracker`, which is plain data, so captured GSI JSON can be
+//! replayed through it without a running game.
+//!
+//! When a game ends its per-minute stats are kept as a recording, which fills in the match's
+//! per-minute data (and marks it parsed) once the match shows up in the match list.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+
+use crate::database::{self, Goal, GoalMetric};
+use crate::error::DotaKeeperError;
+use crate::items;
+use crate::opendota::{DetailedMatch, DetailedPlayer, PurchaseLogEntry};
+
+pub const DEFAULT_GSI_PORT: u16 = 43267;
+
+const CONFIG_FILE_NAME: &str = "gamestate_integration_dotakeeper.cfg";
+
+/// Payloads are a few KB; anything much larger isn't from the game.
+const MAX_BODY_BYTES: usize = 1 << 20;
+
+/// The game's request and header lines are short; a longer one isn't from the game.
+const MAX_HEADER_LINE_BYTES: u64 = 8 * 1024;
+
+/// The game sends each request in one go; a connection that stalls longer is dropped.
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+// ===== Payload =====
+
+/// The parts of a GSI payload we use. Every block is optional: the game omits `map`,
+/// `hero` etc. outside a match, and spectating sends per-team blocks that leave these
+/// fields empty.
+#[derive(Debug, Deserialize, Default)]
+pub struct GsiPayload {
+    pub map: Option<GsiMap>,
+    pub player: Option<GsiPlayer>,
+    pub hero: Option<GsiHero>,
+    pub items: Option<HashMap<String, GsiItem>>,
+    pub auth: Option<GsiAuth>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct GsiMap {
+    pub matchid: Option<String>,
+    pub clock_time: Option<i32>,
+    pub game_state: Option<String>,
+    pub paused: Option<bool>,
+    pub win_team: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct GsiPlayer {
+    pub accountid: Option<String>,
+    pub team_name: Option<String>,
+    pub kills: Option<i32>,
+    pub deaths: Option<i32>,
+    pub assists: Option<i32>,
+    pub last_hits: Option<i32>,
+    pub denies: Option<i32>,
+    pub gold: Option<i32>,
+    /// Not sent by older clients
+    pub net_worth: Option<i32>,
+    pub gpm: Option<i32>,
+    pub xpm: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct GsiHero {
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    pub level: Option<i32>,
+    /// Not sent by older clients
+    pub xp: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct GsiItem {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct GsiAuth {
+    pub token: Option<String>,
+}
+
+/// Parse a request body, rejecting payloads that don't carry our auth token.
+pub fn parse_payload(body: &str, token: &str) -> Result<GsiPayload, DotaKeeperError> {
+    let payload: GsiPayload = serde_json::from_str(body)
+        .map_err(|e| DotaKeeperError::InvalidInput(format!("Failed to parse GSI payload: {}", e)))?;
+    if payload.auth.as_ref().and_then(|a| a.token.as_deref()) != Some(token) {
+        return Err(DotaKeeperError::InvalidInput("GSI payload has a missing or wrong auth token".to_string()));
+    }
+    Ok(payload)
+}
+
+// ===== Live game =====
+
+/// An item that showed up in the inventory or stash, with the game clock it first appeared at.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LivePurchase {
+    pub item: String,
+    pub item_id: Option<i32>,
+    pub clock_time: i32,
+}
+
+/// The player's stats as of the first payload of a game minute.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MinuteSnapshot {
+    pub minute: i32,
+    pub last_hits: i32,
+    pub denies: i32,
+    pub net_worth: i32,
+    pub xp: Option<i32>,
+    pub level: i32,
+    pub kills: i32,
+    pub deaths: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LiveGame {
+    pub match_id: i64,
+    pub account_id: Option<u32>,
+    pub hero_id: Option<i32>,
+    pub hero_name: Option<String>,
+    /// "radiant" or "dire"
+    pub team: Option<String>,
+    /// e.g. "DOTA_GAMERULES_STATE_GAME_IN_PROGRESS"
+    pub game_state: String,
+    /// Game clock in seconds (negative before the horn)
+    pub clock_time: i32,
+    pub paused: bool,
+    /// Clock of the first payload seen for this game; stats before it weren't observed
+    pub tracked_from: i32,
+    pub kills: i32,
+    pub deaths: i32,
+    pub assists: i32,
+    pub last_hits: i32,
+    pub denies: i32,
+    pub gold: i32,
+    pub net_worth: i32,
+    pub level: i32,
+    pub xp: Option<i32>,
+    pub gold_per_min: i32,
+    pub xp_per_min: i32,
+    /// Game clock of each kill / death seen while tracking
+    pub kill_times: Vec<i32>,
+    pub death_times: Vec<i32>,
+    /// Inventory, backpack and neutral slot (item keys without the `item_` prefix)
+    pub items: Vec<String>,
+    pub purchases: Vec<LivePurchase>,
+    /// Items already held when tracking started mid-game, so their timing is unknown
+    pub untimed_items: Vec<String>,
+    pub minutes: Vec<MinuteSnapshot>,
+    /// "radiant" or "dire" once the game is over
+    pub win_team: Option<String>,
+}
+
+impl LiveGame {
+    fn snapshot(&self) -> MinuteSnapshot {
+        MinuteSnapshot {
+            minute: self.clock_time.max(0) / 60,
+            last_hits: self.last_hits,
+            denies: self.denies,
+            net_worth: self.net_worth,
+            xp: self.xp,
+            level: self.level,
+            kills: self.kills,
+            deaths: self.deaths,
+        }
+    }
+
+    /// Stats at the start of `minute`, if that moment was observed (or is now).
+    pub fn stats_at_minute(&self, minute: i32) -> Option<MinuteSnapshot> {
+        self.minutes
+            .iter()
+            .find(|s| s.minute == minute)
+            .cloned()
+            .or_else(|| (self.clock_time >= 0 && self.clock_time / 60 == minute).then(|| self.snapshot()))
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.win_team.is_some() || self.game_state == "DOTA_GAMERULES_STATE_POST_GAME"
+    }
+
+    /// The player's line in the shape the providers return, for `store_match_details`.
+    /// Per-minute arrays are indexed by minute, so this needs a game tracked from before
+    /// the horn and only keeps the minutes observed without a gap. Returns `None` otherwise.
+    pub fn to_detailed_match(&self, player_slot: i32) -> Option<DetailedMatch> {
+        if self.tracked_from > 0 {
+            return None;
+        }
+        let timeline: Vec<&MinuteSnapshot> = self
+            .minutes
+            .iter()
+            .enumerate()
+            .take_while(|(i, s)| s.minute == *i as i32)
+            .map(|(_, s)| s)
+            .collect();
+        if timeline.is_empty() {
+            return None;
+        }
+        let player = DetailedPlayer {
+            account_id: self.account_id,
+            player_slot,
+            hero_id: self.hero_id,
+            lane_role: None,
+            lh_t: Some(timeline.iter().map(|s| s.last_hits).collect()),
+            dn_t: Some(timeline.iter().map(|s| s.denies).collect()),
+            gold_t: Some(timeline.iter().map(|s| s.net_worth).collect()),
+            // Older clients don't send XP
+            xp_t: timeline.iter().map(|s| s.xp).collect(),
+            purchase_log: Some(
+                self.purchases
+                    .iter()
+                    .map(|p| PurchaseLogEntry { time: p.clock_time, key: p.item.clone() })
+                    .collect(),
+            ),
+            kills_log: None,
+            kill_times: Some(self.kill_times.clone()),
+            death_times: Some(self.death_times.clone()),
+            kills: Some(self.kills),
+            deaths: Some(self.deaths),
+            assists: Some(self.assists),
+            xp_per_min: Some(self.xp_per_min),
+            gold_per_min: Some(self.gold_per_min),
+            last_hits: Some(self.last_hits),
+            denies: Some(self.denies),
+            hero_damage: None,
+            tower_damage: None,
+            hero_healing: None,
+        };
+        Some(DetailedMatch { match_id: self.match_id, players: vec![player] })
+    }
+}
+
+/// Folds successive payloads into the state of the game being played.
+#[derive(Debug, Default)]
+pub struct LiveTracker {
+    game: Option<LiveGame>,
+    /// Items already accounted for in the current game
+    seen_items: HashSet<String>,
+    /// Whether the current game's end has been handed out by `take_finished`
+    finish_taken: bool,
+}
+
+impl LiveTracker {
+    pub fn game(&self) -> Option<&LiveGame> {
+        self.game.as_ref()
+    }
+
+    /// The current game once it's over, returned only the first time it's asked for.
+    pub fn take_finished(&mut self) -> Option<LiveGame> {
+        let game = self.game.as_ref().filter(|g| g.is_finished() && !self.finish_taken)?;
+        self.finish_taken = true;
+        Some(game.clone())
+    }
+
+    /// Apply one payload. Returns the updated game, or `None` when the payload doesn't
+    /// describe a game the player is in (menus, spectating).
+    pub fn apply(&mut self, payload: &GsiPayload) -> Option<&LiveGame> {
+        let map = payload.map.as_ref()?;
+        let player = payload.player.as_ref()?;
+        let match_id = map.matchid.as_deref()?.parse::<i64>().ok()?;
+        let clock_time = map.clock_time.unwrap_or(0);
+
+        let new_game = self.game.as_ref().is_none_or(|g| g.match_id != match_id);
+        if new_game {
+            self.game = Some(LiveGame { match_id, tracked_from: clock_time, ..LiveGame::default() });
+            self.seen_items.clear();
+            self.finish_taken = false;
+        }
+        let joined_late = self.game.as_ref().is_some_and(|g| g.minutes.is_empty() && g.tracked_from > 0);
+        let game = self.game.as_mut()?;
+
+        // Kills and deaths before the first payload happened at an unknown time
+        if !new_game {
+            let kills = player.kills.unwrap_or(game.kills);
+            let deaths = player.deaths.unwrap_or(game.deaths);
+            game.kill_times.extend((game.kills..kills).map(|_| clock_time));
+            game.death_times.extend((game.deaths..deaths).map(|_| clock_time));
+        }
+
+        game.clock_time = clock_time;
+        game.game_state = map.game_state.clone().unwrap_or_default();
+        game.paused = map.paused.unwrap_or(false);
+        game.win_team = map.win_team.clone().filter(|t| t != "none");
+
+        game.account_id = player.accountid.as_deref().and_then(|id| id.parse().ok()).or(game.account_id);
+        game.team = player.team_name.clone().or(game.team.take());
+        game.kills = player.kills.unwrap_or(game.kills);
+        game.deaths = player.deaths.unwrap_or(game.deaths);
+        game.assists = player.assists.unwrap_or(game.assists);
+        game.last_hits = player.last_hits.unwrap_or(game.last_hits);
+        game.denies = player.denies.unwrap_or(game.denies);
+        game.gold = player.gold.unwrap_or(game.gold);
+        // Older clients only report unspent gold
+        game.net_worth = player.net_worth.or(player.gold).unwrap_or(game.net_worth);
+        game.gold_per_min = player.gpm.unwrap_or(game.gold_per_min);
+        game.xp_per_min = player.xpm.unwrap_or(game.xp_per_min);
+
+        if let Some(hero) = &payload.hero {
+            if let Some(id) = hero.id.filter(|id| *id > 0) {
+                game.hero_id = Some(id);
+                game.hero_name = hero.name.clone();
+            }
+            game.level = hero.level.unwrap_or(game.level);
+            game.xp = hero.xp.or(game.xp);
+        }
+
+        if let Some(slots) = &payload.items {
+            let mut keys: Vec<&String> = slots.keys().collect();
+            keys.sort_by_key(|k| slot_order(k));
+            game.items.clear();
+            for key in keys {
+                let Some(item) = slots[key].name.as_deref().and_then(|n| n.strip_prefix("item_")) else {
+                    continue;
+                };
+                if key.starts_with("slot") || key.starts_with("neutral") {
+                    game.items.push(item.to_string());
+                }
+                // Neutral items drop rather than being bought
+                if key.starts_with("neutral") || !self.seen_items.insert(item.to_string()) {
+                    continue;
+                }
+                // Items held when we first saw a game already in progress have no known timing
+                if joined_late {
+                    game.untimed_items.push(item.to_string());
+                } else {
+                    game.purchases.push(LivePurchase {
+                        item: item.to_string(),
+                        item_id: items::get_item_id(item),
+                        clock_time,
+                    });
+                }
+            }
+        }
+
+        if clock_time >= 0 && !game.minutes.iter().any(|s| s.minute == clock_time / 60) {
+            let snapshot = game.snapshot();
+            game.minutes.push(snapshot);
+        }
+
+        self.game.as_ref()
+    }
+}
+
+/// Sort slots by kind then index ("slot2" before "slot10"), so `items` follows the inventory.
+fn slot_order(key: &str) -> (u8, u32) {
+    let split = key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len());
+    let kind = match &key[..split] {
+        "slot" => 0,
+        "neutral" => 1,
+        "teleport" => 2,
+        "stash" => 3,
+        _ => 4,
+    };
+    (kind, key[split..].parse().unwrap_or(0))
+}
+
+// ===== Live goal evaluation =====
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum LiveGoalStatus {
+    OnTrack,
+    Behind,
+    Achieved,
+    Missed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct LiveGoalProgress {
+    pub goal: Goal,
+    pub current_value: i32,
+    /// Where the value should be by now on a straight-line pace to the target
+    /// (for item timings and deaths: the target itself)
+    pub expected_value: i32,
+    pub status: LiveGoalStatus,
+    pub message: String,
+}
+
+/// What the frontend receives on every `live-game-update` event.
+#[derive(Debug, Serialize, Clone)]
+pub struct LiveGameUpdate {
+    pub game: LiveGame,
+    pub goals: Vec<LiveGoalProgress>,
+}
+
+fn metric_label(metric: &GoalMetric) -> &'static str {
+    match metric {
+        GoalMetric::LastHits => "CS",
+        GoalMetric::Denies => "denies",
+        GoalMetric::Networth => "net worth",
+        GoalMetric::Level => "levels",
+        GoalMetric::Kills => "kills",
+        GoalMetric::Deaths => "deaths",
+        GoalMetric::ItemTiming => "item",
+        GoalMetric::PartnerNetworth => "partner net worth",
+        GoalMetric::LaneNetworthLead => "lane lead",
+    }
+}
+
+fn metric_value(stats: &MinuteSnapshot, metric: &GoalMetric) -> Option<i32> {
+    match metric {
+        GoalMetric::LastHits => Some(stats.last_hits),
+        GoalMetric::Denies => Some(stats.denies),
+        GoalMetric::Networth => Some(stats.net_worth),
+        GoalMetric::Level => Some(stats.level),
+        GoalMetric::Kills => Some(stats.kills),
+        GoalMetric::Deaths => Some(stats.deaths),
+        // Need other players' data, which GSI only sends to spectators
+        GoalMetric::PartnerNetworth | GoalMetric::LaneNetworthLead | GoalMetric::ItemTiming => None,
+    }
+}
+
+fn format_clock(seconds: i32) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    format!("{}{}:{:02}", sign, seconds.abs() / 60, seconds.abs() % 60)
+}
+
+/// Progress on every goal that applies to the live game.
+pub fn evaluate_live_goals(game: &LiveGame, goals: &[Goal]) -> Vec<LiveGoalProgress> {
+    goals.iter().filter_map(|g| evaluate_live_goal(game, g)).collect()
+}
+
+/// Live counterpart of `database::evaluate_goal`. Goals scoped by role are skipped (the role
+/// is only known once the match is parsed), as are compound goals, and the game mode, lobby
+/// type and patch aren't checked (GSI doesn't report them). Returns `None` when the goal
+/// doesn't apply or can't be judged live.
+pub fn evaluate_live_goal(game: &LiveGame, goal: &Goal) -> Option<LiveGoalProgress> {
+    if goal.hero_scope.is_some() || !goal.scope.roles.is_empty() || !goal.conditions.is_empty() {
+        return None;
+    }
+    if goal.hero_id.is_some_and(|id| Some(id) != game.hero_id) {
+        return None;
+    }
+
+    if goal.metric == GoalMetric::ItemTiming {
+        return evaluate_live_item_goal(game, goal);
+    }
+
+    let minutes = goal.target_time_minutes;
+    let label = metric_label(&goal.metric);
+    let target = goal.target_value;
+    let progress = |current_value: i32, expected_value: i32, status: LiveGoalStatus, message: String| {
+        Some(LiveGoalProgress { goal: goal.clone(), current_value, expected_value, status, message })
+    };
+
+    // Past the target minute: the outcome is settled by the stats at that minute
+    if game.clock_time >= minutes * 60 {
+        let value = metric_value(&game.stats_at_minute(minutes)?, &goal.metric)?;
+        let achieved = match goal.metric {
+            GoalMetric::Deaths => value <= target,
+            _ => value >= target,
+        };
+        let (status, outcome) = if achieved {
+            (LiveGoalStatus::Achieved, "achieved")
+        } else {
+            (LiveGoalStatus::Missed, "missed")
+        };
+        return progress(value, target, status, format!("{}-minute {} goal {} ({} / {})", minutes, label, outcome, value, target));
+    }
+
+    let current = metric_value(&game.snapshot(), &goal.metric)?;
+    if goal.metric == GoalMetric::Deaths {
+        return if current > target {
+            progress(current, target, LiveGoalStatus::Missed, format!("{}-minute {} goal missed ({} / {})", minutes, label, current, target))
+        } else {
+            progress(current, target, LiveGoalStatus::OnTrack, format!("{} of {} deaths allowed before {}:00", current, target, minutes))
+        };
+    }
+
+    let elapsed = game.clock_time.max(0) as i64;
+    let expected = (target as i64 * elapsed / (minutes as i64 * 60)) as i32;
+    if current < expected {
+        progress(
+            current,
+            expected,
+            LiveGoalStatus::Behind,
+            format!("You're {} {} behind your {}-minute goal ({} / {})", expected - current, label, minutes, current, target),
+        )
+    } else {
+        progress(
+            current,
+            expected,
+            LiveGoalStatus::OnTrack,
+            format!("On pace for your {}-minute {} goal ({} / {})", minutes, label, current, target),
+        )
+    }
+}
+
+fn evaluate_live_item_goal(game: &LiveGame, goal: &Goal) -> Option<LiveGoalProgress> {
+    let item_id = goal.item_id?;
+    let name = items::get_item_name(item_id).unwrap_or("item");
+    let target = goal.target_value;
+    let (current_value, status, message) = match game.purchases.iter().find(|p| p.item_id == Some(item_id)) {
+        Some(p) if p.clock_time <= target => (
+            p.clock_time,
+            LiveGoalStatus::Achieved,
+            format!("{} bought at {} (goal {})", name, format_clock(p.clock_time), format_clock(target)),
+        ),
+        Some(p) => (
+            p.clock_time,
+            LiveGoalStatus::Missed,
+            format!("{} bought at {}, after the {} goal", name, format_clock(p.clock_time), format_clock(target)),
+        ),
+        // Bought before we started watching: the timing is unknown
+        None if game.untimed_items.iter().any(|i| i == name) => return None,
+        None if game.clock_time > target => (
+            game.clock_time,
+            LiveGoalStatus::Missed,
+            format!("{} not bought by {}", name, format_clock(target)),
+        ),
+        None => (
+            game.clock_time,
+            LiveGoalStatus::OnTrack,
+            format!("{} left to get {}", format_clock(target - game.clock_time), name),
+        ),
+    };
+    Some(LiveGoalProgress { goal: goal.clone(), current_value, expected_value: target, status, message })
+}
+
+// ===== Listener =====
+
+static TRACKER: OnceLock<Mutex<LiveTracker>> = OnceLock::new();
+
+/// Bumped on every start/stop; a listener exits once the generation it was started
+/// with is no longer current.
+static LISTENER_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+fn tracker() -> &'static Mutex<LiveTracker> {
+    TRACKER.get_or_init(|| Mutex::new(LiveTracker::default()))
+}
+
+/// The game currently (or most recently) tracked, with live goal progress.
+pub fn current_update(goals: &[Goal]) -> Result<Option<LiveGameUpdate>, DotaKeeperError> {
+    let tracker = tracker().lock().map_err(|e| DotaKeeperError::Other(format!("GSI tracker lock poisoned: {}", e)))?;
+    Ok(tracker.game().map(|game| LiveGameUpdate {
+        game: game.clone(),
+        goals: evaluate_live_goals(game, goals),
+    }))
+}
+
+/// Start listening on `port`, replacing any running listener.
+pub fn start(app: tauri::AppHandle, port: u16, token: String) {
+    let generation = LISTENER_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    tauri::async_runtime::spawn(serve(app, port, token, generation));
+}
+
+/// Stop the running listener (it exits within a second).
+pub fn stop() {
+    LISTENER_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+async fn serve(app: tauri::AppHandle, port: u16, token: String, generation: usize) {
+    use tokio::net::TcpListener;
+
+    // A listener being replaced may still hold the port for up to a second
+    let mut listener = None;
+    for _ in 0..5 {
+        match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(l) => {
+                listener = Some(l);
+                break;
+            }
+            Err(e) => {
+                tracing::warn!(target: "dota_keeper", "GSI: failed to bind port {}: {}", port, e);
+                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            }
+        }
+    }
+    let Some(listener) = listener else {
+        let _ = app.emit("gsi-status", serde_json::json!({ "listening": false, "error": format!("Port {} is in use", port) }));
+        return;
+    };
+    tracing::info!(target: "dota_keeper", "GSI: listening on 127.0.0.1:{}", port);
+    let _ = app.emit("gsi-status", serde_json::json!({ "listening": true, "port": port }));
+
+    while LISTENER_GENERATION.load(Ordering::SeqCst) == generation {
+        let accepted = tokio::time::timeout(tokio::time::Duration::from_secs(1), listener.accept()).await;
+        let Ok(Ok((stream, _))) = accepted else {
+            continue;
+        };
+        let app = app.clone();
+        let token = token.clone();
+        tokio::spawn(async move {
+            if let Some(body) = read_request_body(stream).await {
+                // Goal evaluation and recording take the blocking database lock
+                let _ = tokio::task::spawn_blocking(move || handle_payload(&app, &body, &token)).await;
+            }
+        });
+    }
+    tracing::info!(target: "dota_keeper", "GSI: listener on port {} stopped", port);
+}
+
+/// Read one HTTP request, answer `200 OK` and return its body. Gives up on requests
+/// that take longer than `REQUEST_TIMEOUT` or have overlong lines.
+pub(crate) async fn read_request_body(stream: tokio::net::TcpStream) -> Option<String> {
+    tokio::time::timeout(REQUEST_TIMEOUT, read_request(stream)).await.ok().flatten()
+}
+
+async fn read_request(stream: tokio::net::TcpStream) -> Option<String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+
+    let (reader_half, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader_half);
+
+    let request_line = read_header_line(&mut reader).await?;
+    if !request_line.starts_with("POST ") {
+        return None;
+    }
+
+    let mut content_length = 0usize;
+    loop {
+        let line = read_header_line(&mut reader).await?;
+        if line == "\r\n" || line == "\n" {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().ok()?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_BYTES {
+        return None;
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await.ok()?;
+    let _ = writer
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+        .await;
+    let _ = writer.shutdown().await;
+    String::from_utf8(body).ok()
+}
+
+/// One line of the request head, or None at the end of the stream or when the line runs
+/// past `MAX_HEADER_LINE_BYTES`.
+async fn read_header_line(reader: &mut (impl tokio::io::AsyncBufRead + Unpin)) -> Option<String> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+    let mut line = String::new();
+    (&mut *reader).take(MAX_HEADER_LINE_BYTES).read_line(&mut line).await.ok()?;
+    line.ends_with('\n').then_some(line)
+}
+
+fn handle_payload(app: &tauri::AppHandle, body: &str, token: &str) {
+    let payload = match parse_payload(body, token) {
+        Ok(p) => p,
+        Err(e) => {
+            tracing::debug!(target: "dota_keeper", "GSI: ignoring request: {}", e);
+            return;
+        }
+    };
+
+    let (game, finished) = {
+        let Ok(mut tracker) = tracker().lock() else { return };
+        let Some(game) = tracker.apply(&payload).cloned() else { return };
+        (game, tracker.take_finished())
+    };
+
+    let goals = database::get_db_conn()
+        .and_then(|conn| database::get_active_goals(&conn))
+        .unwrap_or_default();
+    let update = LiveGameUpdate { goals: evaluate_live_goals(&game, &goals), game };
+    let _ = app.emit("live-game-update", &update);
+
+    if let Some(game) = finished {
+        if let Err(e) = record_finished_game(app, &game) {
+            tracing::warn!(target: "dota_keeper", "GSI: failed to record match {}: {}", game.match_id, e);
+        }
+    }
+}
+
+/// Keep a finished game's stats for the active profile and apply them right away if the
+/// match is already in the match list (otherwise the next refresh does).
+fn record_finished_game(app: &tauri::AppHandle, game: &LiveGame) -> Result<(), DotaKeeperError> {
+    let conn = database::get_db_conn()?;
+    let Some(account_id) = game.account_id.filter(|id| database::get_db_account(&conn).ok().flatten() == Some(*id)) else {
+        return Ok(()); // not the active profile's game
+    };
+    let data = serde_json::to_string(game).map_err(|e| DotaKeeperError::Other(format!("Failed to serialize GSI recording: {}", e)))?;
+    database::save_gsi_recording(&conn, game.match_id, account_id, &data)?;
+    for match_id in crate::apply_gsi_recordings(&conn, account_id)? {
+        let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Parsed" }));
+    }
+    Ok(())
+}
+
+// ===== Game config file =====
+
+/// Contents of the GSI config file that tells Dota 2 where to send its game state.
+pub fn config_file_contents(port: u16, token: &str) -> String {
+    format!(
+        "\"Dota Keeper\"\n\
+         {{\n\
+         \t\"uri\"\t\t\"http://127.0.0.1:{port}/\"\n\
+         \t\"timeout\"\t\"5.0\"\n\
+         \t\"buffer\"\t\"0.1\"\n\
+         \t\"throttle\"\t\"0.5\"\n\
+         \t\"heartbeat\"\t\"30.0\"\n\
+         \t\"data\"\n\
+         \t{{\n\
+         \t\t\"provider\"\t\"1\"\n\
+         \t\t\"map\"\t\t\"1\"\n\
+         \t\t\"player\"\t\"1\"\n\
+         \t\t\"hero\"\t\t\"1\"\n\
+         \t\t\"items\"\t\t\"1\"\n\
+         \t}}\n\
+         \t\"auth\"\n\
+         \t{{\n\
+         \t\t\"token\"\t\"{token}\"\n\
+         \t}}\n\
+         }}\n"
+    )
+}
+
+/// Default Steam install locations of Dota 2, checked when no folder is given.
+fn default_dota_dirs() -> Vec<PathBuf> {
+    let mut candidates = vec![
+        PathBuf::from(r"C:\Program Files (x86)\Steam\steamapps\common\dota 2 beta"),
+        PathBuf::from(r"C:\Program Files\Steam\steamapps\common\dota 2 beta"),
+    ];
+    if let Some(home) = dirs::home_dir() {
+        candidates.push(home.join(".steam/steam/steamapps/common/dota 2 beta"));
+        candidates.push(home.join(".local/share/Steam/steamapps/common/dota 2 beta"));
+        candidates.push(home.join("Library/Application Support/Steam/steamapps/common/dota 2 beta"));
+    }
+    candidates
+}
+
+/// Write the config file into the Dota 2 install at `dota_dir` (or the first default Steam
+/// location that exists). Returns the path written. The game picks it up on next launch.
+pub fn install_config_file(dota_dir: Option<PathBuf>, port: u16, token: &str) -> Result<PathBuf, DotaKeeperError> {
+    let dota_dir = match dota_dir {
+        Some(dir) => dir,
+        None => default_dota_dirs()
+            .into_iter()
+            .find(|d| d.is_dir())
+            .ok_or_else(|| DotaKeeperError::NotFound("Could not find the Dota 2 install folder. Please select it manually.".to_string()))?,
+    };
+    if !dota_dir.join("game").join("dota").is_dir() {
+        return Err(DotaKeeperError::InvalidInput(format!("{} is not a Dota 2 install folder", dota_dir.display())));
+    }
+    let cfg_dir = dota_dir.join("game").join("dota").join("cfg").join("gamestate_integration");
+    std::fs::create_dir_all(&cfg_dir)
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to create GSI config directory: {}", e)))?;
+    let path = cfg_dir.join(CONFIG_FILE_NAME);
+    std::fs::write(&path, config_file_contents(port, token))
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to write GSI config file: {}", e)))?;
+    Ok(path)
+}
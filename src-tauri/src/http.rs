@@ -0,0 +1,318 @@
+//! The one HTTP client every API module sends through.
+//!
+//! Requests are tagged with the `ApiHost` they go to. Each rate-limited host has a token
+//! bucket sized to its published limits, so bursts (backfills, background parsing, a sync
+//! started mid-backfill) queue up here instead of tripping the API's limiter. A 429 (or a
+//! 503 with `Retry-After`) pauses every request to that host until the server says to come
+//! back. Per-host counters are exposed through `diagnostics` for the diagnostics command.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tracing::debug;
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+static HOSTS: OnceLock<Mutex<HashMap<ApiHost, HostState>>> = OnceLock::new();
+
+/// Set by the offline fixture server: requests then skip throttling and backoff.
+static LIMITS_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// How long to hold off a host that answered 429 without a `Retry-After` header.
+const DEFAULT_RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Longer `Retry-After` values are cut down to this, so a bogus header can't shut a host
+/// out for days (or overflow the pause deadline).
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+/// A `Retry-After` at most this long is waited out and the request retried once;
+/// longer ones are returned to the caller as `HttpError::RateLimited`.
+const MAX_INLINE_RETRY_WAIT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiHost {
+    /// OpenDota without an API key (free tier)
+    OpenDota,
+    /// OpenDota with an API key (higher limits)
+    OpenDotaKeyed,
+    Stratz,
+    /// Analytics only send from release builds
+    #[cfg_attr(debug_assertions, allow(dead_code))]
+    PostHog,
+    GitHub,
+    Steam,
+}
+
+impl ApiHost {
+    pub fn opendota(api_key: Option<&str>) -> Self {
+        if api_key.is_some() { ApiHost::OpenDotaKeyed } else { ApiHost::OpenDota }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ApiHost::OpenDota => "opendota",
+            ApiHost::OpenDotaKeyed => "opendota_keyed",
+            ApiHost::Stratz => "stratz",
+            ApiHost::PostHog => "posthog",
+            ApiHost::GitHub => "github",
+            ApiHost::Steam => "steam",
+        }
+    }
+
+    /// (requests per minute, burst) for hosts that publish a limit
+    fn limit(self) -> Option<(u32, u32)> {
+        match self {
+            ApiHost::OpenDota => Some((60, 10)),
+            ApiHost::OpenDotaKeyed => Some((1200, 20)),
+            ApiHost::Stratz => Some((250, 20)),
+            ApiHost::PostHog | ApiHost::GitHub | ApiHost::Steam => None,
+        }
+    }
+}
+
+/// Why a request failed. Decoding the body is left to the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HttpError {
+    /// Couldn't connect, or the request timed out
+    Unreachable,
+    /// Any other transport failure
+    Network(String),
+    /// HTTP 429 (or 503 with `Retry-After`); the host is paused for `retry_after`
+    RateLimited { retry_after: Option<Duration> },
+    /// Any other non-success status
+    Status(u16),
+}
+
+impl HttpError {
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            HttpError::RateLimited { .. } => Some(429),
+            HttpError::Status(code) => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Unreachable => write!(f, "host unreachable"),
+            HttpError::Network(e) => write!(f, "network error: {}", e),
+            HttpError::RateLimited { retry_after: Some(wait) } => write!(f, "rate limited, retry after {}s", wait.as_secs()),
+            HttpError::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            HttpError::Status(code) => write!(f, "HTTP {}", code),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {}
+
+/// Token bucket for one host: `burst` requests at once, refilled at `per_minute`.
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    tokens: f64,
+    burst: f64,
+    per_second: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(per_minute: u32, burst: u32, now: Instant) -> Self {
+        TokenBucket { tokens: burst as f64, burst: burst as f64, per_second: per_minute as f64 / 60.0, last_refill: now }
+    }
+
+    /// Take a token, or say how long until one is available.
+    pub(crate) fn take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.burst);
+        self.last_refill = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / self.per_second))
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct HostState {
+    bucket: Option<TokenBucket>,
+    /// Set from `Retry-After`: no requests to this host before then
+    paused_until: Option<Instant>,
+    stats: HostStats,
+}
+
+#[derive(Debug, Default, Clone)]
+struct HostStats {
+    requests: u64,
+    throttled: u64,
+    throttled_wait_ms: u64,
+    rate_limited: u64,
+    server_errors: u64,
+    network_errors: u64,
+}
+
+/// Counters for one host since the app started, for the diagnostics screen.
+#[derive(Debug, Serialize, Clone)]
+pub struct HostDiagnostics {
+    pub host: &'static str,
+    /// `None` for hosts without a client-side limit
+    pub limit_per_minute: Option<u32>,
+    pub requests: u64,
+    /// Requests that had to wait for the limiter
+    pub throttled: u64,
+    pub throttled_wait_ms: u64,
+    /// 429 responses
+    pub rate_limited: u64,
+    pub server_errors: u64,
+    pub network_errors: u64,
+    /// Seconds until the host may be called again after a 429 (0 when not paused)
+    pub paused_for_secs: u64,
+}
+
+/// The shared client (connection pooling across every module).
+pub fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(concat!("DotaKeeper/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(60))
+            .build()
+            .unwrap_or_default()
+    })
+}
+
+/// Turn off throttling and backoff (the in-process fixture server has no limits).
+#[cfg(test)]
+pub fn disable_rate_limits() {
+    LIMITS_DISABLED.store(true, Ordering::Relaxed);
+}
+
+fn with_host<T>(host: ApiHost, f: impl FnOnce(&mut HostState) -> T) -> T {
+    let hosts = HOSTS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut hosts = hosts.lock().unwrap_or_else(|e| e.into_inner());
+    let state = hosts.entry(host).or_insert_with(|| HostState {
+        bucket: host.limit().map(|(per_minute, burst)| TokenBucket::new(per_minute, burst, Instant::now())),
+        ..HostState::default()
+    });
+    f(state)
+}
+
+/// Wait until `host` may be called again (limiter token and any `Retry-After` pause).
+async fn acquire(host: ApiHost) {
+    if LIMITS_DISABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut waited = Duration::ZERO;
+    loop {
+        let wait = with_host(host, |state| {
+            let now = Instant::now();
+            if let Some(until) = state.paused_until.filter(|until| *until > now) {
+                return Some(until - now);
+            }
+            state.paused_until = None;
+            state.bucket.as_mut().and_then(|bucket| bucket.take(now).err())
+        });
+        let Some(wait) = wait else { break };
+        waited += wait;
+        tokio::time::sleep(wait).await;
+    }
+    if !waited.is_zero() {
+        debug!("http: waited {}ms for {}", waited.as_millis(), host.name());
+        with_host(host, |state| {
+            state.stats.throttled += 1;
+            state.stats.throttled_wait_ms += waited.as_millis() as u64;
+        });
+    }
+}
+
+/// Parse a `Retry-After` header: delay in seconds, or an HTTP date. Capped at
+/// `MAX_RETRY_AFTER`.
+pub(crate) fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let wait = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+            (date.with_timezone(&chrono::Utc) - now).to_std().unwrap_or(Duration::ZERO)
+        }
+    };
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+/// Send `request` to `host` through the limiter. Non-success statuses come back as
+/// errors; a short `Retry-After` is waited out and the request retried once.
+pub async fn send(host: ApiHost, request: reqwest::RequestBuilder) -> Result<reqwest::Response, HttpError> {
+    let mut retry = request.try_clone();
+    let mut request = request;
+    loop {
+        acquire(host).await;
+        with_host(host, |state| state.stats.requests += 1);
+
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                with_host(host, |state| state.stats.network_errors += 1);
+                return Err(if e.is_connect() || e.is_timeout() { HttpError::Unreachable } else { HttpError::Network(e.to_string()) });
+            }
+        };
+
+        let status = response.status().as_u16();
+        if response.status().is_success() {
+            return Ok(response);
+        }
+
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, chrono::Utc::now()));
+        let rate_limited = status == 429 || (status == 503 && retry_after.is_some());
+        if !rate_limited {
+            if status >= 500 {
+                with_host(host, |state| state.stats.server_errors += 1);
+            }
+            return Err(HttpError::Status(status));
+        }
+
+        with_host(host, |state| {
+            state.stats.rate_limited += 1;
+            if !LIMITS_DISABLED.load(Ordering::Relaxed) {
+                let now = Instant::now();
+                state.paused_until = Some(now.checked_add(retry_after.unwrap_or(DEFAULT_RATE_LIMIT_BACKOFF)).unwrap_or(now));
+            }
+        });
+        debug!("http: {} rate limited (retry after {:?})", host.name(), retry_after);
+
+        match (retry.take(), retry_after) {
+            (Some(again), Some(wait)) if wait <= MAX_INLINE_RETRY_WAIT => request = again,
+            _ => return Err(HttpError::RateLimited { retry_after }),
+        }
+    }
+}
+
+/// Counters for every host contacted since startup.
+pub fn diagnostics() -> Vec<HostDiagnostics> {
+    let hosts = HOSTS.get_or_init(|| Mutex::new(HashMap::new()));
+    let hosts = hosts.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    let mut result: Vec<HostDiagnostics> = hosts
+        .iter()
+        .map(|(host, state)| HostDiagnostics {
+            host: host.name(),
+            limit_per_minute: host.limit().map(|(per_minute, _)| per_minute),
+            requests: state.stats.requests,
+            throttled: state.stats.throttled,
+            throttled_wait_ms: state.stats.throttled_wait_ms,
+            rate_limited: state.stats.rate_limited,
+            server_errors: state.stats.server_errors,
+            network_errors: state.stats.network_errors,
+            paused_for_secs: state.paused_until.map_or(0, |until| until.saturating_duration_since(now).as_secs()),
+        })
+        .collect();
+    result.sort_by_key(|d| d.host);
+    result
+}
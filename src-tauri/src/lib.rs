@@ -1,10 +1,19 @@
 use rusqlite::OptionalExtension;
 mod analytics;
+mod backup;
 mod database;
+mod error;
+mod export;
+mod gsi;
+mod http;
 mod items;
 mod opendota;
+mod provider;
+mod replay;
 mod settings;
 mod stratz;
+#[cfg(test)]
+mod fixture_server;
 
 use std::sync::OnceLock;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
@@ -62,6 +71,11 @@ static BG_PARSER_PENDING: AtomicUsize = AtomicUsize::new(0);
 static BACKFILL_ACTIVE: AtomicBool = AtomicBool::new(false);
 static BACKFILL_PENDING: AtomicUsize = AtomicUsize::new(0);
 
+// Set when a match is parsed or a goal changes; background_parse_loop then checks
+// whether any active goal has been achieved. Starts set to pick up matches parsed just
+// before the last exit; only games played since a goal became active are counted.
+static GOAL_COMPLETION_CHECK_DUE: AtomicBool = AtomicBool::new(true);
+
 fn get_session_id() -> String {
     SESSION_ID
         .get_or_init(|| uuid::Uuid::new_v4().to_string())
@@ -69,19 +83,19 @@ fn get_session_id() -> String {
 }
 
 use database::{
-    accept_weekly_challenge, clear_all_matches, delete_goal, evaluate_match_goals, factory_reset_db,
-    get_active_weekly_challenge, get_all_goals, get_all_matches, get_challenge_history,
+    accept_weekly_challenge, clear_all_matches, delete_goal, evaluate_match_goals,
+    get_active_weekly_challenge, get_all_goals, get_challenge_history,
     get_daily_challenge_progress, get_daily_streak, get_db_dir, get_db_conn, get_favorite_hero_ids,
     get_goal_by_id, get_goal_match_data, get_goals_with_daily_progress, get_item_timings_for_match,
     get_last_hits_analysis, get_match_cs_data, get_match_networth_data, get_match_xp_data,
-    get_matches_with_goals, get_oldest_match_timestamp,
+    get_matches_with_goals,
     get_or_generate_daily_challenge, get_or_generate_hero_suggestion, get_unparsed_matches,
     get_weekly_challenge_options, get_weekly_challenge_progress, init_db, init_shared_db,
     backfill_match_patches, clear_item_timings_for_match, get_all_patches, insert_goal,
     insert_item_timing, insert_match,
-    insert_match_cs_data, insert_match_xp_data, insert_player_networth,
+    insert_match_cs_data, insert_match_events, insert_match_xp_data, insert_player_networth,
     match_exists, regenerate_hero_suggestion, reroll_weekly_challenges, set_db_dir,
-    skip_weekly_challenge, toggle_hero_favorite, update_goal, update_match_partner_slot,
+    skip_weekly_challenge, toggle_hero_favorite, update_goal, update_match_parsed_by, update_match_partner_slot,
     update_match_patch, update_match_role, update_match_state, update_match_stats, upsert_patches,
     ChallengeHistoryItem, ChallengeOption, DailyChallenge,
     DailyChallengeProgress, Goal, GoalEvaluation, GoalWithDailyProgress, HeroGoalSuggestion,
@@ -93,6 +107,9 @@ use database::{
     HeroBenchmarkRow, BenchmarkResult, get_user_lh_at_minute_history,
 };
 use serde_json;
+use error::DotaKeeperError;
+use http::ApiHost;
+use provider::MatchDataProvider;
 use settings::{set_settings_dir, AnalyticsConsent, Settings};
 use tauri::{Emitter, Manager};
 /// Get the current settings
@@ -107,11 +124,63 @@ fn get_os_locale() -> String {
     sys_locale::get_locale().unwrap_or_else(|| "en".to_string())
 }
 
-/// Save the Steam ID to settings
+/// Save the Steam ID to settings, registering it as a profile and opening its database
+#[tauri::command]
+fn save_steam_id(steam_id: String) -> Result<Settings, DotaKeeperError> {
+    activate_profile(&steam_id)
+}
+
+/// List every tracked account
+#[tauri::command]
+fn get_profiles() -> Vec<settings::Profile> {
+    Settings::load().profiles
+}
+
+/// Switch the active account, adding it as a new profile if it isn't tracked yet
+#[tauri::command]
+fn switch_account(steam_id: String) -> Result<Settings, DotaKeeperError> {
+    let settings = activate_profile(&steam_id)?;
+    // A brand new profile database has no benchmark data until the next launch otherwise.
+    if !has_benchmark_data(&*get_db_conn()?)? {
+        tauri::async_runtime::spawn(async {
+            if let Err(e) = fetch_and_store_benchmarks().await {
+                tracing::warn!(target: "dota_keeper", "Benchmark fetch for new profile failed: {}", e);
+            }
+        });
+    }
+    Ok(settings)
+}
+
+/// Set the label shown for a profile in the account switcher
 #[tauri::command]
-fn save_steam_id(steam_id: String) -> Result<Settings, String> {
+fn rename_profile(steam_id: String, display_name: Option<String>) -> Result<Settings, DotaKeeperError> {
+    let mut settings = Settings::load();
+    let profile = settings
+        .profiles
+        .iter_mut()
+        .find(|p| p.steam_id == steam_id)
+        .ok_or_else(|| DotaKeeperError::NotFound("Profile not found".to_string()))?;
+    profile.display_name = display_name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    settings.save()?;
+    Ok(settings)
+}
+
+/// Make `steam_id` the active profile: register it if needed, swap the shared connection
+/// to its database and save it as the logged-in account.
+fn activate_profile(steam_id: &str) -> Result<Settings, DotaKeeperError> {
+    let account_id = steam_id64_to_id32(steam_id)?;
     let mut settings = Settings::load();
-    settings.steam_id = Some(steam_id);
+    let profile = settings.add_profile(steam_id);
+    settings.steam_id = Some(steam_id.to_string());
+
+    // Hold the lock until settings are saved so no sync or parse can write in between.
+    let mut conn = get_db_conn()?;
+    match (database::get_db_account(&conn)?, &profile.db_key) {
+        (Some(owner), _) if owner == account_id => {}
+        // Only the original database is ever open without an owner; the first profile claims it.
+        (None, None) => database::set_db_account(&conn, account_id)?,
+        _ => *conn = database::init_db(profile.db_key.as_deref(), Some(account_id))?,
+    }
     settings.save()?;
     Ok(settings)
 }
@@ -121,7 +190,7 @@ fn save_steam_id(steam_id: String) -> Result<Settings, String> {
 fn save_suggestion_difficulty(
     difficulty: String,
     custom_percentage: Option<f64>,
-) -> Result<Settings, String> {
+) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.suggestion_difficulty = difficulty;
     settings.suggestion_custom_percentage = custom_percentage;
@@ -131,7 +200,7 @@ fn save_suggestion_difficulty(
 
 /// Update analytics consent setting
 #[tauri::command]
-fn save_analytics_consent(consent: String) -> Result<Settings, String> {
+fn save_analytics_consent(consent: String) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.analytics_consent = match consent.as_str() {
         "Accepted" => AnalyticsConsent::Accepted,
@@ -144,7 +213,7 @@ fn save_analytics_consent(consent: String) -> Result<Settings, String> {
 
 /// Identify the user in analytics (should be called once on app start)
 #[tauri::command]
-async fn identify_analytics_user() -> Result<(), String> {
+async fn identify_analytics_user() -> Result<(), DotaKeeperError> {
     let settings = Settings::load();
     let is_accepted = settings.analytics_consent == AnalyticsConsent::Accepted;
     let installation_id = settings.installation_id.clone();
@@ -153,7 +222,7 @@ async fn identify_analytics_user() -> Result<(), String> {
 
 /// Track an analytics event (async, fails silently)
 #[tauri::command]
-async fn track_event(event: String, properties: Option<serde_json::Value>) -> Result<(), String> {
+async fn track_event(event: String, properties: Option<serde_json::Value>) -> Result<(), DotaKeeperError> {
     let settings = Settings::load();
     let is_accepted = settings.analytics_consent == AnalyticsConsent::Accepted;
     let installation_id = settings.installation_id.clone();
@@ -161,9 +230,10 @@ async fn track_event(event: String, properties: Option<serde_json::Value>) -> Re
     analytics::track_event(event, properties, is_accepted, installation_id, session_id).await
 }
 
-/// Clear the Steam ID (logout)
+/// Clear the Steam ID (logout). The profile and its data are kept, so logging back in
+/// with the same account picks up where it left off.
 #[tauri::command]
-fn logout() -> Result<Settings, String> {
+fn logout() -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.steam_id = None;
     settings.save()?;
@@ -172,7 +242,7 @@ fn logout() -> Result<Settings, String> {
 
 /// Set the data provider ("opendota" or "stratz")
 #[tauri::command]
-fn save_data_provider(provider: String) -> Result<Settings, String> {
+fn save_data_provider(provider: String) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.data_provider = provider;
     settings.save()?;
@@ -181,7 +251,7 @@ fn save_data_provider(provider: String) -> Result<Settings, String> {
 
 /// Save the Stratz API key
 #[tauri::command]
-fn save_stratz_api_key(api_key: Option<String>) -> Result<Settings, String> {
+fn save_stratz_api_key(api_key: Option<String>) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.stratz_api_key = api_key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
     settings.save()?;
@@ -190,7 +260,7 @@ fn save_stratz_api_key(api_key: Option<String>) -> Result<Settings, String> {
 
 /// Save the OpenDota API key (optional — raises rate limit when provided)
 #[tauri::command]
-fn save_opendota_api_key(api_key: Option<String>) -> Result<Settings, String> {
+fn save_opendota_api_key(api_key: Option<String>) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.opendota_api_key = api_key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty());
     settings.save()?;
@@ -199,7 +269,7 @@ fn save_opendota_api_key(api_key: Option<String>) -> Result<Settings, String> {
 
 /// Enable or disable mental health mood tracking
 #[tauri::command]
-fn save_mental_health_enabled(enabled: bool) -> Result<Settings, String> {
+fn save_mental_health_enabled(enabled: bool) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.mental_health_tracking_enabled = enabled;
     settings.save()?;
@@ -208,7 +278,7 @@ fn save_mental_health_enabled(enabled: bool) -> Result<Settings, String> {
 
 /// Save the check-in frequency preference
 #[tauri::command]
-fn save_checkin_frequency(frequency: String) -> Result<Settings, String> {
+fn save_checkin_frequency(frequency: String) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.checkin_frequency = frequency;
     settings.save()?;
@@ -217,7 +287,7 @@ fn save_checkin_frequency(frequency: String) -> Result<Settings, String> {
 
 /// Mark the first-enable explanation modal as shown
 #[tauri::command]
-fn mark_mental_health_intro_shown() -> Result<(), String> {
+fn mark_mental_health_intro_shown() -> Result<(), DotaKeeperError> {
     let mut settings = Settings::load();
     settings.mental_health_intro_shown = true;
     settings.save()?;
@@ -226,10 +296,10 @@ fn mark_mental_health_intro_shown() -> Result<(), String> {
 
 /// Delete all mood check-in data (does not affect match history or goals)
 #[tauri::command]
-fn clear_mood_data() -> Result<(), String> {
+fn clear_mood_data() -> Result<(), DotaKeeperError> {
     let conn = get_db_conn()?;
     conn.execute("DELETE FROM mood_checkins", [])
-        .map_err(|e| format!("Failed to clear mood data: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("Failed to clear mood data", e))?;
     Ok(())
 }
 
@@ -242,7 +312,7 @@ struct PendingCheckin {
 /// Return the most recent match that needs a check-in, if trigger conditions are met.
 /// Returns null if tracking is disabled, no qualifying match exists, or no trigger fires.
 #[tauri::command]
-fn get_pending_checkin() -> Result<Option<PendingCheckin>, String> {
+fn get_pending_checkin() -> Result<Option<PendingCheckin>, DotaKeeperError> {
     let settings = Settings::load();
     if !settings.mental_health_tracking_enabled {
         return Ok(None);
@@ -260,7 +330,7 @@ fn get_pending_checkin() -> Result<Option<PendingCheckin>, String> {
             |row| row.get(0),
         )
         .optional()
-        .map_err(|e| format!("DB error getting pending checkin: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("DB error getting pending checkin", e))?;
 
     let Some(match_id) = pending else {
         return Ok(None);
@@ -273,7 +343,7 @@ fn get_pending_checkin() -> Result<Option<PendingCheckin>, String> {
     }
     let mut stmt = conn
         .prepare("SELECT radiant_win, player_slot FROM matches ORDER BY start_time DESC LIMIT 5")
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("DB error", e))?;
     let recent: Vec<bool> = stmt
         .query_map([], |row| {
             Ok(RecentMatch {
@@ -281,7 +351,7 @@ fn get_pending_checkin() -> Result<Option<PendingCheckin>, String> {
                 player_slot: row.get(1)?,
             })
         })
-        .map_err(|e| format!("DB error: {}", e))?
+        .map_err(|e| DotaKeeperError::db("DB error", e))?
         .filter_map(|r| r.ok())
         .map(|m| {
             let is_radiant = m.player_slot < 128;
@@ -359,7 +429,7 @@ fn save_mood_checkin(
     energy: i32,
     calm: i32,
     attribution: Option<String>,
-) -> Result<(), String> {
+) -> Result<(), DotaKeeperError> {
     let conn = get_db_conn()?;
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -371,13 +441,13 @@ fn save_mood_checkin(
          VALUES (?1, ?2, ?3, ?4, ?5, 0)",
         rusqlite::params![match_id, now, energy, calm, attribution],
     )
-    .map_err(|e| format!("Failed to save mood checkin: {}", e))?;
+    .map_err(|e| DotaKeeperError::db("Failed to save mood checkin", e))?;
     Ok(())
 }
 
 /// Dismiss a check-in for a match (records a skip so it won't appear again)
 #[tauri::command]
-fn dismiss_checkin(match_id: i64) -> Result<(), String> {
+fn dismiss_checkin(match_id: i64) -> Result<(), DotaKeeperError> {
     let conn = get_db_conn()?;
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
@@ -387,7 +457,7 @@ fn dismiss_checkin(match_id: i64) -> Result<(), String> {
         "INSERT OR IGNORE INTO mood_checkins (match_id, checked_at, skipped) VALUES (?1, ?2, 1)",
         rusqlite::params![match_id, now],
     )
-    .map_err(|e| format!("Failed to dismiss checkin: {}", e))?;
+    .map_err(|e| DotaKeeperError::db("Failed to dismiss checkin", e))?;
     Ok(())
 }
 
@@ -423,7 +493,7 @@ struct TiltAssessment {
 /// Compute a tilt assessment from objective match data + subjective mood check-ins.
 /// Returns None if mental health tracking is disabled.
 #[tauri::command]
-fn get_tilt_assessment() -> Result<Option<TiltAssessment>, String> {
+fn get_tilt_assessment() -> Result<Option<TiltAssessment>, DotaKeeperError> {
     let settings = Settings::load();
     if !settings.mental_health_tracking_enabled {
         return Ok(None);
@@ -438,14 +508,14 @@ fn get_tilt_assessment() -> Result<Option<TiltAssessment>, String> {
     // Loss streak — look at last 5 matches
     let mut stmt = conn
         .prepare("SELECT radiant_win, player_slot FROM matches ORDER BY start_time DESC LIMIT 5")
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("DB error", e))?;
     let recent_results: Vec<bool> = stmt
         .query_map([], |row| {
             let rw: i32 = row.get(0)?;
             let ps: i32 = row.get(1)?;
             Ok((ps < 128 && rw == 1) || (ps >= 128 && rw == 0))
         })
-        .map_err(|e| format!("DB: {}", e))?
+        .map_err(|e| DotaKeeperError::db("DB", e))?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -468,7 +538,7 @@ fn get_tilt_assessment() -> Result<Option<TiltAssessment>, String> {
     }
     let mut stat_stmt = conn
         .prepare("SELECT deaths, kills, assists FROM matches ORDER BY start_time DESC LIMIT 30")
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("DB error", e))?;
     let all_stats: Vec<MatchStats> = stat_stmt
         .query_map([], |row| {
             Ok(MatchStats {
@@ -477,7 +547,7 @@ fn get_tilt_assessment() -> Result<Option<TiltAssessment>, String> {
                 assists: row.get::<_, f64>(2)?,
             })
         })
-        .map_err(|e| format!("DB: {}", e))?
+        .map_err(|e| DotaKeeperError::db("DB", e))?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -548,7 +618,7 @@ fn get_tilt_assessment() -> Result<Option<TiltAssessment>, String> {
              WHERE skipped = 0 AND hidden = 0 \
              ORDER BY checked_at DESC LIMIT 10",
         )
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("DB error", e))?;
     let checkins: Vec<RecentCheckin> = ci_stmt
         .query_map([], |row| {
             Ok(RecentCheckin {
@@ -557,7 +627,7 @@ fn get_tilt_assessment() -> Result<Option<TiltAssessment>, String> {
                 attribution: row.get(2)?,
             })
         })
-        .map_err(|e| format!("DB: {}", e))?
+        .map_err(|e| DotaKeeperError::db("DB", e))?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -637,13 +707,13 @@ fn get_tilt_assessment() -> Result<Option<TiltAssessment>, String> {
             "SELECT energy, calm FROM mood_checkins \
              WHERE skipped = 0 AND hidden = 0 AND checked_at > ?1",
         )
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("DB error", e))?;
     struct AvgRow { energy: Option<i32>, calm: Option<i32> }
     let week_checkins: Vec<AvgRow> = avg_stmt
         .query_map(rusqlite::params![seven_days_ago], |row| {
             Ok(AvgRow { energy: row.get(0)?, calm: row.get(1)? })
         })
-        .map_err(|e| format!("DB: {}", e))?
+        .map_err(|e| DotaKeeperError::db("DB", e))?
         .filter_map(|r| r.ok())
         .collect();
 
@@ -797,7 +867,7 @@ struct CheckinHistoryItem {
 
 /// Return the most recent check-in history entries (including skipped ones).
 #[tauri::command]
-fn get_checkin_history(limit: i32) -> Result<Vec<CheckinHistoryItem>, String> {
+fn get_checkin_history(limit: i32) -> Result<Vec<CheckinHistoryItem>, DotaKeeperError> {
     let settings = Settings::load();
     if !settings.mental_health_tracking_enabled {
         return Ok(vec![]);
@@ -814,7 +884,7 @@ fn get_checkin_history(limit: i32) -> Result<Vec<CheckinHistoryItem>, String> {
              ORDER BY mc.checked_at DESC \
              LIMIT ?1",
         )
-        .map_err(|e| format!("DB error: {}", e))?;
+        .map_err(|e| DotaKeeperError::db("DB error", e))?;
 
     let items: Vec<CheckinHistoryItem> = stmt
         .query_map(rusqlite::params![limit], |row| {
@@ -836,69 +906,19 @@ fn get_checkin_history(limit: i32) -> Result<Vec<CheckinHistoryItem>, String> {
                 match_start_time: row.get(9)?,
             })
         })
-        .map_err(|e| format!("DB error: {}", e))?
+        .map_err(|e| DotaKeeperError::db("DB error", e))?
         .filter_map(|r| r.ok())
         .collect();
 
     Ok(items)
 }
 
-// ── Provider dispatch helpers ─────────────────────────────────────────────────
-
-async fn api_fetch_recent_matches(settings: &Settings, limit: usize) -> Result<Vec<database::Match>, String> {
-    let steam_id = settings.steam_id.as_deref().ok_or("No Steam ID configured")?;
-    if settings.data_provider == "stratz" {
-        let key = settings.stratz_api_key.as_deref()
-            .ok_or("No Stratz API key configured. Add your API key in Settings.")?;
-        stratz::fetch_recent_matches(steam_id, key, limit).await
-    } else {
-        opendota::fetch_recent_matches(steam_id, limit, settings.opendota_api_key.as_deref()).await
-    }
-}
-
-async fn api_fetch_matches_before(settings: &Settings, before_timestamp: i64, limit: usize) -> Result<Vec<database::Match>, String> {
-    let steam_id = settings.steam_id.as_deref().ok_or("No Steam ID configured")?;
-    if settings.data_provider == "stratz" {
-        let key = settings.stratz_api_key.as_deref()
-            .ok_or("No Stratz API key configured. Add your API key in Settings.")?;
-        stratz::fetch_matches_before(steam_id, key, before_timestamp, limit).await
-    } else {
-        opendota::fetch_matches_before(steam_id, before_timestamp, limit, settings.opendota_api_key.as_deref()).await
-    }
-}
-
-/// For OpenDota: submits a parse job and returns the job ID.
-/// For Stratz: no parse needed — returns Ok(None) immediately.
-async fn api_request_parse(settings: &Settings, match_id: i64) -> Result<Option<i64>, String> {
-    if settings.data_provider == "stratz" {
-        Ok(None)
-    } else {
-        opendota::request_match_parse(match_id, settings.opendota_api_key.as_deref()).await
-    }
-}
-
-/// For OpenDota: polls until the parse job completes.
-/// For Stratz: no-op, returns true immediately.
-async fn api_wait_for_parse(settings: &Settings, job_id: i64) -> bool {
-    if settings.data_provider == "stratz" {
-        true
-    } else {
-        opendota::wait_for_parse_job(job_id).await
-    }
-}
-
-async fn api_fetch_match_details(settings: &Settings, match_id: i64) -> Result<opendota::DetailedMatch, String> {
-    if settings.data_provider == "stratz" {
-        let key = settings.stratz_api_key.as_deref()
-            .ok_or("No Stratz API key configured. Add your API key in Settings.")?;
-        stratz::fetch_match_details(match_id, key).await
-    } else {
-        opendota::fetch_match_details(match_id, settings.opendota_api_key.as_deref()).await
-    }
+/// Capabilities of the configured data provider, so the UI can hide features it can't back
+#[tauri::command]
+fn get_provider_capabilities() -> provider::ProviderCapabilities {
+    provider::from_settings(&Settings::load()).capabilities()
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-
 #[derive(Debug, serde::Serialize)]
 struct RefreshResult {
     new_count: usize,
@@ -907,8 +927,10 @@ struct RefreshResult {
 
 /// Refresh matches from the configured data provider
 #[tauri::command]
-async fn refresh_matches() -> Result<RefreshResult, String> {
+async fn refresh_matches() -> Result<RefreshResult, DotaKeeperError> {
     let settings = Settings::load();
+    let steam_id = settings.steam_id.as_deref().ok_or_else(|| DotaKeeperError::InvalidInput("No Steam ID configured".to_string()))?;
+    let provider = provider::from_settings(&settings);
 
     // On a fresh install with no matches, the /recentMatches endpoint may return empty
     // if the player's profile hasn't been indexed on OpenDota yet. Fall back to the
@@ -917,7 +939,7 @@ async fn refresh_matches() -> Result<RefreshResult, String> {
         let conn = get_db_conn()?;
         let count: i64 = conn
             .query_row("SELECT COUNT(*) FROM matches", [], |row| row.get(0))
-            .map_err(|e| format!("Failed to count matches: {}", e))?;
+            .map_err(|e| DotaKeeperError::db("Failed to count matches", e))?;
         count == 0
     };
 
@@ -926,92 +948,118 @@ async fn refresh_matches() -> Result<RefreshResult, String> {
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs() as i64;
-        api_fetch_matches_before(&settings, now, 20).await?
+        provider.fetch_matches_before(steam_id, now, 20).await?
     } else {
-        api_fetch_recent_matches(&settings, 20).await?
+        provider.fetch_recent_matches(steam_id, 20).await?
     };
 
     // Initialize database
     let conn = get_db_conn()?;
+    database::ensure_db_account(&conn, steam_id64_to_id32(steam_id)?)?;
+
+    let new_count = store_new_matches(&conn, matches)?;
+    apply_gsi_recordings(&conn, steam_id64_to_id32(steam_id)?)?;
+
+    Ok(RefreshResult {
+        new_count,
+        matches: get_matches_with_goals(&conn)?,
+    })
+}
 
-    // Insert matches that don't already exist
+/// Insert matches that don't already exist, assigning each one its patch.
+/// Returns the number of newly stored matches.
+fn store_new_matches(conn: &rusqlite::Connection, matches: Vec<database::Match>) -> Result<usize, DotaKeeperError> {
     let mut new_count = 0;
     for m in matches {
-        if !match_exists(&conn, m.match_id)? {
-            insert_match(&conn, &m)?;
+        if !match_exists(conn, m.match_id)? {
+            insert_match(conn, &m)?;
             // Assign patch based on start_time
-            if let Some(patch) = database::get_patch_for_timestamp(&conn, m.start_time) {
-                let _ = update_match_patch(&conn, m.match_id, &patch);
+            if let Some(patch) = database::get_patch_for_timestamp(conn, m.start_time) {
+                let _ = update_match_patch(conn, m.match_id, &patch);
             }
             new_count += 1;
         }
     }
-
-    Ok(RefreshResult {
-        new_count,
-        matches: get_matches_with_goals(&conn)?,
-    })
+    Ok(new_count)
 }
 
 /// Get all stored matches
 #[tauri::command]
-fn get_matches() -> Result<Vec<MatchWithGoals>, String> {
+fn get_matches() -> Result<Vec<MatchWithGoals>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_matches_with_goals(&conn)
 }
 
+/// One page of stored matches, filtered and sorted in the database. Pass the returned
+/// `next_cursor` back in `query.cursor` to fetch the next page.
+#[tauri::command]
+fn query_matches(query: database::MatchQuery) -> Result<database::MatchPage, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::query_matches(&conn, &query)
+}
+
+/// Heroes and patches the match list can be filtered by
+#[tauri::command]
+fn get_match_filter_options() -> Result<database::MatchFilterOptions, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_match_filter_options(&conn)
+}
+
 /// Get medal (rank) history — all matches with rank_tier, ordered oldest first
 #[tauri::command]
-fn get_medal_history() -> Result<Vec<database::MedalEntry>, String> {
+fn get_medal_history() -> Result<Vec<database::MedalEntry>, DotaKeeperError> {
     let conn = get_db_conn()?;
     database::get_medal_history(&conn)
 }
 
 /// Get current and peak medal stats
 #[tauri::command]
-fn get_medal_stats() -> Result<database::MedalStats, String> {
+fn get_medal_stats() -> Result<database::MedalStats, DotaKeeperError> {
     let conn = get_db_conn()?;
     database::get_medal_stats(&conn)
 }
 
 /// Create a new goal
 #[tauri::command]
-fn create_goal(goal: NewGoal) -> Result<Goal, String> {
+fn create_goal(goal: NewGoal) -> Result<Goal, DotaKeeperError> {
     let conn = get_db_conn()?;
+    GOAL_COMPLETION_CHECK_DUE.store(true, Ordering::Relaxed);
     insert_goal(&conn, &goal)
 }
 
 /// Get all goals
 #[tauri::command]
-fn get_goals() -> Result<Vec<Goal>, String> {
+fn get_goals() -> Result<Vec<Goal>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_all_goals(&conn)
 }
 
 /// Update an existing goal
 #[tauri::command]
-fn save_goal(goal: Goal) -> Result<(), String> {
+fn save_goal(goal: database::GoalEdit) -> Result<(), DotaKeeperError> {
     let conn = get_db_conn()?;
-    update_goal(&conn, &goal)
+    let stored = database::get_goal_by_id(&conn, goal.id)?;
+    GOAL_COMPLETION_CHECK_DUE.store(true, Ordering::Relaxed);
+    update_goal(&conn, &goal.apply_to(stored))
 }
 
 /// Delete a goal
 #[tauri::command]
-fn remove_goal(goal_id: i64) -> Result<(), String> {
+fn remove_goal(goal_id: i64) -> Result<(), DotaKeeperError> {
     let conn = get_db_conn()?;
     delete_goal(&conn, goal_id)
 }
 
 /// Get all cached patches (sorted oldest first)
 #[tauri::command]
-fn get_patches() -> Result<Vec<PatchInfo>, String> {
+fn get_patches() -> Result<Vec<PatchInfo>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_all_patches(&conn)
 }
 
 /// Fetch patch list from OpenDota, cache in DB, then back-fill patch on existing matches.
 #[tauri::command]
-async fn sync_patches() -> Result<usize, String> {
+async fn sync_patches() -> Result<usize, DotaKeeperError> {
     let settings = Settings::load();
     let api_key = settings.opendota_api_key.as_deref();
     let patches = opendota::fetch_patches(api_key).await?;
@@ -1023,43 +1071,41 @@ async fn sync_patches() -> Result<usize, String> {
 
 /// Evaluate goals for a specific match
 #[tauri::command]
-fn evaluate_goals_for_match(match_id: i64) -> Result<Vec<GoalEvaluation>, String> {
+fn evaluate_goals_for_match(match_id: i64) -> Result<Vec<GoalEvaluation>, DotaKeeperError> {
     let conn = get_db_conn()?;
 
-    // Get the match
-    let matches = get_all_matches(&conn)?;
-    let match_data = matches
-        .iter()
-        .find(|m| m.match_id == match_id)
-        .ok_or_else(|| "Match not found".to_string())?;
+    let match_data = database::get_match_by_id(&conn, match_id)?
+        .ok_or_else(|| DotaKeeperError::NotFound("Match not found".to_string()))?;
 
-    // Evaluate goals for this match
-    evaluate_match_goals(&conn, match_data)
+    // Evaluate goals for this match (cached in goal_evaluations)
+    evaluate_match_goals(&conn, &match_data)
 }
 
 /// Get or generate weekly hero goal suggestion
 #[tauri::command]
-fn get_hero_goal_suggestion() -> Result<Option<HeroGoalSuggestion>, String> {
+fn get_hero_goal_suggestion() -> Result<Option<HeroGoalSuggestion>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_or_generate_hero_suggestion(&conn)
 }
 
 /// Force regenerate hero goal suggestion (ignores cache)
 #[tauri::command]
-fn refresh_hero_goal_suggestion() -> Result<Option<HeroGoalSuggestion>, String> {
+fn refresh_hero_goal_suggestion() -> Result<Option<HeroGoalSuggestion>, DotaKeeperError> {
     let conn = get_db_conn()?;
     regenerate_hero_suggestion(&conn)
 }
 
 /// Parse a match and extract goal progress data
 #[tauri::command]
-async fn parse_match(app: tauri::AppHandle, match_id: i64, steam_id: String) -> Result<(), String> {
+async fn parse_match(app: tauri::AppHandle, match_id: i64, steam_id: String) -> Result<(), DotaKeeperError> {
     trace_log(&format!("parse_match START match_id={} steam_id={}", match_id, steam_id));
 
     // Update match state to parsing — lock dropped before any await.
+    // A manual request also gives the background parser a fresh set of retries.
     {
         let conn = get_db_conn()?;
         update_match_state(&conn, match_id, MatchState::Parsing)?;
+        database::reset_parse_job(&conn, match_id, chrono::Utc::now().timestamp())?;
     }
     let _ = app.emit(
         "match-state-changed",
@@ -1070,10 +1116,11 @@ async fn parse_match(app: tauri::AppHandle, match_id: i64, steam_id: String) ->
     );
 
     let settings = Settings::load();
+    let provider = provider::from_settings(&settings);
 
     // Request the provider to parse the match (no-op for Stratz)
     trace_log(&format!("parse_match calling request_match_parse match_id={}", match_id));
-    let job_id = match api_request_parse(&settings, match_id).await {
+    let job_id = match provider.request_parse(match_id).await {
         Ok(id) => {
             trace_log(&format!("parse_match request_match_parse OK job_id={:?}", id));
             id
@@ -1091,23 +1138,23 @@ async fn parse_match(app: tauri::AppHandle, match_id: i64, steam_id: String) ->
                     "state": "Failed"
                 }),
             );
-            return Err(format!("Failed to request parse: {}", e));
+            return Err(e);
         }
     };
 
     // Wait for the parse job to finish (OpenDota only; Stratz skips this).
     if let Some(id) = job_id {
         trace_log(&format!("parse_match waiting for job_id={}", id));
-        let done = api_wait_for_parse(&settings, id).await;
+        let done = provider.wait_for_parse(id).await;
         trace_log(&format!("parse_match wait_for_parse_job done={}", done));
-    } else if settings.data_provider != "stratz" {
+    } else if provider.capabilities().needs_parse_request {
         trace_log("parse_match no job_id returned — sleeping 5s");
         tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
     }
 
     // Fetch detailed match data
     trace_log(&format!("parse_match calling fetch_match_details match_id={}", match_id));
-    let detailed_match = match api_fetch_match_details(&settings, match_id).await {
+    let detailed_match = match provider.fetch_match_details(match_id).await {
         Ok(m) => {
             trace_log(&format!("parse_match fetch_match_details OK players={}", m.players.len()));
             m
@@ -1125,56 +1172,75 @@ async fn parse_match(app: tauri::AppHandle, match_id: i64, steam_id: String) ->
                     "state": "Failed"
                 }),
             );
-            return Err(format!("Failed to fetch match details: {}", e));
+            return Err(e);
         }
     };
 
     // No more await points from here — acquire the connection once for all remaining DB writes.
     let account_id = steam_id64_to_id32(&steam_id)?;
+    let conn = get_db_conn()?;
+
+    match store_match_details(&conn, match_id, &detailed_match, account_id, provider.id()) {
+        Ok(true) => {}
+        Ok(false) => {
+            // Both providers: if per-minute CS data is absent the match isn't ready yet — mark
+            // Failed so it can be retried.  (Stratz always provides lastHitsPerMinute; if it's
+            // absent the match hasn't been processed yet.)
+            update_match_state(&conn, match_id, MatchState::Failed)?;
+            let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Failed" }));
+            trace_log(&format!("parse_match FAILED (no per-minute data) match_id={}", match_id));
+            return Err(DotaKeeperError::NotParsedYet(provider.not_ready_message().to_string()));
+        }
+        Err(e) => {
+            update_match_state(&conn, match_id, MatchState::Failed)?;
+            let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Failed" }));
+            trace_log(&format!("parse_match FAILED match_id={}: {}", match_id, e));
+            return Err(e);
+        }
+    }
+
+    let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Parsed" }));
+    trace_log(&format!("parse_match SUCCESS match_id={}", match_id));
+    Ok(())
+}
+
+/// Store everything we keep from a provider's match details and mark the match Parsed.
+/// Returns `Ok(false)` without touching the match when the provider has no per-minute
+/// CS data for the player yet, so the caller can mark it Failed and retry later.
+fn store_match_details(
+    conn: &rusqlite::Connection,
+    match_id: i64,
+    detailed_match: &opendota::DetailedMatch,
+    account_id: u32,
+    parsed_by: &str,
+) -> Result<bool, DotaKeeperError> {
+    database::ensure_db_account(conn, account_id)?;
 
     // Find the player's data
     let player_data = detailed_match
         .players
         .iter()
         .find(|p| p.account_id == Some(account_id))
-        .ok_or_else(|| "Player not found in match".to_string())?;
-
-    let conn = get_db_conn()?;
-
-    let has_cs_data = matches!(
-        (&player_data.lh_t, &player_data.dn_t),
-        (Some(lh), Some(dn)) if !lh.is_empty() && !dn.is_empty()
-    );
+        .ok_or_else(|| DotaKeeperError::NotFound("Player not found in match".to_string()))?;
 
-    // Both providers: if per-minute CS data is absent the match isn't ready yet — mark Failed so
-    // it can be retried.  (Stratz always provides lastHitsPerMinute; if it's absent the match
-    // hasn't been processed yet.)
-    if !has_cs_data {
-        update_match_state(&conn, match_id, MatchState::Failed)?;
-        let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Failed" }));
-        trace_log(&format!("parse_match FAILED (no per-minute data) match_id={}", match_id));
-        let msg = if settings.data_provider == "stratz" {
-            "Stratz hasn't processed this match yet. Try again in a few minutes."
-        } else {
-            "OpenDota has not finished parsing this match yet. Try again in a few minutes."
-        };
-        return Err(msg.to_string());
-    }
+    let (lh_t, dn_t) = match (&player_data.lh_t, &player_data.dn_t) {
+        (Some(lh), Some(dn)) if !lh.is_empty() && !dn.is_empty() => (lh, dn),
+        _ => return Ok(false),
+    };
+    insert_match_cs_data(conn, match_id, lh_t, dn_t)?;
 
-    // Store CS data if available
-    if has_cs_data {
-        if let (Some(lh_t), Some(dn_t)) = (&player_data.lh_t, &player_data.dn_t) {
-            insert_match_cs_data(&conn, match_id, lh_t, dn_t)?;
+    // Store lane role. GSI recordings don't know it, so the role the match already has is kept.
+    let role = match player_data.lane_role {
+        Some(role) => {
+            let _ = update_match_role(conn, match_id, role);
+            role
         }
-    }
-
-    // Store lane role
-    let role = player_data.lane_role.unwrap_or(0);
-    let _ = update_match_role(&conn, match_id, role);
+        None => database::get_match_by_id(conn, match_id)?.map_or(0, |m| m.role),
+    };
 
     // Backfill end-of-game stats that may have been zero when first inserted
     let _ = update_match_stats(
-        &conn, match_id,
+        conn, match_id,
         player_data.xp_per_min,
         player_data.gold_per_min,
         player_data.last_hits,
@@ -1184,59 +1250,215 @@ async fn parse_match(app: tauri::AppHandle, match_id: i64, steam_id: String) ->
         player_data.hero_healing,
     );
 
-    // Store per-minute networth for all players (used by PartnerNetworth goals)
+    // Store every player's line, timelines and purchases (used for lane/ally/enemy analysis),
+    // including per-minute networth (used by PartnerNetworth goals)
     for p in &detailed_match.players {
         if let Some(nw_t) = &p.gold_t {
-            let _ = insert_player_networth(&conn, match_id, p.player_slot, nw_t);
+            let _ = insert_player_networth(conn, match_id, p.player_slot, nw_t);
+        }
+        let _ = database::upsert_match_player(conn, &database::MatchPlayer {
+            match_id,
+            player_slot: p.player_slot,
+            account_id: p.account_id,
+            hero_id: p.hero_id.unwrap_or(0),
+            lane_role: p.lane_role.unwrap_or(if p.player_slot == player_data.player_slot { role } else { 0 }),
+            kills: p.kills,
+            deaths: p.deaths,
+            assists: p.assists,
+            last_hits: p.last_hits,
+            denies: p.denies,
+            xp_per_min: p.xp_per_min,
+            gold_per_min: p.gold_per_min,
+            hero_damage: p.hero_damage,
+            tower_damage: p.tower_damage,
+            hero_healing: p.hero_healing,
+        });
+        let _ = database::insert_match_player_timeline(
+            conn,
+            match_id,
+            p.player_slot,
+            p.lh_t.as_deref().unwrap_or_default(),
+            p.dn_t.as_deref().unwrap_or_default(),
+            p.xp_t.as_deref().unwrap_or_default(),
+        );
+        if let Some(purchase_log) = &p.purchase_log {
+            let purchases: Vec<(i32, i32)> = purchase_log
+                .iter()
+                .filter_map(|purchase| Some((items::get_item_id(&purchase.key)?, purchase.time)))
+                .collect();
+            let _ = database::insert_match_player_items(conn, match_id, p.player_slot, &purchases);
         }
     }
     // Store per-minute XP for the player (used for XP/Level charts)
     if let Some(xp_t) = &player_data.xp_t {
-        let _ = insert_match_xp_data(&conn, match_id, xp_t);
+        let _ = insert_match_xp_data(conn, match_id, xp_t);
+    }
+    // Store kill/death timestamps (used by Kills/Deaths goals). Either may be unknown, in
+    // which case goals on that metric fall back to the end-of-game count.
+    if player_data.kill_times.is_some() || player_data.death_times.is_some() {
+        let kill_times = player_data.kill_times.as_deref().unwrap_or_default();
+        let death_times = player_data.death_times.as_deref().unwrap_or_default();
+        let _ = insert_match_events(conn, match_id, kill_times, death_times);
     }
     // Identify and store lane partner slot
     let partner =
         opendota::find_lane_partner(&detailed_match.players, player_data.player_slot, role);
-    let _ = update_match_partner_slot(&conn, match_id, partner.map(|p| p.player_slot));
+    let _ = update_match_partner_slot(conn, match_id, partner.map(|p| p.player_slot));
 
     // Store item purchase timings if available
     if let Some(purchase_log) = &player_data.purchase_log {
-        let _ = clear_item_timings_for_match(&conn, match_id);
+        let _ = clear_item_timings_for_match(conn, match_id);
         for purchase in purchase_log {
             if let Some(item_id) = items::get_item_id(&purchase.key) {
                 let timing = NewItemTiming { match_id, item_id, timing_seconds: purchase.time };
-                let _ = insert_item_timing(&conn, &timing);
+                let _ = insert_item_timing(conn, &timing);
             }
         }
     }
 
-    update_match_state(&conn, match_id, MatchState::Parsed)?;
-    let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Parsed" }));
-    trace_log(&format!("parse_match SUCCESS match_id={}", match_id));
-    Ok(())
+    update_match_parsed_by(conn, match_id, parsed_by)?;
+    update_match_state(conn, match_id, MatchState::Parsed)?;
+    database::remove_from_parse_queue(conn, match_id)?;
+    // Fill the goal evaluation cache now rather than on the next match list load
+    let _ = database::cache_match_goal_evaluations(conn, match_id);
+    GOAL_COMPLETION_CHECK_DUE.store(true, Ordering::Relaxed);
+    Ok(true)
+}
+
+/// How long a GSI recording waits for its match to show up before it's dropped
+/// (practice lobbies and bot games never do).
+const GSI_RECORDING_MAX_AGE_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Fill in the per-minute data of stored, unparsed matches from their GSI recordings and mark
+/// them parsed, so the user's own games don't wait on the provider's parse queue. Recordings
+/// are dropped once used, once a provider has parsed the match, or when they expire.
+/// Returns the matches that were parsed.
+pub(crate) fn apply_gsi_recordings(conn: &rusqlite::Connection, account_id: u32) -> Result<Vec<i64>, DotaKeeperError> {
+    let now = chrono::Utc::now().timestamp();
+    let mut parsed = Vec::new();
+    for recording in database::get_gsi_recordings(conn)? {
+        if recording.account_id != account_id {
+            continue;
+        }
+        match database::get_match_slot_and_state(conn, recording.match_id)? {
+            None if now - recording.recorded_at < GSI_RECORDING_MAX_AGE_SECS => continue,
+            None | Some((_, MatchState::Parsed)) => {}
+            Some((player_slot, _)) => {
+                let game: gsi::LiveGame = serde_json::from_str(&recording.data)
+                    .map_err(|e| DotaKeeperError::Database(format!("Failed to read GSI recording: {}", e)))?;
+                // Games joined mid-way have no usable timeline; the provider parses those
+                if let Some(details) = game.to_detailed_match(player_slot) {
+                    if store_match_details(conn, recording.match_id, &details, account_id, "gsi")? {
+                        parsed.push(recording.match_id);
+                    }
+                }
+            }
+        }
+        database::delete_gsi_recording(conn, recording.match_id)?;
+    }
+    Ok(parsed)
+}
+
+/// Get all ten players stored for a parsed match
+#[tauri::command]
+fn get_match_players(match_id: i64) -> Result<Vec<database::MatchPlayer>, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_match_players(&conn, match_id)
+}
+
+/// Get one player's per-minute last hits, denies and XP for a parsed match
+#[tauri::command]
+fn get_match_player_timeline(match_id: i64, player_slot: i32) -> Result<Vec<database::MatchPlayerMinute>, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_match_player_timeline(&conn, match_id, player_slot)
+}
+
+/// Get every player's item purchases for a parsed match
+#[tauri::command]
+fn get_match_player_items(match_id: i64) -> Result<Vec<database::MatchPlayerItem>, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_match_player_items(&conn, match_id)
+}
+
+/// Get CS, denies and networth differentials against the direct lane opponent at 5 and 10 minutes
+#[tauri::command]
+fn get_lane_outcome(match_id: i64) -> Result<Option<database::LaneOutcome>, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_lane_outcome(&conn, match_id)
 }
 
 /// Get goals with daily progress for the last N days
 #[tauri::command]
-fn get_goals_calendar(days: i32) -> Result<Vec<GoalWithDailyProgress>, String> {
+fn get_goals_calendar(days: i32) -> Result<Vec<GoalWithDailyProgress>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_goals_with_daily_progress(&conn, days)
 }
 
 /// Get a specific goal by ID
 #[tauri::command]
-fn get_goal(goal_id: i64) -> Result<Goal, String> {
+fn get_goal(goal_id: i64) -> Result<Goal, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_goal_by_id(&conn, goal_id)
 }
 
 /// Get match data for a specific goal (for histogram visualization)
 #[tauri::command]
-fn get_goal_histogram_data(goal_id: i64) -> Result<Vec<MatchDataPoint>, String> {
+fn get_goal_histogram_data(goal_id: i64) -> Result<Vec<MatchDataPoint>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_goal_match_data(&conn, goal_id)
 }
 
+/// Whether a goal is on track over the last N games or days (default: last 20 games),
+/// judged by its frequency type, with confidence intervals and the trend
+#[tauri::command]
+fn get_goal_status(goal_id: i64, window: Option<database::GoalWindow>) -> Result<database::GoalStatus, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_goal_status(&conn, goal_id, window.unwrap_or_default())
+}
+
+/// Pause, resume, archive or mark a goal achieved. Retiring a goal (achieved / archived)
+/// keeps its stats over the last `goal_completion_games` games.
+#[tauri::command]
+fn set_goal_state(goal_id: i64, state: database::GoalState) -> Result<Goal, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    GOAL_COMPLETION_CHECK_DUE.store(true, Ordering::Relaxed);
+    let window = database::GoalWindow::LastGames(Settings::load().goal_completion_games);
+    database::set_goal_state(&conn, goal_id, state, window)
+}
+
+/// Achieved and archived goals with their final stats, most recently retired first.
+#[tauri::command]
+fn get_goal_history() -> Result<Vec<database::RetiredGoal>, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_retired_goals(&conn)
+}
+
+/// Save how many games a goal must be on track over before it's marked achieved.
+#[tauri::command]
+fn save_goal_completion_games(value: u32) -> Result<Settings, DotaKeeperError> {
+    let mut settings = Settings::load();
+    settings.goal_completion_games = value.max(1);
+    settings.save()?;
+    GOAL_COMPLETION_CHECK_DUE.store(true, Ordering::Relaxed);
+    Ok(settings)
+}
+
+/// Mark active goals whose frequency criterion is met as achieved, and tell the UI
+/// (`goals-achieved`, with the promoted goals) so it can celebrate.
+fn check_goal_completion(app: &tauri::AppHandle) {
+    let games = Settings::load().goal_completion_games;
+    let promoted = match get_db_conn().and_then(|conn| database::promote_achieved_goals(&conn, games)) {
+        Ok(promoted) => promoted,
+        Err(e) => {
+            eprintln!("Goal completion check failed: {}", e);
+            return;
+        }
+    };
+    if !promoted.is_empty() {
+        let _ = app.emit("goals-achieved", serde_json::json!({ "goals": promoted }));
+    }
+}
+
 /// Returns true when this binary was compiled with the `beta` feature flag.
 #[tauri::command]
 fn is_beta_build() -> bool {
@@ -1245,7 +1467,7 @@ fn is_beta_build() -> bool {
 
 /// Get the path to the database folder
 #[tauri::command]
-fn get_database_folder_path() -> Result<String, String> {
+fn get_database_folder_path() -> Result<String, DotaKeeperError> {
     if let Some(dir) = get_db_dir() {
         return Ok(dir.to_string_lossy().to_string());
     }
@@ -1254,43 +1476,43 @@ fn get_database_folder_path() -> Result<String, String> {
             path.push("DotaKeeper");
             path.to_string_lossy().to_string()
         })
-        .ok_or_else(|| "Could not determine database directory".to_string())
+        .ok_or_else(|| DotaKeeperError::Other("Could not determine database directory".to_string()))
 }
 
 /// Open the database folder in the system file explorer
 #[tauri::command]
-async fn open_database_folder() -> Result<(), String> {
+async fn open_database_folder() -> Result<(), DotaKeeperError> {
     let folder_path = get_database_folder_path()?;
 
     // Ensure the folder exists
     std::fs::create_dir_all(&folder_path)
-        .map_err(|e| format!("Failed to create database directory: {}", e))?;
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to create database directory: {}", e)))?;
 
     // Open the folder using tauri opener plugin
     tauri_plugin_opener::open_path(folder_path, None::<&str>)
-        .map_err(|e| format!("Failed to open folder: {}", e))?;
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to open folder: {}", e)))?;
 
     Ok(())
 }
 
 /// Get the path to the logs folder
 #[tauri::command]
-fn get_logs_folder_path() -> Result<String, String> {
+fn get_logs_folder_path() -> Result<String, DotaKeeperError> {
     dirs::data_local_dir()
         .map(|d| d.join("DotaKeeper").join("logs").to_string_lossy().to_string())
-        .ok_or_else(|| "Logs folder is not available on this platform".to_string())
+        .ok_or_else(|| DotaKeeperError::Other("Logs folder is not available on this platform".to_string()))
 }
 
 /// Open the logs folder in the system file explorer
 #[tauri::command]
-async fn open_logs_folder() -> Result<(), String> {
+async fn open_logs_folder() -> Result<(), DotaKeeperError> {
     let folder_path = get_logs_folder_path()?;
 
     std::fs::create_dir_all(&folder_path)
-        .map_err(|e| format!("Failed to create logs directory: {}", e))?;
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to create logs directory: {}", e)))?;
 
     tauri_plugin_opener::open_path(folder_path, None::<&str>)
-        .map_err(|e| format!("Failed to open folder: {}", e))?;
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to open folder: {}", e)))?;
 
     Ok(())
 }
@@ -1302,7 +1524,7 @@ fn get_last_hits_analysis_data(
     window_size: usize,
     hero_id: Option<i32>,
     game_mode: Option<i32>,
-) -> Result<LastHitsAnalysis, String> {
+) -> Result<LastHitsAnalysis, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_last_hits_analysis(&conn, time_minutes, window_size, hero_id, game_mode)
 }
@@ -1312,10 +1534,19 @@ fn get_last_hits_analysis_data(
 const BENCHMARK_CSV_URL: &str =
     "https://raw.githubusercontent.com/stringhandler/dota-keeper/main/meta/benchmarks/hero_benchmarks.csv";
 
+async fn fetch_benchmark_csv() -> Result<String, DotaKeeperError> {
+    http::send(ApiHost::GitHub, http::client().get(BENCHMARK_CSV_URL))
+        .await
+        .map_err(|e| DotaKeeperError::Api(format!("Failed to fetch benchmarks: {}", e)))?
+        .text()
+        .await
+        .map_err(|e| DotaKeeperError::Api(format!("Failed to read benchmark response: {}", e)))
+}
+
 /// Fetch the hero benchmark CSV from GitHub, parse it, and upsert into the database.
 /// Called on startup (non-blocking) and can also be invoked from the frontend.
 /// In debug builds, reads from the local repo file instead of fetching over HTTP.
-async fn fetch_and_store_benchmarks() -> Result<String, String> {
+async fn fetch_and_store_benchmarks() -> Result<String, DotaKeeperError> {
     let body = if cfg!(debug_assertions) {
         // In dev, prefer the local repo file so we don't hit the network on every run.
         // If it doesn't exist (e.g. running on a mobile device), fall through to the network.
@@ -1327,42 +1558,18 @@ async fn fetch_and_store_benchmarks() -> Result<String, String> {
         if local_path.exists() {
             tracing::info!(target: "dota_keeper", "Loading benchmarks from local file: {}", local_path.display());
             std::fs::read_to_string(&local_path)
-                .map_err(|e| format!("Failed to read local benchmark CSV at {}: {}", local_path.display(), e))?
+                .map_err(|e| DotaKeeperError::Other(format!("Failed to read local benchmark CSV at {}: {}", local_path.display(), e)))?
         } else {
             tracing::info!(target: "dota_keeper", "Local benchmark CSV not found, fetching from GitHub");
-            let client = reqwest::Client::new();
-            let resp = client
-                .get(BENCHMARK_CSV_URL)
-                .send()
-                .await
-                .map_err(|e| format!("Failed to fetch benchmarks: {}", e))?;
-            if !resp.status().is_success() {
-                return Err(format!("Benchmark fetch returned HTTP {}", resp.status()));
-            }
-            resp.text()
-                .await
-                .map_err(|e| format!("Failed to read benchmark response: {}", e))?
+            fetch_benchmark_csv().await?
         }
     } else {
-        let client = reqwest::Client::new();
-        let resp = client
-            .get(BENCHMARK_CSV_URL)
-            .send()
-            .await
-            .map_err(|e| format!("Failed to fetch benchmarks: {}", e))?;
-
-        if !resp.status().is_success() {
-            return Err(format!("Benchmark fetch returned HTTP {}", resp.status()));
-        }
-
-        resp.text()
-            .await
-            .map_err(|e| format!("Failed to read benchmark response: {}", e))?
+        fetch_benchmark_csv().await?
     };
 
     let rows = parse_benchmark_csv(&body)?;
     if rows.is_empty() {
-        return Err("Benchmark CSV was empty or contained no valid rows".to_string());
+        return Err(DotaKeeperError::Api("Benchmark CSV was empty or contained no valid rows".to_string()));
     }
 
     let data_date = rows.first().map(|r| r.data_date.clone()).unwrap_or_default();
@@ -1511,17 +1718,17 @@ fn hero_name_to_id(name: &str) -> Option<i32> {
     Some(id)
 }
 
-fn parse_benchmark_csv(body: &str) -> Result<Vec<HeroBenchmarkRow>, String> {
+fn parse_benchmark_csv(body: &str) -> Result<Vec<HeroBenchmarkRow>, DotaKeeperError> {
     let mut rows = Vec::new();
     let mut lines = body.lines();
 
     // Skip header
-    let header = lines.next().ok_or("Empty CSV")?;
+    let header = lines.next().ok_or_else(|| DotaKeeperError::Api("Empty CSV".to_string()))?;
     // Validate header has expected columns
     // data_date,hero_name,mode,bracket,stat_name,mean,std_dev,sample_size,ideal_match_id_avg,ideal_match_id_top
     let cols: Vec<&str> = header.split(',').collect();
     if cols.len() < 10 {
-        return Err(format!("Expected 10 CSV columns, got {}", cols.len()));
+        return Err(DotaKeeperError::Api(format!("Expected 10 CSV columns, got {}", cols.len())));
     }
 
     for (line_num, line) in lines.enumerate() {
@@ -1544,13 +1751,13 @@ fn parse_benchmark_csv(body: &str) -> Result<Vec<HeroBenchmarkRow>, String> {
             }
         };
         let mean: f64 = fields[5].trim().parse().map_err(|_| {
-            format!("Invalid mean on line {}: '{}'", line_num + 2, fields[5])
+            DotaKeeperError::InvalidInput(format!("Invalid mean on line {}: '{}'", line_num + 2, fields[5]))
         })?;
         let std_dev: f64 = fields[6].trim().parse().map_err(|_| {
-            format!("Invalid std_dev on line {}: '{}'", line_num + 2, fields[6])
+            DotaKeeperError::InvalidInput(format!("Invalid std_dev on line {}: '{}'", line_num + 2, fields[6]))
         })?;
         let sample_size: i32 = fields[7].trim().parse().map_err(|_| {
-            format!("Invalid sample_size on line {}: '{}'", line_num + 2, fields[7])
+            DotaKeeperError::InvalidInput(format!("Invalid sample_size on line {}: '{}'", line_num + 2, fields[7]))
         })?;
         let ideal_match_id_avg: Option<i64> = fields[8].trim().parse().ok();
         let ideal_match_id_top: Option<i64> = fields[9].trim().parse().ok();
@@ -1581,7 +1788,7 @@ fn get_hero_benchmark(
     user_value: f64,
     user_hero_id: Option<i32>,
     user_game_mode: Option<i32>,
-) -> Result<BenchmarkResult, String> {
+) -> Result<BenchmarkResult, DotaKeeperError> {
     let conn = get_db_conn()?;
     let mut result = get_benchmark_comparison(&conn, hero_id, &mode, &stat_name, user_value)?;
 
@@ -1601,20 +1808,20 @@ fn get_hero_benchmark(
 
 /// Get the benchmark data date metadata.
 #[tauri::command]
-fn get_benchmark_data_date() -> Result<String, String> {
+fn get_benchmark_data_date() -> Result<String, DotaKeeperError> {
     let conn = get_db_conn()?;
     Ok(get_benchmark_metadata(&conn, "data_date")?.unwrap_or_default())
 }
 
 /// Manually trigger a benchmark refresh from the frontend.
 #[tauri::command]
-async fn refresh_benchmarks() -> Result<String, String> {
+async fn refresh_benchmarks() -> Result<String, DotaKeeperError> {
     fetch_and_store_benchmarks().await
 }
 
 /// Check if benchmark data is available.
 #[tauri::command]
-fn has_benchmarks() -> Result<bool, String> {
+fn has_benchmarks() -> Result<bool, DotaKeeperError> {
     let conn = get_db_conn()?;
     has_benchmark_data(&conn)
 }
@@ -1622,7 +1829,7 @@ fn has_benchmarks() -> Result<bool, String> {
 /// Get user's personal LH history at a specific minute across all parsed matches.
 /// Returns list of {match_id, hero_id, game_mode, last_hits} objects ordered newest first.
 #[tauri::command]
-fn get_user_lh_history(minute: i32) -> Result<Vec<serde_json::Value>, String> {
+fn get_user_lh_history(minute: i32) -> Result<Vec<serde_json::Value>, DotaKeeperError> {
     let conn = get_db_conn()?;
     let rows = get_user_lh_at_minute_history(&conn, minute)?;
     Ok(rows
@@ -1638,26 +1845,48 @@ fn get_user_lh_history(minute: i32) -> Result<Vec<serde_json::Value>, String> {
         .collect())
 }
 
-/// Starts a background backfill of 100 historical matches, returning immediately.
+/// Matches requested per history page (the most OpenDota returns per call).
+const BACKFILL_PAGE_SIZE: usize = 100;
+
+/// Starts (or resumes) importing the account's full match history in the background,
+/// returning immediately. The import pages back until the account's first match, or until
+/// `stop_before` (Unix timestamp) / the release of `stop_at_patch` when given; the cap
+/// of an interrupted import is kept unless a new one is passed. Imported matches are
+/// parsed by the background parser.
 #[tauri::command]
 async fn backfill_historical_matches(
     app: tauri::AppHandle,
     steam_id: String,
-) -> Result<String, String> {
+    stop_before: Option<i64>,
+    stop_at_patch: Option<String>,
+) -> Result<String, DotaKeeperError> {
     if BACKFILL_ACTIVE.load(Ordering::Relaxed) {
-        return Err("A backfill is already in progress.".to_string());
+        return Err(DotaKeeperError::InvalidInput("A backfill is already in progress.".to_string()));
     }
 
-    // Read the oldest timestamp — lock dropped before first await.
-    let before_timestamp = {
+    let resumed = {
         let conn = get_db_conn()?;
-        let oldest = get_oldest_match_timestamp(&conn)?;
-        oldest.unwrap_or_else(|| {
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as i64
-        })
+        let patch_start = match &stop_at_patch {
+            Some(patch) => Some(
+                database::get_patch_release(&conn, patch)?
+                    .ok_or_else(|| DotaKeeperError::InvalidInput(format!("Unknown patch {}. Sync patches and try again.", patch)))?,
+            ),
+            None => None,
+        };
+        let cap = patch_start.or(stop_before);
+        let now = chrono::Utc::now().timestamp();
+        let (state, resumed) = match database::get_backfill_state(&conn)? {
+            Some(mut state) if !state.finished => {
+                if cap.is_some() {
+                    state.stop_before = cap;
+                    state.stop_at_patch = stop_at_patch;
+                }
+                (state, true)
+            }
+            _ => (database::BackfillState::new(cap, stop_at_patch, now), false),
+        };
+        database::save_backfill_state(&conn, &state)?;
+        resumed
     };
 
     let settings = {
@@ -1665,25 +1894,42 @@ async fn backfill_historical_matches(
         s.steam_id = Some(steam_id.clone());
         s
     };
+    start_backfill_task(app, steam_id, settings);
+
+    Ok(if resumed { "Backfill resumed." } else { "Backfill started." }.to_string())
+}
 
+fn start_backfill_task(app: tauri::AppHandle, steam_id: String, settings: Settings) {
     BACKFILL_ACTIVE.store(true, Ordering::Relaxed);
     BACKFILL_PENDING.store(0, Ordering::Relaxed);
     let _ = app.emit("backfill-progress", serde_json::json!({ "active": true, "pending": 0 }));
 
     tauri::async_runtime::spawn(async move {
-        run_backfill_task(app, steam_id, before_timestamp, settings).await;
+        run_backfill_task(app, steam_id, settings).await;
     });
+}
 
-    Ok("Backfill started.".to_string())
+/// Progress payload of `backfill-progress`. `pending` counts imported matches still
+/// waiting for the background parser.
+fn backfill_progress_json(active: bool, pending: usize, state: &database::BackfillState) -> serde_json::Value {
+    serde_json::json!({
+        "active": active,
+        "pending": pending,
+        "matches_seen": state.matches_seen,
+        "matches_added": state.matches_added,
+        "oldest_start_time": state.oldest_start_time,
+        "stop_before": state.stop_before,
+        "progress": state.progress(),
+    })
 }
 
-/// Inner task that does the actual backfill work. Runs in a spawned background task.
-async fn run_backfill_task(
-    app: tauri::AppHandle,
-    steam_id: String,
-    before_timestamp: i64,
-    settings: Settings,
-) {
+/// Inner task that does the actual backfill work: pages through the match history from
+/// the saved cursor, storing new matches and saving the cursor after every page. Runs in
+/// a spawned background task.
+async fn run_backfill_task(app: tauri::AppHandle, steam_id: String, settings: Settings) {
+    // Rate-limit retries per page before giving up (the cursor is kept for next time)
+    const MAX_UNAVAILABLE_RETRIES: u32 = 5;
+
     macro_rules! finish {
         ($msg:expr) => {{
             BACKFILL_ACTIVE.store(false, Ordering::Relaxed);
@@ -1696,169 +1942,95 @@ async fn run_backfill_task(
         }};
     }
 
-    // Fetch 25 matches per backfill run — keeps request volume low.
-    // Users can trigger another backfill to continue pulling more history.
-    let matches = match api_fetch_matches_before(&settings, before_timestamp, 25).await {
-        Ok(m) => m,
-        Err(e) => finish!(format!("Backfill failed: {}", e)),
-    };
-
-    if matches.is_empty() {
-        finish!("No new matches found to backfill.");
-    }
-
-    // Insert matches that don't already exist — lock dropped before the parse loop.
-    let mut new_count = 0;
-    {
-        let conn = match get_db_conn() {
-            Ok(c) => c,
-            Err(e) => finish!(format!("Backfill failed: {}", e)),
-        };
-        for m in &matches {
-            if matches!(match_exists(&conn, m.match_id), Ok(false)) {
-                if insert_match(&conn, m).is_ok() {
-                    if let Some(patch) = database::get_patch_for_timestamp(&conn, m.start_time) {
-                        let _ = update_match_patch(&conn, m.match_id, &patch);
-                    }
-                    new_count += 1;
-                }
-            }
-        }
-    }
-
-    // Convert Steam ID for parsing
     let account_id = match steam_id64_to_id32(&steam_id) {
         Ok(id) => id,
         Err(e) => finish!(format!("Backfill failed: {}", e)),
     };
+    let provider = provider::from_settings(&settings);
+    let mut unavailable_retries = 0;
+    let mut total_requested = false;
 
-    BACKFILL_PENDING.store(matches.len(), Ordering::Relaxed);
-    let _ = app.emit("backfill-progress", serde_json::json!({ "active": true, "pending": matches.len() }));
-
-    let mut parsed_count = 0;
-
-    // Parse matches (with a small delay between each to avoid rate limiting).
-    // The DB lock is acquired in short scopes so it is never held across an await.
-    for m in &matches {
-        // Request parse (no-op for Stratz)
-        let job_id = match api_request_parse(&settings, m.match_id).await {
-            Ok(id) => id,
-            Err(e) => {
-                eprintln!("Failed to request parse for match {}: {}", m.match_id, e);
-                BACKFILL_PENDING.fetch_sub(1, Ordering::Relaxed);
-                let _ = app.emit("backfill-progress", serde_json::json!({ "active": true, "pending": BACKFILL_PENDING.load(Ordering::Relaxed) }));
-                continue;
+    loop {
+        let mut state = {
+            let conn = match database::get_account_db_conn(account_id) {
+                Ok(c) => c,
+                Err(_) => finish!("Backfill paused: the active account changed. It will resume from where it stopped."),
+            };
+            match database::get_backfill_state(&conn) {
+                Ok(Some(state)) => state,
+                Ok(None) => finish!("Backfill stopped: its progress was reset."),
+                Err(e) => finish!(format!("Backfill failed: {}", e)),
             }
         };
 
-        if let Ok(conn) = get_db_conn() {
-            let _ = update_match_state(&conn, m.match_id, MatchState::Parsing);
-        }
-        let _ = app.emit(
-            "match-state-changed",
-            serde_json::json!({ "match_id": m.match_id, "state": "Parsing" }),
-        );
-
-        // Wait for the parse job to finish, or fall back to a fixed delay.
-        if let Some(id) = job_id {
-            api_wait_for_parse(&settings, id).await;
-        } else if settings.data_provider != "stratz" {
-            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        // Without a cap, progress is measured against the player's match count.
+        // Saved with the next page; if it can't be fetched the import goes on without it.
+        if state.stop_before.is_none() && state.total_matches.is_none() && !total_requested {
+            total_requested = true;
+            state.total_matches =
+                opendota::fetch_match_count(&steam_id, settings.opendota_api_key.as_deref()).await.ok();
         }
 
-        // Fetch detailed match data
-        let detailed_match = match api_fetch_match_details(&settings, m.match_id).await {
-            Ok(dm) => dm,
-            Err(e) => {
-                eprintln!("Failed to fetch match details for {}: {}", m.match_id, e);
-                if let Ok(conn) = get_db_conn() {
-                    let _ = update_match_state(&conn, m.match_id, MatchState::Failed);
-                }
-                let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": m.match_id, "state": "Failed" }));
-                BACKFILL_PENDING.fetch_sub(1, Ordering::Relaxed);
-                let _ = app.emit("backfill-progress", serde_json::json!({ "active": true, "pending": BACKFILL_PENDING.load(Ordering::Relaxed) }));
+        let page = match provider.fetch_match_history_page(&steam_id, state.offset, BACKFILL_PAGE_SIZE).await {
+            Ok(page) => page,
+            Err(e) if e.is_unavailable() && unavailable_retries < MAX_UNAVAILABLE_RETRIES => {
+                // Back off and retry the same page; the cursor hasn't moved.
+                unavailable_retries += 1;
+                let wait = e.retry_after().unwrap_or(tokio::time::Duration::from_secs(60));
+                tokio::time::sleep(wait).await;
                 continue;
             }
+            Err(e) => finish!(format!("Backfill paused: {} Start it again to resume.", e)),
         };
+        unavailable_retries = 0;
 
-        // No more awaits until the sleep at the end — acquire the connection once for all writes.
-        let player_data = match detailed_match
-            .players
-            .iter()
-            .find(|p| p.account_id == Some(account_id))
-        {
-            Some(p) => p,
-            None => {
-                eprintln!("Player not found in match {}", m.match_id);
-                if let Ok(conn) = get_db_conn() {
-                    let _ = update_match_state(&conn, m.match_id, MatchState::Failed);
+        // Store the page and move the cursor — lock dropped before the next request.
+        let (state, pending) = {
+            let conn = match database::get_account_db_conn(account_id) {
+                Ok(c) => c,
+                Err(_) => finish!("Backfill paused: the active account changed. It will resume from where it stopped."),
+            };
+            for m in state.advance(page, BACKFILL_PAGE_SIZE) {
+                if matches!(match_exists(&conn, m.match_id), Ok(false)) && insert_match(&conn, &m).is_ok() {
+                    if let Some(patch) = database::get_patch_for_timestamp(&conn, m.start_time) {
+                        let _ = update_match_patch(&conn, m.match_id, &patch);
+                    }
+                    state.matches_added += 1;
                 }
-                let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": m.match_id, "state": "Failed" }));
-                BACKFILL_PENDING.fetch_sub(1, Ordering::Relaxed);
-                let _ = app.emit("backfill-progress", serde_json::json!({ "active": true, "pending": BACKFILL_PENDING.load(Ordering::Relaxed) }));
-                continue;
             }
+            if let Err(e) = database::save_backfill_state(&conn, &state) {
+                finish!(format!("Backfill failed: {}", e));
+            }
+            (state, get_unparsed_matches(&conn).map(|m| m.len()).unwrap_or(0))
         };
 
-        if let Ok(conn) = get_db_conn() {
-            let has_cs = matches!(
-                (&player_data.lh_t, &player_data.dn_t),
-                (Some(lh), Some(dn)) if !lh.is_empty() && !dn.is_empty()
-            );
-
-            // Both providers: no CS data means the match isn't ready yet — mark Failed so it
-            // can be retried.
-            if !has_cs {
-                let _ = update_match_state(&conn, m.match_id, MatchState::Failed);
-                let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": m.match_id, "state": "Failed" }));
-            } else {
-                // Store CS data if available
-                if has_cs {
-                    if let (Some(lh_t), Some(dn_t)) = (&player_data.lh_t, &player_data.dn_t) {
-                        let _ = insert_match_cs_data(&conn, m.match_id, lh_t, dn_t);
-                    }
-                }
-
-                let role = player_data.lane_role.unwrap_or(0);
-                let _ = update_match_role(&conn, m.match_id, role);
-
-                for p in &detailed_match.players {
-                    if let Some(nw_t) = &p.gold_t {
-                        let _ = insert_player_networth(&conn, m.match_id, p.player_slot, nw_t);
-                    }
-                }
-                if let Some(xp_t) = &player_data.xp_t {
-                    let _ = insert_match_xp_data(&conn, m.match_id, xp_t);
-                }
-                let partner = opendota::find_lane_partner(&detailed_match.players, player_data.player_slot, role);
-                let _ = update_match_partner_slot(&conn, m.match_id, partner.map(|p| p.player_slot));
-
-                if let Some(purchase_log) = &player_data.purchase_log {
-                    let _ = clear_item_timings_for_match(&conn, m.match_id);
-                    for purchase in purchase_log {
-                        if let Some(item_id) = items::get_item_id(&purchase.key) {
-                            let _ = insert_item_timing(&conn, &NewItemTiming { match_id: m.match_id, item_id, timing_seconds: purchase.time });
-                        }
-                    }
-                }
-
-                let _ = update_match_state(&conn, m.match_id, MatchState::Parsed);
-                let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": m.match_id, "state": "Parsed" }));
-                parsed_count += 1;
-            }
-        } // conn dropped here, before the sleep
+        BACKFILL_PENDING.store(pending, Ordering::Relaxed);
+        let _ = app.emit("backfill-progress", backfill_progress_json(!state.finished, pending, &state));
 
-        BACKFILL_PENDING.fetch_sub(1, Ordering::Relaxed);
-        let _ = app.emit("backfill-progress", serde_json::json!({ "active": true, "pending": BACKFILL_PENDING.load(Ordering::Relaxed) }));
+        if state.finished {
+            finish!(format!(
+                "Backfill complete! Added {} new matches; they'll be parsed in the background.",
+                state.matches_added
+            ));
+        }
 
-        // Pause between matches to stay well within OpenDota's rate limits.
+        // Pause between pages to stay well within the provider's rate limits.
         tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
     }
+}
 
-    finish!(format!(
-        "Backfill complete! Added {} new matches, parsed {} matches.",
-        new_count, parsed_count
-    ));
+/// Resume a match history import that was interrupted by closing the app.
+fn resume_backfill(app: tauri::AppHandle) {
+    let settings = Settings::load();
+    let Some(steam_id) = settings.steam_id.clone() else { return };
+    let unfinished = get_db_conn()
+        .and_then(|conn| database::get_backfill_state(&conn))
+        .ok()
+        .flatten()
+        .is_some_and(|state| !state.finished);
+    if unfinished && !BACKFILL_ACTIVE.load(Ordering::Relaxed) {
+        start_backfill_task(app, steam_id, settings);
+    }
 }
 
 /// Reparse all unparsed or failed matches
@@ -1866,12 +2038,18 @@ async fn run_backfill_task(
 async fn reparse_pending_matches(
     app: tauri::AppHandle,
     steam_id: String,
-) -> Result<String, String> {
-    let settings = Settings::load();
+) -> Result<String, DotaKeeperError> {
+    let provider = provider::from_settings(&Settings::load());
     // Get all unparsed or failed matches — lock dropped before any await.
+    // Each one gets a fresh set of background retries, whatever happens below.
     let matches = {
         let conn = get_db_conn()?;
-        get_unparsed_matches(&conn)?
+        let matches = get_unparsed_matches(&conn)?;
+        let now = chrono::Utc::now().timestamp();
+        for m in &matches {
+            database::reset_parse_job(&conn, m.match_id, now)?;
+        }
+        matches
     };
 
     if matches.is_empty() {
@@ -1889,7 +2067,7 @@ async fn reparse_pending_matches(
     // The DB lock is acquired in short scopes so it is never held across an await.
     for m in &matches {
         // Request parse
-        if let Err(e) = opendota::request_match_parse(m.match_id, settings.opendota_api_key.as_deref()).await {
+        if let Err(e) = provider.request_parse(m.match_id).await {
             eprintln!("Failed to request parse for match {}: {}", m.match_id, e);
             failed_count += 1;
             continue;
@@ -1908,10 +2086,12 @@ async fn reparse_pending_matches(
         );
 
         // Wait a bit for the parse to complete
-        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        if provider.capabilities().needs_parse_request {
+            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+        }
 
         // Fetch detailed match data
-        let detailed_match = match opendota::fetch_match_details(m.match_id, settings.opendota_api_key.as_deref()).await {
+        let detailed_match = match provider.fetch_match_details(m.match_id).await {
             Ok(dm) => dm,
             Err(e) => {
                 eprintln!("Failed to fetch match details for {}: {}", m.match_id, e);
@@ -1931,108 +2111,33 @@ async fn reparse_pending_matches(
             }
         };
 
-        // No more awaits until the sleep at the end — acquire the connection once for all writes.
-        let player_data = match detailed_match
-            .players
-            .iter()
-            .find(|p| p.account_id == Some(account_id))
-        {
-            Some(p) => p,
-            None => {
-                eprintln!("Player not found in match {}", m.match_id);
-                {
-                    let conn = get_db_conn()?;
-                    update_match_state(&conn, m.match_id, MatchState::Failed)?;
-                }
-                let _ = app.emit(
-                    "match-state-changed",
-                    serde_json::json!({
-                        "match_id": m.match_id,
-                        "state": "Failed"
-                    }),
-                );
-                failed_count += 1;
-                continue;
-            }
-        };
-
         {
             let conn = get_db_conn()?;
-            // Store CS data if available
-            if let (Some(lh_t), Some(dn_t)) = (&player_data.lh_t, &player_data.dn_t) {
-                if let Err(e) = insert_match_cs_data(&conn, m.match_id, lh_t, dn_t) {
-                    eprintln!("Failed to insert CS data for match {}: {}", m.match_id, e);
-                    update_match_state(&conn, m.match_id, MatchState::Failed)?;
+            match store_match_details(&conn, m.match_id, &detailed_match, account_id, provider.id()) {
+                Ok(true) => {
                     let _ = app.emit(
                         "match-state-changed",
                         serde_json::json!({
                             "match_id": m.match_id,
-                            "state": "Failed"
+                            "state": "Parsed"
                         }),
                     );
-                    failed_count += 1;
-                } else {
-                    // Store lane role
-                    let role = player_data.lane_role.unwrap_or(0);
-                    let _ = update_match_role(&conn, m.match_id, role);
-
-                    // Store per-minute networth for all players (used by PartnerNetworth goals)
-                    for p in &detailed_match.players {
-                        if let Some(nw_t) = &p.gold_t {
-                            let _ = insert_player_networth(&conn, m.match_id, p.player_slot, nw_t);
-                        }
-                    }
-                    // Store per-minute XP for the player (used for XP/Level charts)
-                    if let Some(xp_t) = &player_data.xp_t {
-                        let _ = insert_match_xp_data(&conn, m.match_id, xp_t);
-                    }
-                    // Identify and store lane partner slot
-                    let partner = opendota::find_lane_partner(
-                        &detailed_match.players,
-                        player_data.player_slot,
-                        role,
-                    );
-                    let _ = update_match_partner_slot(
-                        &conn,
-                        m.match_id,
-                        partner.map(|p| p.player_slot),
-                    );
-
-                    // Store item purchase timings if available
-                    if let Some(purchase_log) = &player_data.purchase_log {
-                        let _ = clear_item_timings_for_match(&conn, m.match_id);
-                        for purchase in purchase_log {
-                            if let Some(item_id) = items::get_item_id(&purchase.key) {
-                                let timing = NewItemTiming {
-                                    match_id: m.match_id,
-                                    item_id,
-                                    timing_seconds: purchase.time,
-                                };
-                                let _ = insert_item_timing(&conn, &timing);
-                            }
-                        }
+                    parsed_count += 1;
+                }
+                result => {
+                    if let Err(e) = result {
+                        eprintln!("Failed to store match {}: {}", m.match_id, e);
                     }
-
-                    update_match_state(&conn, m.match_id, MatchState::Parsed)?;
+                    update_match_state(&conn, m.match_id, MatchState::Failed)?;
                     let _ = app.emit(
                         "match-state-changed",
                         serde_json::json!({
                             "match_id": m.match_id,
-                            "state": "Parsed"
+                            "state": "Failed"
                         }),
                     );
-                    parsed_count += 1;
+                    failed_count += 1;
                 }
-            } else {
-                update_match_state(&conn, m.match_id, MatchState::Failed)?;
-                let _ = app.emit(
-                    "match-state-changed",
-                    serde_json::json!({
-                        "match_id": m.match_id,
-                        "state": "Failed"
-                    }),
-                );
-                failed_count += 1;
             }
         } // conn dropped here, before the sleep
 
@@ -2048,15 +2153,112 @@ async fn reparse_pending_matches(
 
 /// Set the reparse_dirty flag — all matches will be reparsed on next app restart.
 #[tauri::command]
-fn set_reparse_dirty_flag() -> Result<String, String> {
+fn set_reparse_dirty_flag() -> Result<String, DotaKeeperError> {
     let conn = get_db_conn()?;
     database::set_reparse_dirty(&conn)?;
     Ok("Reparse flag set. All matches will be reparsed on next restart.".to_string())
 }
 
+/// Store a parsed replay as a parsed match. The match row and its details land together,
+/// so a replay that can't be stored doesn't leave an unparsed match behind.
+fn store_replay(conn: &rusqlite::Connection, replay: &replay::Replay, account_id: u32) -> Result<(), DotaKeeperError> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| DotaKeeperError::db("Failed to begin replay import", e))?;
+    store_new_matches(&tx, vec![replay.to_match(account_id)?])?;
+    if !store_match_details(&tx, replay.match_id, &replay.to_detailed_match(), account_id, "replay")? {
+        return Err(DotaKeeperError::InvalidInput("The replay doesn't contain any per-minute data.".to_string()));
+    }
+    tx.commit().map_err(|e| DotaKeeperError::db("Failed to save replay import", e))
+}
+
+/// Import a match from a local replay file (.dem). Works without any network access,
+/// so offline games and private lobbies can be tracked too. Returns the match ID.
+#[tauri::command]
+async fn import_replay(app: tauri::AppHandle, path: String) -> Result<i64, DotaKeeperError> {
+    let settings = Settings::load();
+    let steam_id = settings.steam_id.as_deref().ok_or_else(|| DotaKeeperError::InvalidInput("No Steam ID configured".to_string()))?;
+    let account_id = steam_id64_to_id32(steam_id)?;
+
+    // Replays run to hundreds of megabytes; parse off the async runtime's worker threads.
+    let replay = tauri::async_runtime::spawn_blocking(move || replay::parse_replay_file(std::path::Path::new(&path)))
+        .await
+        .map_err(|e| DotaKeeperError::Other(format!("Failed to parse replay: {}", e)))??;
+    let conn = database::get_account_db_conn(account_id)?;
+    store_replay(&conn, &replay, account_id)?;
+    drop(conn);
+    let _ = app.emit(
+        "match-state-changed",
+        serde_json::json!({ "match_id": replay.match_id, "state": "Parsed" }),
+    );
+    Ok(replay.match_id)
+}
+
+/// Export the active profile's database and settings to a backup archive. Without a
+/// `path` the archive is written to the database folder. Returns the file written.
+/// Other profiles aren't included: each account has its own database, so switch to a
+/// profile to back it up.
+#[tauri::command]
+fn export_backup(path: Option<String>, include_api_keys: bool) -> Result<String, DotaKeeperError> {
+    let dest = match path {
+        Some(p) => std::path::PathBuf::from(p),
+        None => std::path::PathBuf::from(get_database_folder_path()?).join(format!(
+            "dota_keeper_backup_{}.dkbackup",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        )),
+    };
+    let conn = get_db_conn()?;
+    backup::export_archive(&conn, &Settings::load(), include_api_keys, &dest)?;
+    Ok(dest.to_string_lossy().to_string())
+}
+
+/// Import a backup archive into the active profile. `mode` is "merge" (keep local data,
+/// add what's missing) or "replace" (restore the backup as-is).
+#[tauri::command]
+fn import_backup(path: String, mode: String) -> Result<String, DotaKeeperError> {
+    let mode = backup::ImportMode::from_string(&mode)
+        .ok_or_else(|| DotaKeeperError::InvalidInput(format!("Unknown import mode: {}", mode)))?;
+    let local = Settings::load();
+    let steam_id = local.steam_id.as_deref().ok_or_else(|| DotaKeeperError::InvalidInput("Log in before importing a backup.".to_string()))?;
+    let account_id = steam_id64_to_id32(steam_id)?;
+
+    let mut conn = database::get_account_db_conn(account_id)?;
+    let (manifest, matches) = backup::import_archive(&mut conn, account_id, std::path::Path::new(&path), mode)?;
+    backup::merged_settings(&local, &manifest.settings, mode).save()?;
+
+    Ok(match mode {
+        backup::ImportMode::Merge => format!("Backup merged: added {} new matches.", matches),
+        backup::ImportMode::Replace => format!("Backup restored: {} matches.", matches),
+    })
+}
+
+/// Export matches with their goal evaluations, item timings and per-minute CS as "csv"
+/// or "ndjson". Without a `path` the file is written to the database folder.
+/// Returns the file written.
+#[tauri::command]
+fn export_matches(
+    format: String,
+    filters: Option<export::MatchExportFilters>,
+    path: Option<String>,
+) -> Result<String, DotaKeeperError> {
+    let format = export::ExportFormat::from_string(&format)
+        .ok_or_else(|| DotaKeeperError::InvalidInput(format!("Unknown export format: {}", format)))?;
+    let dest = match path {
+        Some(p) => std::path::PathBuf::from(p),
+        None => std::path::PathBuf::from(get_database_folder_path()?).join(format!(
+            "dota_keeper_matches_{}.{}",
+            chrono::Local::now().format("%Y%m%d_%H%M%S"),
+            format.extension()
+        )),
+    };
+    let conn = get_db_conn()?;
+    export::export_matches(&conn, format, &filters.unwrap_or_default(), &dest)?;
+    Ok(dest.to_string_lossy().to_string())
+}
+
 /// Clear all matches from the database
 #[tauri::command]
-fn clear_matches() -> Result<String, String> {
+fn clear_matches() -> Result<String, DotaKeeperError> {
     let conn = get_db_conn()?;
     clear_all_matches(&conn)?;
     Ok("All matches cleared successfully.".to_string())
@@ -2064,9 +2266,13 @@ fn clear_matches() -> Result<String, String> {
 
 /// Factory reset: wipe all database data and settings, then exit the app.
 #[tauri::command]
-fn factory_reset(app: tauri::AppHandle) -> Result<(), String> {
+fn factory_reset(app: tauri::AppHandle) -> Result<(), DotaKeeperError> {
+    let settings = settings::Settings::load();
+    let active_key = settings.active_profile().and_then(|p| p.db_key.as_deref());
+    let profile_keys: Vec<Option<&str>> = settings.profiles.iter().map(|p| p.db_key.as_deref()).collect();
+    let dir = database::db_dir().ok_or_else(|| DotaKeeperError::Database("Could not determine database directory".to_string()))?;
     let conn = get_db_conn()?;
-    factory_reset_db(&conn)?;
+    database::factory_reset_profiles(&conn, &dir, active_key, &profile_keys)?;
     drop(conn); // release the mutex guard before deleting settings
     settings::Settings::delete_settings_file()?;
     app.exit(0);
@@ -2075,7 +2281,7 @@ fn factory_reset(app: tauri::AppHandle) -> Result<(), String> {
 
 /// Mark onboarding as completed
 #[tauri::command]
-fn complete_onboarding() -> Result<settings::Settings, String> {
+fn complete_onboarding() -> Result<settings::Settings, DotaKeeperError> {
     let mut s = settings::Settings::load();
     s.onboarding_completed = true;
     s.save()?;
@@ -2084,14 +2290,14 @@ fn complete_onboarding() -> Result<settings::Settings, String> {
 
 /// Toggle hero favorite status
 #[tauri::command]
-fn toggle_favorite_hero(hero_id: i32) -> Result<bool, String> {
+fn toggle_favorite_hero(hero_id: i32) -> Result<bool, DotaKeeperError> {
     let conn = get_db_conn()?;
     toggle_hero_favorite(&conn, hero_id)
 }
 
 /// Get all favorite hero IDs
 #[tauri::command]
-fn get_favorite_heroes() -> Result<Vec<i32>, String> {
+fn get_favorite_heroes() -> Result<Vec<i32>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_favorite_hero_ids(&conn)
 }
@@ -2104,65 +2310,73 @@ fn get_all_items() -> Vec<items::Item> {
 
 /// Get item timings for a specific match
 #[tauri::command]
-fn get_match_item_timings(match_id: i64) -> Result<Vec<database::ItemTiming>, String> {
+fn get_match_item_timings(match_id: i64) -> Result<Vec<database::ItemTiming>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_item_timings_for_match(&conn, match_id)
 }
 
 /// Get per-minute CS data for a specific match
 #[tauri::command]
-fn get_match_cs(match_id: i64) -> Result<Vec<MatchCS>, String> {
+fn get_match_cs(match_id: i64) -> Result<Vec<MatchCS>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_match_cs_data(&conn, match_id)
 }
 
 /// Get best + average CS per minute for a hero in a given game mode
 #[tauri::command]
-fn get_hero_cs_stats(hero_id: i32, game_mode: i32, exclude_match_id: i64) -> Result<Vec<HeroCsStats>, String> {
+fn get_hero_cs_stats(hero_id: i32, game_mode: i32, exclude_match_id: i64) -> Result<Vec<HeroCsStats>, DotaKeeperError> {
     let conn = get_db_conn()?;
     database::get_hero_cs_stats(&conn, hero_id, game_mode, exclude_match_id)
 }
 
+/// Get win rate, KDA and lane CS diff on a hero broken down by enemy and allied heroes.
+/// Heroes seen in fewer than `min_games` games (default 3) are omitted.
+#[tauri::command]
+fn get_hero_matchups(hero_id: i32, min_games: Option<i32>) -> Result<database::HeroMatchups, DotaKeeperError> {
+    let conn = get_db_conn()?;
+    database::get_hero_matchups(&conn, hero_id, min_games.unwrap_or(database::MIN_MATCHUP_GAMES))
+}
+
 /// Get per-minute networth data for the player in a specific match
 #[tauri::command]
-fn get_match_networth(match_id: i64) -> Result<Vec<MatchNW>, String> {
+fn get_match_networth(match_id: i64) -> Result<Vec<MatchNW>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_match_networth_data(&conn, match_id)
 }
 
 /// Get per-minute XP data for a specific match
 #[tauri::command]
-fn get_match_xp(match_id: i64) -> Result<Vec<MatchXP>, String> {
+fn get_match_xp(match_id: i64) -> Result<Vec<MatchXP>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_match_xp_data(&conn, match_id)
 }
 
 /// Get (or generate) today's daily challenge
 #[tauri::command]
-fn get_daily_challenge() -> Result<Option<DailyChallenge>, String> {
+fn get_daily_challenge() -> Result<Option<DailyChallenge>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_or_generate_daily_challenge(&conn)
 }
 
 /// Get today's daily challenge with evaluated progress
 #[tauri::command]
-fn get_daily_challenge_progress_cmd() -> Result<Option<DailyChallengeProgress>, String> {
+fn get_daily_challenge_progress_cmd() -> Result<Option<DailyChallengeProgress>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_daily_challenge_progress(&conn)
 }
 
 /// Get the current daily challenge completion streak
 #[tauri::command]
-fn get_daily_streak_cmd() -> Result<i32, String> {
+fn get_daily_streak_cmd() -> Result<i32, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_daily_streak(&conn)
 }
 
 /// Convert Steam ID64 to Steam ID32 (account ID)
-fn steam_id64_to_id32(steam_id64: &str) -> Result<u32, String> {
+fn steam_id64_to_id32(steam_id64: &str) -> Result<u32, DotaKeeperError> {
     let id64: u64 = steam_id64
         .parse()
-        .map_err(|_| "Invalid Steam ID format".to_string())?;
+        .map_err(|_| DotaKeeperError::InvalidInput("Invalid Steam ID format".to_string()))?;
 
     const STEAM_ID64_BASE: u64 = 76561197960265728;
 
@@ -2176,37 +2390,37 @@ fn steam_id64_to_id32(steam_id64: &str) -> Result<u32, String> {
 // ===== Weekly Challenge Commands =====
 
 #[tauri::command]
-fn get_weekly_challenge_options_cmd() -> Result<Vec<ChallengeOption>, String> {
+fn get_weekly_challenge_options_cmd() -> Result<Vec<ChallengeOption>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_weekly_challenge_options(&conn)
 }
 
 #[tauri::command]
-fn reroll_weekly_challenges_cmd() -> Result<Vec<ChallengeOption>, String> {
+fn reroll_weekly_challenges_cmd() -> Result<Vec<ChallengeOption>, DotaKeeperError> {
     let conn = get_db_conn()?;
     reroll_weekly_challenges(&conn)
 }
 
 #[tauri::command]
-fn skip_weekly_challenge_cmd() -> Result<(), String> {
+fn skip_weekly_challenge_cmd() -> Result<(), DotaKeeperError> {
     let conn = get_db_conn()?;
     skip_weekly_challenge(&conn)
 }
 
 #[tauri::command]
-fn accept_weekly_challenge_cmd(option_id: i64) -> Result<WeeklyChallenge, String> {
+fn accept_weekly_challenge_cmd(option_id: i64) -> Result<WeeklyChallenge, DotaKeeperError> {
     let conn = get_db_conn()?;
     accept_weekly_challenge(&conn, option_id)
 }
 
 #[tauri::command]
-fn get_active_weekly_challenge_cmd() -> Result<Option<WeeklyChallenge>, String> {
+fn get_active_weekly_challenge_cmd() -> Result<Option<WeeklyChallenge>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_active_weekly_challenge(&conn)
 }
 
 #[tauri::command]
-fn get_weekly_challenge_progress_cmd() -> Result<Option<WeeklyChallengeProgress>, String> {
+fn get_weekly_challenge_progress_cmd() -> Result<Option<WeeklyChallengeProgress>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_weekly_challenge_progress(&conn)
 }
@@ -2215,7 +2429,7 @@ fn get_weekly_challenge_progress_cmd() -> Result<Option<WeeklyChallengeProgress>
 fn get_challenge_history_cmd(
     challenge_type: Option<String>,
     limit: Option<i32>,
-) -> Result<Vec<ChallengeHistoryItem>, String> {
+) -> Result<Vec<ChallengeHistoryItem>, DotaKeeperError> {
     let conn = get_db_conn()?;
     get_challenge_history(&conn, challenge_type, limit.unwrap_or(50))
 }
@@ -2265,7 +2479,7 @@ fn percent_decode(input: &str) -> String {
 /// When Steam redirects back, the callback is verified and a `steam-login-complete`
 /// event is emitted with `{steam_id}` on success or `{error}` on failure.
 #[tauri::command]
-async fn start_steam_login(app: tauri::AppHandle) -> Result<String, String> {
+async fn start_steam_login(app: tauri::AppHandle) -> Result<String, DotaKeeperError> {
     #[cfg(target_os = "android")]
     {
         let return_to = "https://dotakeeper.com/steam-callback/";
@@ -2290,11 +2504,11 @@ async fn start_steam_login(app: tauri::AppHandle) -> Result<String, String> {
 
         let listener = TcpListener::bind("127.0.0.1:0")
             .await
-            .map_err(|e| format!("Failed to bind port: {e}"))?;
+            .map_err(|e| DotaKeeperError::Other(format!("Failed to bind port: {e}")))?;
 
         let port = listener
             .local_addr()
-            .map_err(|e| format!("Failed to get local address: {e}"))?
+            .map_err(|e| DotaKeeperError::Other(format!("Failed to get local address: {e}")))?
             .port();
 
         let return_to = format!("http://127.0.0.1:{port}/callback");
@@ -2328,7 +2542,7 @@ async fn start_steam_login(app: tauri::AppHandle) -> Result<String, String> {
 /// the WebView reloads on return, so any event listener registered before the redirect
 /// no longer exists by the time this runs.
 #[tauri::command]
-async fn verify_steam_deep_link(query_string: String) -> Result<Option<String>, String> {
+async fn verify_steam_deep_link(query_string: String) -> Result<Option<String>, DotaKeeperError> {
     let mut params: Vec<(String, String)> = Vec::new();
     let mut claimed_id: Option<String> = None;
     let mut openid_mode: Option<String> = None;
@@ -2358,7 +2572,7 @@ async fn verify_steam_deep_link(query_string: String) -> Result<Option<String>,
         .map(|s| s.trim().to_string())
     {
         Some(id) if !id.is_empty() => id,
-        _ => return Err("Could not extract Steam ID".to_string()),
+        _ => return Err(DotaKeeperError::InvalidInput("Could not extract Steam ID".to_string())),
     };
 
     let verify_params: Vec<(String, String)> = params
@@ -2372,12 +2586,8 @@ async fn verify_steam_deep_link(query_string: String) -> Result<Option<String>,
         })
         .collect();
 
-    let verified = match reqwest::Client::new()
-        .post("https://steamcommunity.com/openid/login")
-        .form(&verify_params)
-        .send()
-        .await
-    {
+    let request = http::client().post("https://steamcommunity.com/openid/login").form(&verify_params);
+    let verified = match http::send(ApiHost::Steam, request).await {
         Ok(resp) => resp.text().await.unwrap_or_default().contains("is_valid:true"),
         Err(e) => {
             eprintln!("Steam OpenID verification request failed: {e}");
@@ -2388,7 +2598,7 @@ async fn verify_steam_deep_link(query_string: String) -> Result<Option<String>,
     if verified {
         Ok(Some(steam_id))
     } else {
-        Err("Steam verification failed".to_string())
+        Err(DotaKeeperError::AuthFailed { provider: "Steam" })
     }
 }
 
@@ -2519,12 +2729,8 @@ async fn handle_steam_callback(listener: tokio::net::TcpListener, app: tauri::Ap
         })
         .collect();
 
-    let verified = match reqwest::Client::new()
-        .post("https://steamcommunity.com/openid/login")
-        .form(&verify_params)
-        .send()
-        .await
-    {
+    let request = http::client().post("https://steamcommunity.com/openid/login").form(&verify_params);
+    let verified = match http::send(ApiHost::Steam, request).await {
         Ok(resp) => resp
             .text()
             .await
@@ -2609,6 +2815,8 @@ fn get_background_parse_status() -> BackgroundParseStatus {
 struct BackfillStatus {
     active: bool,
     pending: usize,
+    /// The saved history import (cursor, cap and totals), if one was ever started
+    state: Option<database::BackfillState>,
 }
 
 /// Returns the current backfill status (active flag + remaining count).
@@ -2617,21 +2825,70 @@ fn get_backfill_status() -> BackfillStatus {
     BackfillStatus {
         active: BACKFILL_ACTIVE.load(Ordering::Relaxed),
         pending: BACKFILL_PENDING.load(Ordering::Relaxed),
+        state: get_db_conn().and_then(|conn| database::get_backfill_state(&conn)).ok().flatten(),
     }
 }
 
+/// Per-host request counters, limiter waits and rate-limit pauses since startup.
+#[tauri::command]
+fn get_http_diagnostics() -> Vec<http::HostDiagnostics> {
+    http::diagnostics()
+}
+
 /// Enable or disable privacy mode (masks Steam ID in the UI).
 #[tauri::command]
-fn save_privacy_mode(enabled: bool) -> Result<Settings, String> {
+fn save_privacy_mode(enabled: bool) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.privacy_mode = enabled;
     settings.save()?;
     Ok(settings)
 }
 
+/// Turn live match tracking (Game State Integration) on or off. The listener starts or
+/// stops immediately.
+#[tauri::command]
+fn save_gsi_enabled(app: tauri::AppHandle, enabled: bool) -> Result<Settings, DotaKeeperError> {
+    let mut settings = Settings::load();
+    settings.gsi_enabled = enabled;
+    settings.save()?;
+    if enabled {
+        gsi::start(app, settings.gsi_port, settings.gsi_token.clone());
+    } else {
+        gsi::stop();
+    }
+    Ok(settings)
+}
+
+/// The GSI config file Dota 2 needs to send its game state to this app, for users who
+/// prefer to copy it into the game's `cfg/gamestate_integration` folder themselves.
+#[tauri::command]
+fn get_gsi_config() -> String {
+    let settings = Settings::load();
+    gsi::config_file_contents(settings.gsi_port, &settings.gsi_token)
+}
+
+/// Write the GSI config file into the Dota 2 install folder (auto-detected when `dota_path`
+/// is omitted). Returns the file written; Dota 2 must be restarted to pick it up.
+#[tauri::command]
+fn install_gsi_config(dota_path: Option<String>) -> Result<String, DotaKeeperError> {
+    let settings = Settings::load();
+    let path = gsi::install_config_file(dota_path.map(std::path::PathBuf::from), settings.gsi_port, &settings.gsi_token)?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// The game being tracked over GSI (or the last one), with live progress on goals.
+#[tauri::command]
+fn get_live_game() -> Result<Option<gsi::LiveGameUpdate>, DotaKeeperError> {
+    let goals = {
+        let conn = get_db_conn()?;
+        database::get_active_goals(&conn)?
+    };
+    gsi::current_update(&goals)
+}
+
 /// Save the minimum benchmark games setting.
 #[tauri::command]
-fn save_min_benchmark_games(value: i32) -> Result<Settings, String> {
+fn save_min_benchmark_games(value: i32) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.min_benchmark_games = value;
     settings.save()?;
@@ -2640,190 +2897,155 @@ fn save_min_benchmark_games(value: i32) -> Result<Settings, String> {
 
 /// Enable or disable the background match parser.
 #[tauri::command]
-fn save_background_parse_enabled(enabled: bool) -> Result<Settings, String> {
+fn save_background_parse_enabled(enabled: bool) -> Result<Settings, DotaKeeperError> {
     let mut settings = Settings::load();
     settings.background_parse_enabled = enabled;
     settings.save()?;
     Ok(settings)
 }
 
-/// Parses unparsed matches one at a time in the background with a 10-second
-/// delay between each attempt.  Called once from `run()` via `tokio::spawn`.
+/// Long-running worker that drains the persistent parse queue, one match at a time with a
+/// 10-second gap between attempts.  Unparsed and failed matches are (re)queued on every pass;
+/// failures are retried with exponential backoff, so progress survives restarts.
+/// Called once from `run()` via `tokio::spawn`.
 async fn background_parse_loop(app: tauri::AppHandle) {
-    let settings = Settings::load();
-    if !settings.background_parse_enabled {
-        return;
-    }
-    let steam_id = match &settings.steam_id {
-        Some(s) => s.clone(),
-        None => return,
-    };
-    let account_id = match steam_id64_to_id32(&steam_id) {
-        Ok(id) => id,
-        Err(_) => return,
-    };
+    // How long to sleep when the worker is paused or has nothing due.
+    const IDLE_POLL_SECS: u64 = 30;
 
-    let matches = {
-        let conn = match get_db_conn() {
-            Ok(c) => c,
-            Err(_) => return,
-        };
-        match get_unparsed_matches(&conn) {
-            Ok(m) => m,
-            Err(_) => return,
+    loop {
+        if GOAL_COMPLETION_CHECK_DUE.swap(false, Ordering::Relaxed) {
+            check_goal_completion(&app);
         }
-    };
 
-    if matches.is_empty() {
-        return;
-    }
+        let settings = Settings::load();
+        let account_id = settings
+            .steam_id
+            .as_deref()
+            .and_then(|id| steam_id64_to_id32(id).ok());
+        let Some(account_id) = account_id.filter(|_| settings.background_parse_enabled) else {
+            set_background_parse_progress(&app, false, BG_PARSER_PENDING.load(Ordering::Relaxed));
+            tokio::time::sleep(tokio::time::Duration::from_secs(IDLE_POLL_SECS)).await;
+            continue;
+        };
 
-    BG_PARSER_ACTIVE.store(true, Ordering::Relaxed);
-    BG_PARSER_PENDING.store(matches.len(), Ordering::Relaxed);
-    let _ = app.emit(
-        "background-parse-progress",
-        serde_json::json!({ "pending": matches.len(), "active": true }),
-    );
+        let now = chrono::Utc::now().timestamp();
+        let (next, pending) = {
+            let Ok(conn) = get_db_conn() else { return };
+            // Games recorded over GSI don't need the provider
+            for match_id in apply_gsi_recordings(&conn, account_id).unwrap_or_default() {
+                let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Parsed" }));
+            }
+            let _ = database::enqueue_unparsed_matches(&conn, now);
+            (
+                database::next_due_parse_job(&conn, now).ok().flatten(),
+                database::count_pending_parse_jobs(&conn).unwrap_or(0),
+            )
+        };
 
-    for m in &matches {
-        // Re-check the enabled flag on each iteration so the user can pause it.
-        if !Settings::load().background_parse_enabled {
-            break;
-        }
+        let Some(match_id) = next else {
+            set_background_parse_progress(&app, false, pending);
+            tokio::time::sleep(tokio::time::Duration::from_secs(IDLE_POLL_SECS)).await;
+            continue;
+        };
+        set_background_parse_progress(&app, true, pending);
 
-        // Request parse; for OpenDota retry once after 60 s if rate-limited.
-        // For Stratz, api_request_parse returns Ok(None) immediately.
-        let job_id = match api_request_parse(&settings, m.match_id).await {
-            Ok(id) => id,
-            Err(ref e) if e.contains("Too many requests") => {
+        let provider = provider::from_settings(&settings);
+        if let Err(e) = parse_queued_match(&app, &provider, match_id, account_id).await {
+            let rate_limited = e.is_unavailable();
+            if let Ok(conn) = database::get_account_db_conn(account_id) {
+                let _ = database::record_parse_failure(&conn, match_id, &e.to_string(), chrono::Utc::now().timestamp());
+            }
+            if rate_limited {
+                // Give the provider a breather before touching the queue again.
                 sentry::capture_message(
-                    "OpenDota rate limit hit in background parser — retrying after 60s",
+                    "Data provider unavailable in background parser — pausing for 60s",
                     sentry::Level::Warning,
                 );
                 tokio::time::sleep(tokio::time::Duration::from_secs(60)).await;
-                match api_request_parse(&settings, m.match_id).await {
-                    Ok(id) => id,
-                    Err(_) => {
-                        BG_PARSER_PENDING.fetch_sub(1, Ordering::Relaxed);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-                        continue;
-                    }
-                }
-            }
-            Err(_) => {
-                BG_PARSER_PENDING.fetch_sub(1, Ordering::Relaxed);
-                tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-                continue;
             }
-        };
-
-        {
-            let Ok(conn) = get_db_conn() else { break };
-            let _ = update_match_state(&conn, m.match_id, MatchState::Parsing);
         }
+
+        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+    }
+}
+
+fn set_background_parse_progress(app: &tauri::AppHandle, active: bool, pending: usize) {
+    let was_active = BG_PARSER_ACTIVE.swap(active, Ordering::Relaxed);
+    let was_pending = BG_PARSER_PENDING.swap(pending, Ordering::Relaxed);
+    if was_active != active || was_pending != pending {
         let _ = app.emit(
-            "match-state-changed",
-            serde_json::json!({ "match_id": m.match_id, "state": "Parsing" }),
+            "background-parse-progress",
+            serde_json::json!({ "pending": pending, "active": active }),
         );
+    }
+}
 
-        // Wait for the parse job to finish (OpenDota only; Stratz skips this).
-        if let Some(id) = job_id {
-            api_wait_for_parse(&settings, id).await;
-        } else if settings.data_provider != "stratz" {
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+/// Run one parse attempt for a queued match: request parse, wait, fetch and store details.
+/// Emits the usual match-state-changed events; on error the match is left Failed. A match
+/// parsed from a GSI recording stays Parsed with its GSI data until the provider's lands.
+async fn parse_queued_match(
+    app: &tauri::AppHandle,
+    provider: &provider::ProviderChain,
+    match_id: i64,
+    account_id: u32,
+) -> Result<(), DotaKeeperError> {
+    let from_gsi = {
+        let conn = database::get_account_db_conn(account_id)?;
+        database::get_match_by_id(&conn, match_id)?.is_some_and(|m| m.parsed_by.as_deref() == Some("gsi"))
+    };
+    let mark_failed = |e: DotaKeeperError| -> DotaKeeperError {
+        if from_gsi {
+            return e;
         }
+        if let Ok(conn) = database::get_account_db_conn(account_id) {
+            let _ = update_match_state(&conn, match_id, MatchState::Failed);
+        }
+        let _ = app.emit(
+            "match-state-changed",
+            serde_json::json!({ "match_id": match_id, "state": "Failed" }),
+        );
+        e
+    };
 
-        let detailed_match = match api_fetch_match_details(&settings, m.match_id).await {
-            Ok(dm) => dm,
-            Err(_) => {
-                if let Ok(conn) = get_db_conn() {
-                    let _ = update_match_state(&conn, m.match_id, MatchState::Failed);
-                }
-                let _ = app.emit(
-                    "match-state-changed",
-                    serde_json::json!({ "match_id": m.match_id, "state": "Failed" }),
-                );
-                BG_PARSER_PENDING.fetch_sub(1, Ordering::Relaxed);
-                tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-                continue;
-            }
-        };
-
-        let player_data = match detailed_match
-            .players
-            .iter()
-            .find(|p| p.account_id == Some(account_id))
-        {
-            Some(p) => p,
-            None => {
-                if let Ok(conn) = get_db_conn() {
-                    let _ = update_match_state(&conn, m.match_id, MatchState::Failed);
-                }
-                BG_PARSER_PENDING.fetch_sub(1, Ordering::Relaxed);
-                tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
-                continue;
-            }
-        };
+    // Providers without a parse step return Ok(None) immediately.
+    let job_id = provider.request_parse(match_id).await.map_err(&mark_failed)?;
 
+    if !from_gsi {
         {
-            let Ok(conn) = get_db_conn() else { break };
-            let has_cs = matches!(
-                (&player_data.lh_t, &player_data.dn_t),
-                (Some(lh), Some(dn)) if !lh.is_empty() && !dn.is_empty()
-            );
-
-            // Both providers: no CS data means the match isn't ready yet — mark Failed so it
-            // can be retried.
-            if !has_cs {
-                let _ = update_match_state(&conn, m.match_id, MatchState::Failed);
-                let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": m.match_id, "state": "Failed" }));
-            } else {
-                if has_cs {
-                    if let (Some(lh_t), Some(dn_t)) = (&player_data.lh_t, &player_data.dn_t) {
-                        let _ = insert_match_cs_data(&conn, m.match_id, lh_t, dn_t);
-                    }
-                }
-                let role = player_data.lane_role.unwrap_or(0);
-                let _ = update_match_role(&conn, m.match_id, role);
-                for p in &detailed_match.players {
-                    if let Some(nw_t) = &p.gold_t {
-                        let _ = insert_player_networth(&conn, m.match_id, p.player_slot, nw_t);
-                    }
-                }
-                if let Some(xp_t) = &player_data.xp_t {
-                    let _ = insert_match_xp_data(&conn, m.match_id, xp_t);
-                }
-                let partner = opendota::find_lane_partner(&detailed_match.players, player_data.player_slot, role);
-                let _ = update_match_partner_slot(&conn, m.match_id, partner.map(|p| p.player_slot));
-                if let Some(purchase_log) = &player_data.purchase_log {
-                    let _ = clear_item_timings_for_match(&conn, m.match_id);
-                    for purchase in purchase_log {
-                        if let Some(item_id) = items::get_item_id(&purchase.key) {
-                            let _ = insert_item_timing(&conn, &NewItemTiming { match_id: m.match_id, item_id, timing_seconds: purchase.time });
-                        }
-                    }
-                }
-                let _ = update_match_state(&conn, m.match_id, MatchState::Parsed);
-                let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": m.match_id, "state": "Parsed" }));
-            }
+            let conn = database::get_account_db_conn(account_id)?;
+            update_match_state(&conn, match_id, MatchState::Parsing)?;
         }
-
-        BG_PARSER_PENDING.fetch_sub(1, Ordering::Relaxed);
-        let pending = BG_PARSER_PENDING.load(Ordering::Relaxed);
         let _ = app.emit(
-            "background-parse-progress",
-            serde_json::json!({ "pending": pending, "active": true }),
+            "match-state-changed",
+            serde_json::json!({ "match_id": match_id, "state": "Parsing" }),
         );
+    }
 
-        tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+    // Wait for the parse job to finish (OpenDota only; Stratz skips this).
+    if let Some(id) = job_id {
+        provider.wait_for_parse(id).await;
+    } else if provider.capabilities().needs_parse_request {
+        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
     }
 
-    BG_PARSER_ACTIVE.store(false, Ordering::Relaxed);
-    BG_PARSER_PENDING.store(0, Ordering::Relaxed);
-    let _ = app.emit(
-        "background-parse-progress",
-        serde_json::json!({ "pending": 0, "active": false }),
-    );
+    let detailed_match = provider.fetch_match_details(match_id).await.map_err(&mark_failed)?;
+
+    let conn = database::get_account_db_conn(account_id)?;
+    match store_match_details(&conn, match_id, &detailed_match, account_id, provider.id()) {
+        Ok(true) => {
+            let _ = app.emit("match-state-changed", serde_json::json!({ "match_id": match_id, "state": "Parsed" }));
+            Ok(())
+        }
+        // Both providers: no CS data means the match isn't ready yet — retry later.
+        Ok(false) => {
+            drop(conn);
+            Err(mark_failed(DotaKeeperError::NotParsedYet(provider.not_ready_message().to_string())))
+        }
+        Err(e) => {
+            drop(conn);
+            Err(mark_failed(e))
+        }
+    }
 }
 
 // ── end Background match parser ───────────────────────────────────────────────
@@ -2863,8 +3085,8 @@ fn get_performance_journal(
     metric: String,
     hero_id: Option<i32>,
     limit: usize,
-) -> Result<PerformanceJournal, String> {
-    let conn = get_db_conn().map_err(|e| format!("DB connection error: {}", e))?;
+) -> Result<PerformanceJournal, DotaKeeperError> {
+    let conn = get_db_conn()?;
 
     // Build the metric expression and whether "low = good" (deaths).
     let (metric_expr, low_is_best, use_lh10): (&str, bool, bool) = match metric.as_str() {
@@ -2874,7 +3096,7 @@ fn get_performance_journal(
         "damage" => ("CAST(m.hero_damage AS REAL)", false, false),
         "deaths" => ("CAST(m.deaths AS REAL)", true, false),
         "lh10"   => ("CAST(mc.last_hits AS REAL)", false, true),
-        other    => return Err(format!("Unknown metric: {}", other)),
+        other    => return Err(DotaKeeperError::InvalidInput(format!("Unknown metric: {}", other))),
     };
 
     let hero_filter = if hero_id.is_some() { "AND m.hero_id = ?2" } else { "" };
@@ -2913,10 +3135,10 @@ fn get_performance_journal(
         )
     };
 
-    let fetch_entries = |sql: &str| -> Result<Vec<JournalEntry>, String> {
+    let fetch_entries = |sql: &str| -> Result<Vec<JournalEntry>, DotaKeeperError> {
         let mut stmt = conn
             .prepare(sql)
-            .map_err(|e| format!("DB prepare error: {}", e))?;
+            .map_err(|e| DotaKeeperError::db("DB prepare error", e))?;
 
         let rows: Vec<JournalEntry> = if let Some(hid) = hero_id {
             stmt.query_map(rusqlite::params![limit_i64, hid], |row| {
@@ -2936,7 +3158,7 @@ fn get_performance_journal(
                     game_mode:    row.get(12)?,
                 })
             })
-            .map_err(|e| format!("DB query error: {}", e))?
+            .map_err(|e| DotaKeeperError::db("DB query error", e))?
             .filter_map(|r: rusqlite::Result<JournalEntry>| r.ok())
             .collect()
         } else {
@@ -2957,7 +3179,7 @@ fn get_performance_journal(
                     game_mode:    row.get(12)?,
                 })
             })
-            .map_err(|e| format!("DB query error: {}", e))?
+            .map_err(|e| DotaKeeperError::db("DB query error", e))?
             .filter_map(|r: rusqlite::Result<JournalEntry>| r.ok())
             .collect()
         };
@@ -3013,17 +3235,41 @@ pub fn run() {
                 .expect("could not resolve app data directory");
             set_db_dir(app_data_dir.clone());
             set_settings_dir(app_data_dir);
+            // Point the API clients at a local fixture server when requested
+            // (offline development against recorded provider responses).
+            if let Ok(url) = std::env::var("DOTA_KEEPER_OPENDOTA_URL") {
+                opendota::set_api_base(url);
+            }
+            if let Ok(url) = std::env::var("DOTA_KEEPER_STRATZ_URL") {
+                stratz::set_api_base(url);
+            }
             // Open a single shared database connection for the lifetime of the app.
             // All Tauri commands acquire this via get_db_conn(), which serializes DB
             // access through a Mutex and eliminates concurrent-write SQLITE_BUSY errors.
-            let conn = init_db().expect("Failed to initialize database");
+            // The logged-in profile decides which database file is opened.
+            let settings = Settings::load();
+            let profile = settings.active_profile();
+            let account_id = settings.steam_id.as_deref().and_then(|id| steam_id64_to_id32(id).ok());
+            let conn = init_db(profile.and_then(|p| p.db_key.as_deref()), account_id)
+                .expect("Failed to initialize database");
             init_shared_db(conn);
+            // Live match tracking: Dota 2 only runs on desktop.
+            #[cfg(desktop)]
+            if settings.gsi_enabled {
+                gsi::start(app.handle().clone(), settings.gsi_port, settings.gsi_token.clone());
+            }
             // Spawn the background parser; small delay lets the UI render first.
             let bg_app = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
                 background_parse_loop(bg_app).await;
             });
+            // Pick up an interrupted match history import.
+            let backfill_app = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
+                resume_backfill(backfill_app);
+            });
             // Fetch hero benchmark data from GitHub (non-blocking).
             tauri::async_runtime::spawn(async move {
                 tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
@@ -3049,14 +3295,28 @@ pub fn run() {
     builder.invoke_handler(tauri::generate_handler![
             get_settings,
             save_steam_id,
+            get_profiles,
+            switch_account,
+            rename_profile,
             logout,
             refresh_matches,
             get_matches,
+            query_matches,
+            get_match_filter_options,
             create_goal,
             get_goals,
             get_goals_calendar,
             get_goal,
             get_goal_histogram_data,
+            get_goal_status,
+            set_goal_state,
+            get_goal_history,
+            save_goal_completion_games,
+            get_match_players,
+            get_match_player_timeline,
+            get_match_player_items,
+            get_lane_outcome,
+            get_hero_matchups,
             save_goal,
             remove_goal,
             evaluate_goals_for_match,
@@ -3110,13 +3370,19 @@ pub fn run() {
             get_checkin_history,
             get_background_parse_status,
             get_backfill_status,
+            get_http_diagnostics,
             save_background_parse_enabled,
             save_privacy_mode,
             get_os_locale,
             save_data_provider,
+            get_provider_capabilities,
             save_stratz_api_key,
             save_opendota_api_key,
             factory_reset,
+            export_backup,
+            import_backup,
+            import_replay,
+            export_matches,
             get_medal_history,
             get_medal_stats,
             get_patches,
@@ -3127,8 +3393,535 @@ pub fn run() {
             has_benchmarks,
             get_user_lh_history,
             save_min_benchmark_games,
+            save_gsi_enabled,
+            get_gsi_config,
+            install_gsi_config,
+            get_live_game,
             get_performance_journal
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::{FrequencyType, GoalEvaluation, GoalGameMode, GoalMetric};
+
+    /// Steam ID of the player the recorded fixtures were captured for (account 39734273).
+    const TEST_STEAM_ID: &str = "76561198000000001";
+    const TEST_ACCOUNT_ID: u32 = 39734273;
+
+    fn settings_for(data_provider: &str) -> Settings {
+        fixture_server::start();
+        Settings {
+            steam_id: Some(TEST_STEAM_ID.to_string()),
+            data_provider: data_provider.to_string(),
+            stratz_api_key: Some("test-key".to_string()),
+            ..Settings::default()
+        }
+    }
+
+    fn test_db() -> rusqlite::Connection {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        database::run_migrations(&mut conn, database::MIGRATIONS).unwrap();
+        conn
+    }
+
+    fn add_goal(conn: &rusqlite::Connection, metric: GoalMetric, target_value: i32, target_time_minutes: i32) {
+        insert_goal(conn, &NewGoal {
+            hero_id: None,
+            hero_scope: None,
+            metric,
+            target_value,
+            target_time_minutes,
+            item_id: None,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            conditions: Vec::new(),
+            combinator: database::GoalCombinator::And,
+            scope: database::GoalScope::default(),
+        }).unwrap();
+    }
+
+    fn actual_value(evaluations: &[GoalEvaluation], metric: GoalMetric) -> i32 {
+        evaluations
+            .iter()
+            .find(|e| e.goal.metric == metric)
+            .map(|e| e.actual_value)
+            .unwrap_or_else(|| panic!("no evaluation for {:?}", metric))
+    }
+
+    fn stored_match(conn: &rusqlite::Connection, match_id: i64) -> database::Match {
+        database::get_all_matches(conn)
+            .unwrap()
+            .into_iter()
+            .find(|m| m.match_id == match_id)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn opendota_fetch_parse_and_evaluate_goals() {
+        let provider = provider::from_settings(&settings_for("opendota"));
+        let conn = test_db();
+
+        let matches = provider.fetch_recent_matches(TEST_STEAM_ID, 20).await.unwrap();
+        assert_eq!(store_new_matches(&conn, matches).unwrap(), 2);
+
+        add_goal(&conn, GoalMetric::LastHits, 60, 10);
+        add_goal(&conn, GoalMetric::Kills, 2, 10);
+        add_goal(&conn, GoalMetric::Deaths, 1, 10);
+        add_goal(&conn, GoalMetric::LaneNetworthLead, 500, 10);
+
+        let match_id = 7900000001;
+        assert_eq!(provider.request_parse(match_id).await.unwrap(), Some(424242));
+        let details = provider.fetch_match_details(match_id).await.unwrap();
+        assert!(store_match_details(&conn, match_id, &details, TEST_ACCOUNT_ID, provider.id()).unwrap());
+
+        let m = stored_match(&conn, match_id);
+        assert_eq!(m.parse_state, MatchState::Parsed);
+        assert_eq!(m.role, 1);
+        assert_eq!(m.parsed_by.as_deref(), Some("opendota"));
+
+        let timings = database::get_item_timings_for_match(&conn, match_id).unwrap();
+        assert!(timings.iter().any(|t| t.item_id == 63 && t.timing_seconds == 305));
+
+        // All players are kept, not just ours
+        let players = database::get_match_players(&conn, match_id).unwrap();
+        assert_eq!(players.len(), 4);
+        let enemy = players.iter().find(|p| p.player_slot == 128).unwrap();
+        assert_eq!((enemy.hero_id, enemy.lane_role, enemy.kills), (2, 3, Some(5)));
+        let timeline = database::get_match_player_timeline(&conn, match_id, 128).unwrap();
+        assert_eq!(timeline[10].last_hits, Some(25));
+        let items = database::get_match_player_items(&conn, match_id).unwrap();
+        assert!(items.iter().any(|i| i.player_slot == 128 && i.item_id == 1 && i.timing_seconds == 600));
+
+        let evaluations = evaluate_match_goals(&conn, &m).unwrap();
+        assert_eq!(actual_value(&evaluations, GoalMetric::LastHits), 68);
+        assert_eq!(actual_value(&evaluations, GoalMetric::Kills), 1);
+        // Deaths are rebuilt from the enemy heroes' kill logs...
+        assert_eq!(actual_value(&evaluations, GoalMetric::Deaths), 1);
+        // ...but only when those account for every death (Crystal Maiden died 6 times, 2 to heroes)
+        let cm = details.players.iter().find(|p| p.player_slot == 4).unwrap();
+        assert_eq!((cm.deaths, cm.death_times.as_ref()), (Some(6), None));
+        assert!(cm.kill_times.is_some());
+        // Our safe lane (carry + Crystal Maiden) against Axe and Tusk in the off lane
+        assert_eq!(actual_value(&evaluations, GoalMetric::LaneNetworthLead), 1000);
+
+        let lane = database::get_lane_outcome(&conn, match_id).unwrap().unwrap();
+        assert_eq!(lane.ally_slots, [0, 4]);
+        assert_eq!((lane.opponent_slots.as_slice(), lane.opponent_hero_ids.as_slice()), (&[128, 132][..], &[2, 26][..]));
+        let diffs: Vec<_> = lane
+            .snapshots
+            .iter()
+            .map(|s| (s.minute, s.last_hits_diff, s.denies_diff, s.networth_diff))
+            .collect();
+        assert_eq!(diffs, vec![(5, 15, 4, 520), (10, 43, 6, 1000)]);
+    }
+
+    #[tokio::test]
+    async fn opendota_unparsed_match_is_reported() {
+        let provider = provider::from_settings(&settings_for("opendota"));
+
+        let err = provider.fetch_match_details(7900000002).await.unwrap_err();
+        assert!(matches!(err, DotaKeeperError::NotParsedYet(_)), "unexpected error: {:?}", err);
+        assert!(err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn stratz_fetch_parse_and_evaluate_goals() {
+        let provider = provider::from_settings(&settings_for("stratz"));
+        let conn = test_db();
+
+        let matches = provider.fetch_recent_matches(TEST_STEAM_ID, 20).await.unwrap();
+        assert_eq!(store_new_matches(&conn, matches).unwrap(), 1);
+
+        add_goal(&conn, GoalMetric::LastHits, 60, 10);
+        add_goal(&conn, GoalMetric::Kills, 2, 10);
+        add_goal(&conn, GoalMetric::Deaths, 1, 10);
+
+        let match_id = 7900000003;
+        assert_eq!(provider.request_parse(match_id).await.unwrap(), None);
+        let details = provider.fetch_match_details(match_id).await.unwrap();
+        assert!(store_match_details(&conn, match_id, &details, TEST_ACCOUNT_ID, provider.id()).unwrap());
+
+        let m = stored_match(&conn, match_id);
+        assert_eq!(m.parse_state, MatchState::Parsed);
+        assert_eq!(m.role, 2);
+        let players = database::get_match_players(&conn, match_id).unwrap();
+        assert_eq!(players.len(), 2);
+        assert_eq!(players[1].kills, Some(9));
+
+        let timings = database::get_item_timings_for_match(&conn, match_id).unwrap();
+        assert!(timings.iter().any(|t| t.item_id == 145 && t.timing_seconds == 1010));
+
+        let evaluations = evaluate_match_goals(&conn, &m).unwrap();
+        assert_eq!(actual_value(&evaluations, GoalMetric::LastHits), 69);
+        assert_eq!(actual_value(&evaluations, GoalMetric::Kills), 3);
+        assert_eq!(actual_value(&evaluations, GoalMetric::Deaths), 0);
+    }
+
+    #[tokio::test]
+    async fn unavailable_provider_falls_back_when_both_keys_are_set() {
+        let conn = test_db();
+        let stratz = provider::from_settings(&settings_for("stratz"));
+        store_new_matches(&conn, stratz.fetch_recent_matches(TEST_STEAM_ID, 20).await.unwrap()).unwrap();
+
+        // OpenDota answers 429 to the parse request and 503 to the details fetch for this match
+        let provider = provider::from_settings(&Settings {
+            opendota_api_key: Some("test-key".to_string()),
+            ..settings_for("opendota")
+        });
+        let match_id = 7900000003;
+        assert_eq!(provider.request_parse(match_id).await.unwrap(), None);
+        let details = provider.fetch_match_details(match_id).await.unwrap();
+        assert!(store_match_details(&conn, match_id, &details, TEST_ACCOUNT_ID, provider.id()).unwrap());
+
+        assert_eq!(stored_match(&conn, match_id).parsed_by.as_deref(), Some("stratz"));
+    }
+
+    #[tokio::test]
+    async fn unavailable_provider_without_fallback_reports_error() {
+        let provider = provider::from_settings(&settings_for("opendota"));
+
+        let err = provider.fetch_match_details(7900000003).await.unwrap_err();
+        assert_eq!(err, DotaKeeperError::ProviderUnavailable { provider: "OpenDota" });
+        assert!(err.is_unavailable());
+        assert_eq!(provider.id(), "opendota");
+
+        // What the frontend receives from a failed command
+        let json = serde_json::to_value(&err).unwrap();
+        assert_eq!(json["code"], "provider_unavailable");
+        assert_eq!(json["message"], err.to_string());
+        assert_eq!(json["retryable"], true);
+    }
+
+    #[tokio::test]
+    async fn stratz_unknown_match_is_reported() {
+        let provider = provider::from_settings(&settings_for("stratz"));
+
+        assert!(provider.fetch_match_details(7900000099).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn backup_round_trip_merges_and_replaces() {
+        let provider = provider::from_settings(&settings_for("opendota"));
+        let source = test_db();
+        store_new_matches(&source, provider.fetch_recent_matches(TEST_STEAM_ID, 20).await.unwrap()).unwrap();
+        let details = provider.fetch_match_details(7900000001).await.unwrap();
+        assert!(store_match_details(&source, 7900000001, &details, TEST_ACCOUNT_ID, provider.id()).unwrap());
+        add_goal(&source, GoalMetric::LastHits, 60, 10);
+        database::set_db_account(&source, TEST_ACCOUNT_ID).unwrap();
+
+        let archive = std::env::temp_dir().join(format!("dota_keeper_test_{}.dkbackup", uuid::Uuid::new_v4()));
+        let settings = Settings { opendota_api_key: Some("secret".to_string()), ..settings_for("opendota") };
+        backup::export_archive(&source, &settings, false, &archive).unwrap();
+        let (manifest, _) = backup::read_archive(&archive).unwrap();
+        assert_eq!(manifest.settings.opendota_api_key, None);
+        assert_eq!(manifest.schema_version, database::get_schema_version(&source).unwrap());
+
+        // Merging twice adds everything once
+        let mut target = test_db();
+        let (_, added) = backup::import_archive(&mut target, TEST_ACCOUNT_ID, &archive, backup::ImportMode::Merge).unwrap();
+        assert_eq!(added, 2);
+        let (_, added) = backup::import_archive(&mut target, TEST_ACCOUNT_ID, &archive, backup::ImportMode::Merge).unwrap();
+        assert_eq!(added, 0);
+        assert_eq!(database::get_all_goals(&target).unwrap().len(), 1);
+        assert_eq!(database::get_match_players(&target, 7900000001).unwrap().len(), 4);
+
+        // A goal edited since the backup keeps its local version instead of coming back twice
+        let mut goal = database::get_all_goals(&target).unwrap().remove(0);
+        goal.target_value = 70;
+        update_goal(&target, &goal).unwrap();
+        backup::import_archive(&mut target, TEST_ACCOUNT_ID, &archive, backup::ImportMode::Merge).unwrap();
+        let goals = database::get_all_goals(&target).unwrap();
+        assert_eq!(goals.iter().map(|g| g.target_value).collect::<Vec<_>>(), [70]);
+        assert_eq!(stored_match(&target, 7900000001).parse_state, MatchState::Parsed);
+
+        // Replace drops local-only data
+        add_goal(&target, GoalMetric::Kills, 5, 10);
+        let (_, restored) = backup::import_archive(&mut target, TEST_ACCOUNT_ID, &archive, backup::ImportMode::Replace).unwrap();
+        assert_eq!(restored, 2);
+        assert_eq!(database::get_all_goals(&target).unwrap().len(), 1);
+
+        // Another account's backup is refused
+        assert!(backup::import_archive(&mut target, TEST_ACCOUNT_ID + 1, &archive, backup::ImportMode::Merge).is_err());
+        let _ = std::fs::remove_file(&archive);
+    }
+
+    #[tokio::test]
+    async fn export_matches_as_csv_and_ndjson() {
+        let provider = provider::from_settings(&settings_for("opendota"));
+        let conn = test_db();
+        store_new_matches(&conn, provider.fetch_recent_matches(TEST_STEAM_ID, 20).await.unwrap()).unwrap();
+        let details = provider.fetch_match_details(7900000001).await.unwrap();
+        assert!(store_match_details(&conn, 7900000001, &details, TEST_ACCOUNT_ID, provider.id()).unwrap());
+        add_goal(&conn, GoalMetric::LastHits, 60, 10);
+        let goal_id = database::get_all_goals(&conn).unwrap()[0].id;
+
+        let dir = std::env::temp_dir();
+        let ndjson = dir.join(format!("dota_keeper_test_{}.ndjson", uuid::Uuid::new_v4()));
+        let parsed_only = export::MatchExportFilters { parsed_only: true, ..Default::default() };
+        assert_eq!(export::export_matches(&conn, export::ExportFormat::Ndjson, &parsed_only, &ndjson).unwrap(), 1);
+        let line: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&ndjson).unwrap()).unwrap();
+        assert_eq!(line["match_id"], 7900000001i64);
+        assert_eq!(line["goal_evaluations"][0]["actual_value"], 68);
+        assert_eq!(line["cs"][10]["last_hits"], 68);
+        assert!(line["item_timings"].as_array().unwrap().iter().any(|t| t["item_id"] == 63));
+
+        let csv = dir.join(format!("dota_keeper_test_{}.csv", uuid::Uuid::new_v4()));
+        assert_eq!(export::export_matches(&conn, export::ExportFormat::Csv, &Default::default(), &csv).unwrap(), 2);
+        let contents = std::fs::read_to_string(&csv).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let header: Vec<&str> = lines[0].split(',').collect();
+        let value_col = header.iter().position(|c| *c == format!("goal_{}_value", goal_id)).unwrap();
+        let parsed_row = lines.iter().find(|l| l.starts_with("7900000001,")).unwrap();
+        assert_eq!(parsed_row.split(',').nth(value_col), Some("68"));
+
+        let _ = std::fs::remove_file(&ndjson);
+        let _ = std::fs::remove_file(&csv);
+    }
+
+    fn gsi_fixture() -> Vec<String> {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/gsi/ranked_antimage.ndjson");
+        std::fs::read_to_string(path).unwrap().lines().map(String::from).collect()
+    }
+
+    fn live_goal(metric: GoalMetric, target_value: i32, target_time_minutes: i32, item_id: Option<i32>) -> database::Goal {
+        database::Goal {
+            id: 1,
+            hero_id: None,
+            hero_scope: None,
+            metric,
+            target_value,
+            target_time_minutes,
+            item_id,
+            game_mode: GoalGameMode::All,
+            frequency_type: FrequencyType::Pct75,
+            created_at: 0,
+            conditions: Vec::new(),
+            combinator: database::GoalCombinator::And,
+            scope: database::GoalScope::default(),
+            state: database::GoalState::Active,
+            state_changed_at: None,
+            achieved_at: None,
+        }
+    }
+
+    #[test]
+    fn gsi_replay_tracks_game_and_evaluates_goals_live() {
+        use gsi::LiveGoalStatus;
+
+        let lines = gsi_fixture();
+        assert!(gsi::parse_payload(&lines[0], "wrong-token").is_err());
+        let payloads: Vec<gsi::GsiPayload> = lines
+            .iter()
+            .map(|l| gsi::parse_payload(l, "test-token").unwrap())
+            .collect();
+
+        let cs = live_goal(GoalMetric::LastHits, 60, 10, None);
+        let deaths = live_goal(GoalMetric::Deaths, 2, 10, None);
+        let level = live_goal(GoalMetric::Level, 10, 10, None);
+        let treads = live_goal(GoalMetric::ItemTiming, 540, 0, Some(63));
+        let other_hero = database::Goal { hero_id: Some(2), ..cs.clone() };
+        let goals = [cs, deaths, level, treads, other_hero];
+
+        // Five minutes in: behind on CS pace, treads not bought yet
+        let mut tracker = gsi::LiveTracker::default();
+        for p in &payloads[..7] {
+            tracker.apply(p);
+        }
+        let game = tracker.game().unwrap();
+        assert_eq!((game.match_id, game.hero_id, game.clock_time), (7950000001, Some(1), 300));
+        assert_eq!((game.last_hits, game.denies, game.net_worth), (24, 3, 2000));
+        assert_eq!(game.items, ["quelling_blade", "magic_wand", "slippers", "boots"]);
+        let progress = gsi::evaluate_live_goals(game, &goals);
+        assert_eq!(progress.len(), 4);
+        assert_eq!(progress[0].status, LiveGoalStatus::Behind);
+        assert_eq!(progress[0].message, "You're 6 CS behind your 10-minute goal (24 / 60)");
+        assert_eq!(progress[1].status, LiveGoalStatus::OnTrack);
+        assert_eq!(progress[3].status, LiveGoalStatus::OnTrack);
+
+        // Rest of the game: goals settle on the stats at the 10-minute mark
+        for p in &payloads[7..] {
+            tracker.apply(p);
+        }
+        let game = tracker.game().unwrap();
+        assert_eq!(game.win_team.as_deref(), Some("radiant"));
+        assert_eq!(game.minutes.iter().map(|s| s.minute).collect::<Vec<_>>(), (0..=10).chain([30]).collect::<Vec<_>>());
+        assert_eq!(game.stats_at_minute(10).unwrap().last_hits, 55);
+        let bought = |item: &str| game.purchases.iter().find(|p| p.item == item).map(|p| p.clock_time);
+        assert_eq!(bought("quelling_blade"), Some(-75));
+        assert_eq!(bought("boots"), Some(240)); // seen in the stash first
+        assert_eq!(bought("power_treads"), Some(480));
+        let progress = gsi::evaluate_live_goals(game, &goals);
+        let statuses: Vec<_> = progress.iter().map(|p| p.status).collect();
+        assert_eq!(
+            statuses,
+            [LiveGoalStatus::Missed, LiveGoalStatus::Achieved, LiveGoalStatus::Achieved, LiveGoalStatus::Achieved]
+        );
+        assert_eq!(progress[0].message, "10-minute CS goal missed (55 / 60)");
+
+        // Joining mid-game: items already held have no timing
+        let mut late = gsi::LiveTracker::default();
+        let game = late.apply(&payloads[6]).unwrap();
+        assert!(game.purchases.is_empty());
+        assert!(game.untimed_items.contains(&"boots".to_string()));
+        assert!(gsi::evaluate_live_goal(game, &live_goal(GoalMetric::ItemTiming, 300, 0, Some(29))).is_none());
+    }
+
+    #[tokio::test]
+    async fn gsi_listener_rejects_overlong_header_lines() {
+        use tokio::io::AsyncWriteExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let send = |request: Vec<u8>| async move {
+            let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let _ = client.write_all(&request).await;
+            client
+        };
+
+        let _client = send(b"POST / HTTP/1.1\r\nContent-Length: 2\r\n\r\n{}".to_vec()).await;
+        let (stream, _) = listener.accept().await.unwrap();
+        assert_eq!(gsi::read_request_body(stream).await.as_deref(), Some("{}"));
+
+        let long_header = [b"POST / HTTP/1.1\r\nX-Padding: ".as_slice(), &[b'a'; 16 * 1024]].concat();
+        let _client = send(long_header).await;
+        let (stream, _) = listener.accept().await.unwrap();
+        assert_eq!(gsi::read_request_body(stream).await, None);
+    }
+
+    #[test]
+    fn gsi_recording_fills_per_minute_data_and_marks_match_parsed() {
+        let conn = test_db();
+        database::set_db_account(&conn, TEST_ACCOUNT_ID).unwrap();
+        let mut tracker = gsi::LiveTracker::default();
+        for line in gsi_fixture() {
+            tracker.apply(&gsi::parse_payload(&line, "test-token").unwrap());
+        }
+        let game = tracker.take_finished().unwrap();
+        assert!(tracker.take_finished().is_none());
+        assert_eq!(game.death_times, [240, 541, 1850]);
+
+        // Recorded before the match shows up in the match list: kept for later
+        let match_id = game.match_id;
+        let data = serde_json::to_string(&game).unwrap();
+        database::save_gsi_recording(&conn, match_id, TEST_ACCOUNT_ID, &data).unwrap();
+        assert!(apply_gsi_recordings(&conn, TEST_ACCOUNT_ID).unwrap().is_empty());
+        assert_eq!(database::get_gsi_recordings(&conn).unwrap().len(), 1);
+
+        insert_match(&conn, &database::Match {
+            match_id,
+            hero_id: 1,
+            start_time: 1760000000,
+            duration: 1850,
+            game_mode: 22,
+            lobby_type: 7,
+            radiant_win: true,
+            player_slot: 0,
+            kills: 9,
+            deaths: 3,
+            assists: 7,
+            xp_per_min: 0,
+            gold_per_min: 0,
+            last_hits: 0,
+            denies: 0,
+            hero_damage: 0,
+            tower_damage: 0,
+            hero_healing: 0,
+            parse_state: MatchState::Unparsed,
+            role: 2,
+            rank_tier: None,
+            patch: None,
+            parsed_by: None,
+        }).unwrap();
+        assert_eq!(apply_gsi_recordings(&conn, TEST_ACCOUNT_ID).unwrap(), [match_id]);
+        assert!(database::get_gsi_recordings(&conn).unwrap().is_empty());
+
+        let m = stored_match(&conn, match_id);
+        assert_eq!(m.parse_state, MatchState::Parsed);
+        assert_eq!(m.parsed_by.as_deref(), Some("gsi"));
+        assert_eq!(m.last_hits, 280);
+        // GSI doesn't report lanes; the role from the match list is kept
+        assert_eq!(m.role, 2);
+        assert_eq!(database::get_match_players(&conn, match_id).unwrap()[0].lane_role, 2);
+        // Only the gap-free run of minutes from the horn is kept (0-10 here)
+        let cs = database::get_match_cs_data(&conn, match_id).unwrap();
+        assert_eq!(cs.len(), 11);
+        assert_eq!((cs[5].last_hits, cs[5].denies), (24, 3));
+        assert_eq!(database::get_match_xp_at_minute(&conn, match_id, 10).unwrap(), Some(4950));
+        let timings = database::get_item_timings_for_match(&conn, match_id).unwrap();
+        assert!(timings.iter().any(|t| t.item_id == 63 && t.timing_seconds == 480));
+
+        add_goal(&conn, GoalMetric::LastHits, 50, 10);
+        add_goal(&conn, GoalMetric::Networth, 3500, 10);
+        add_goal(&conn, GoalMetric::Deaths, 1, 10);
+        let evaluations = evaluate_match_goals(&conn, &m).unwrap();
+        assert_eq!(actual_value(&evaluations, GoalMetric::LastHits), 55);
+        assert_eq!(actual_value(&evaluations, GoalMetric::Networth), 3890);
+        assert_eq!(actual_value(&evaluations, GoalMetric::Deaths), 2);
+    }
+
+    #[test]
+    fn replay_import_is_all_or_nothing() {
+        let conn = test_db();
+        database::set_db_account(&conn, TEST_ACCOUNT_ID).unwrap();
+        let mut replay = replay::Replay {
+            match_id: 8100000001,
+            game_mode: 22,
+            radiant_win: true,
+            start_time: 1760000000,
+            duration: 600,
+            players: vec![replay::ReplayPlayer {
+                player_slot: 0,
+                account_id: Some(TEST_ACCOUNT_ID),
+                hero_id: Some(1),
+                ..Default::default()
+            }],
+        };
+
+        // No per-minute data: rejected without leaving an unparsed match behind
+        assert!(matches!(store_replay(&conn, &replay, TEST_ACCOUNT_ID), Err(DotaKeeperError::InvalidInput(_))));
+        assert!(database::get_all_matches(&conn).unwrap().is_empty());
+
+        replay.players[0].lh_t = (0..=10).map(|m| m * 5).collect();
+        replay.players[0].dn_t = vec![0; 11];
+        store_replay(&conn, &replay, TEST_ACCOUNT_ID).unwrap();
+        let m = stored_match(&conn, replay.match_id);
+        assert_eq!(m.parse_state, MatchState::Parsed);
+        assert_eq!(m.parsed_by.as_deref(), Some("replay"));
+        assert_eq!(database::get_match_cs_data(&conn, replay.match_id).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn http_limiter_spaces_requests_and_honours_retry_after() {
+        use std::time::{Duration, Instant};
+
+        // 60/min with a burst of 2: two requests go straight out, the third waits a second
+        let start = Instant::now();
+        let mut bucket = http::TokenBucket::new(60, 2, start);
+        assert!(bucket.take(start).is_ok());
+        assert!(bucket.take(start).is_ok());
+        let wait = bucket.take(start).unwrap_err();
+        assert!(wait > Duration::from_millis(990) && wait <= Duration::from_secs(1));
+        assert!(bucket.take(start + Duration::from_millis(500)).is_err());
+        assert!(bucket.take(start + Duration::from_secs(1)).is_ok());
+        // Idle time refills up to the burst, never past it
+        let later = start + Duration::from_secs(600);
+        assert!(bucket.take(later).is_ok());
+        assert!(bucket.take(later).is_ok());
+        assert!(bucket.take(later).is_err());
+
+        let now = chrono::DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&chrono::Utc);
+        assert_eq!(http::parse_retry_after("30", now), Some(Duration::from_secs(30)));
+        assert_eq!(http::parse_retry_after("Thu, 01 Jan 2026 00:01:30 GMT", now), Some(Duration::from_secs(90)));
+        assert_eq!(http::parse_retry_after("Wed, 31 Dec 2025 23:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(http::parse_retry_after("soon", now), None);
+        assert_eq!(http::parse_retry_after("99999999999999999999", now), None);
+        assert_eq!(http::parse_retry_after("18446744073709551615", now), Some(Duration::from_secs(3600)));
+        assert_eq!(http::parse_retry_after("Fri, 01 Jan 2100 00:00:00 GMT", now), Some(Duration::from_secs(3600)));
+    }
+}
@@ -1,26 +1,34 @@
 use crate::database::{Match, MatchState, PatchInfo};
+use crate::error::DotaKeeperError;
+use crate::http::{self, ApiHost, HttpError};
 use serde::Deserialize;
+use std::sync::OnceLock;
 use tracing::debug;
 
 const OPENDOTA_API_BASE: &str = "https://api.opendota.com/api";
 
-// ── User-friendly error helpers ─────────────────────────────────────────────
+/// Optional replacement for `OPENDOTA_API_BASE`, e.g. a local fixture server.
+static API_BASE_OVERRIDE: OnceLock<String> = OnceLock::new();
 
-fn friendly_network_err(err: &reqwest::Error) -> String {
-    if err.is_connect() || err.is_timeout() {
-        "Couldn't reach OpenDota — check your internet connection. Matches will sync automatically when you're back online.".to_string()
-    } else {
-        "Network error while contacting OpenDota. Will retry automatically.".to_string()
-    }
+/// Point every OpenDota request at a different server. Called at most once, before any request.
+pub fn set_api_base(url: String) {
+    let _ = API_BASE_OVERRIDE.set(url);
+}
+
+fn api_base() -> &'static str {
+    API_BASE_OVERRIDE.get().map(String::as_str).unwrap_or(OPENDOTA_API_BASE)
 }
 
-fn friendly_status_err(status: reqwest::StatusCode) -> String {
-    match status.as_u16() {
-        429 => "Too many requests to OpenDota. Will try again shortly.".to_string(),
-        404 => "No data found for this request. The match may not be tracked by OpenDota yet.".to_string(),
-        500..=599 => "OpenDota is unavailable right now. Your matches will sync automatically when it comes back.".to_string(),
-        code => format!("OpenDota returned an unexpected error (HTTP {}). Will retry later.", code),
+// ── Error helpers ─────────────────────────────────────────────
+
+const PROVIDER: &str = "OpenDota";
+
+/// Report an HTTP error status to Sentry and turn the failure into a `DotaKeeperError`.
+fn api_err(endpoint: &str, err: HttpError) -> DotaKeeperError {
+    if let Some(status) = err.status() {
+        log_api_error(endpoint, status);
     }
+    DotaKeeperError::from_http(PROVIDER, err)
 }
 
 /// Send an OpenDota API error to Sentry for monitoring.
@@ -95,6 +103,7 @@ impl From<OpenDotaMatch> for Match {
             role: 0,
             rank_tier: m.rank_tier,
             patch: None, // assigned after insert using patch lookup
+            parsed_by: None,
         }
     }
 }
@@ -110,13 +119,23 @@ pub struct DetailedMatch {
 pub struct DetailedPlayer {
     pub account_id: Option<u32>,
     pub player_slot: i32,
+    pub hero_id: Option<i32>,
     pub lane_role: Option<i32>,  // 1=carry, 2=mid, 3=offlane, 4=soft support, 5=hard support
     pub lh_t: Option<Vec<i32>>,  // Last hits at each minute
     pub dn_t: Option<Vec<i32>>,  // Denies at each minute
     pub gold_t: Option<Vec<i32>>,  // Gold (net worth) at each minute — OpenDota field name
     pub xp_t: Option<Vec<i32>>,  // XP at each minute
     pub purchase_log: Option<Vec<PurchaseLogEntry>>,  // Item purchases
+    pub kills_log: Option<Vec<KillLogEntry>>,  // Hero kills (OpenDota parsed matches only)
+    // Game times (seconds) of this player's hero kills and deaths, filled in after fetching
+    #[serde(skip)]
+    pub kill_times: Option<Vec<i32>>,
+    #[serde(skip)]
+    pub death_times: Option<Vec<i32>>,
     // End-of-game stats (may be absent from match history, populated from detailed fetch)
+    pub kills: Option<i32>,
+    pub deaths: Option<i32>,
+    pub assists: Option<i32>,
     pub xp_per_min: Option<i32>,
     pub gold_per_min: Option<i32>,
     pub last_hits: Option<i32>,
@@ -132,6 +151,47 @@ pub struct PurchaseLogEntry {
     pub key: String,  // Item name/key (e.g., "blink", "armlet")
 }
 
+#[derive(Debug, Deserialize)]
+pub struct KillLogEntry {
+    pub time: i32,  // Game time in seconds of the kill
+    pub key: String,  // Victim unit name (e.g., "npc_dota_hero_axe")
+}
+
+/// Fill in `kill_times` / `death_times` for every player from the parsed kill logs.
+/// OpenDota has no per-player death log, so deaths are rebuilt from the enemy team's
+/// kill logs. That misses deaths to creeps, towers, neutrals, Roshan and denies, so the
+/// rebuilt times are only kept when they account for every one of the player's deaths;
+/// otherwise `death_times` stays unknown rather than undercounting.
+fn fill_kill_death_times(match_details: &mut DetailedMatch) {
+    // (killer is radiant, victim hero id, time)
+    let hero_kills: Vec<(bool, i32, i32)> = match_details
+        .players
+        .iter()
+        .flat_map(|p| {
+            p.kills_log.iter().flatten().filter_map(move |k| {
+                let victim = crate::hero_name_to_id(k.key.strip_prefix("npc_dota_hero_")?)?;
+                Some((p.player_slot < 128, victim, k.time))
+            })
+        })
+        .collect();
+
+    for p in &mut match_details.players {
+        // Without a kill log the match hasn't been parsed; leave the timelines unknown.
+        let Some(kills_log) = &p.kills_log else { continue };
+        p.kill_times = Some(kills_log.iter().map(|k| k.time).collect());
+
+        let is_radiant = p.player_slot < 128;
+        let death_times: Option<Vec<i32>> = p.hero_id.map(|hero_id| {
+            hero_kills
+                .iter()
+                .filter(|&&(killer_radiant, victim, _)| killer_radiant != is_radiant && victim == hero_id)
+                .map(|&(_, _, time)| time)
+                .collect()
+        });
+        p.death_times = death_times.filter(|times| p.deaths == Some(times.len() as i32));
+    }
+}
+
 /// Find the lane partner for a support player (pos 4 or 5).
 /// Pos 5 (hard support) lanes with pos 1 (carry) in the safe lane.
 /// Pos 4 (soft support) lanes with pos 3 (offlaner) in the off lane.
@@ -168,10 +228,10 @@ pub struct JobInfo {
 }
 
 /// Convert Steam ID64 to Steam ID32 (account ID) for OpenDota API
-fn steam_id64_to_id32(steam_id64: &str) -> Result<u32, String> {
-    let id64: u64 = steam_id64
-        .parse()
-        .map_err(|_| "No matches found for this Steam ID. Double-check the ID in Settings.".to_string())?;
+fn steam_id64_to_id32(steam_id64: &str) -> Result<u32, DotaKeeperError> {
+    let id64: u64 = steam_id64.parse().map_err(|_| {
+        DotaKeeperError::InvalidInput("No matches found for this Steam ID. Double-check the ID in Settings.".to_string())
+    })?;
 
     // Steam ID32 = Steam ID64 - 76561197960265728
     const STEAM_ID64_BASE: u64 = 76561197960265728;
@@ -185,12 +245,12 @@ fn steam_id64_to_id32(steam_id64: &str) -> Result<u32, String> {
 }
 
 /// Fetch recent matches from OpenDota API
-pub async fn fetch_recent_matches(steam_id: &str, limit: usize, api_key: Option<&str>) -> Result<Vec<Match>, String> {
+pub async fn fetch_recent_matches(steam_id: &str, limit: usize, api_key: Option<&str>) -> Result<Vec<Match>, DotaKeeperError> {
     let account_id = steam_id64_to_id32(steam_id)?;
 
     let mut url = format!(
         "{}/players/{}/recentMatches",
-        OPENDOTA_API_BASE, account_id
+        api_base(), account_id
     );
     if let Some(key) = api_key {
         url.push_str(&format!("?api_key={}", key));
@@ -198,32 +258,23 @@ pub async fn fetch_recent_matches(steam_id: &str, limit: usize, api_key: Option<
 
     debug!("fetch_recent_matches GET {} limit={}", url, limit);
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| {
-            let msg = friendly_network_err(&e);
-            debug!("fetch_recent_matches network error: {}", msg);
-            msg
-        })?;
-
-    let status = response.status();
-    debug!("fetch_recent_matches response status={}", status.as_u16());
-
-    if !status.is_success() {
-        if status.as_u16() == 404 {
-            return Err("No matches found for this Steam ID. Double-check the ID in Settings.".to_string());
+    let response = match http::send(ApiHost::opendota(api_key), http::client().get(&url)).await {
+        Ok(response) => response,
+        Err(HttpError::Status(404)) => {
+            return Err(DotaKeeperError::NotFound(
+                "No matches found for this Steam ID. Double-check the ID in Settings.".to_string(),
+            ));
         }
-        log_api_error("recentMatches", status.as_u16());
-        return Err(friendly_status_err(status));
-    }
+        Err(e) => {
+            debug!("fetch_recent_matches failed: {}", e);
+            return Err(api_err("recentMatches", e));
+        }
+    };
 
     let matches: Vec<OpenDotaMatch> = response
         .json()
         .await
-        .map_err(|_| "OpenDota returned unexpected data. Check that your Steam ID is correct in Settings.".to_string())?;
+        .map_err(|_| DotaKeeperError::Api("OpenDota returned unexpected data. Check that your Steam ID is correct in Settings.".to_string()))?;
 
     // Take only the requested number of matches
     let matches: Vec<Match> = matches
@@ -242,9 +293,8 @@ pub async fn fetch_matches_before(
     before_timestamp: i64,
     limit: usize,
     api_key: Option<&str>,
-) -> Result<Vec<Match>, String> {
+) -> Result<Vec<Match>, DotaKeeperError> {
     let account_id = steam_id64_to_id32(steam_id)?;
-    let client = reqwest::Client::new();
 
     debug!("fetch_matches_before: looking for {} matches before timestamp {}", limit, before_timestamp);
 
@@ -257,7 +307,7 @@ pub async fn fetch_matches_before(
     while all_matches.len() < limit && attempts < MAX_ATTEMPTS {
         let mut url = format!(
             "{}/players/{}/matches?limit={}&offset={}&significant=0",
-            OPENDOTA_API_BASE, account_id, BATCH_SIZE, offset
+            api_base(), account_id, BATCH_SIZE, offset
         );
         if let Some(key) = api_key {
             url.push_str(&format!("&api_key={}", key));
@@ -265,28 +315,17 @@ pub async fn fetch_matches_before(
 
         debug!("fetch_matches_before GET {} offset={} collected={}", url, offset, all_matches.len());
 
-        let response = client
-            .get(&url)
-            .send()
+        let response = http::send(ApiHost::opendota(api_key), http::client().get(&url))
             .await
             .map_err(|e| {
-                let msg = friendly_network_err(&e);
-                debug!("fetch_matches_before network error at offset {}: {}", offset, msg);
-                msg
+                debug!("fetch_matches_before failed at offset {}: {}", offset, e);
+                api_err("matchesBackfill", e)
             })?;
 
-        let status = response.status();
-        debug!("fetch_matches_before response status={}", status.as_u16());
-
-        if !status.is_success() {
-            log_api_error("matchesBackfill", status.as_u16());
-            return Err(friendly_status_err(status));
-        }
-
         let matches: Vec<OpenDotaMatch> = response
             .json()
             .await
-            .map_err(|_| "OpenDota returned unexpected data while backfilling. Backfill may be incomplete.".to_string())?;
+            .map_err(|_| DotaKeeperError::Api("OpenDota returned unexpected data while backfilling. Backfill may be incomplete.".to_string()))?;
 
         debug!("fetch_matches_before received {} matches from API", matches.len());
 
@@ -311,9 +350,6 @@ pub async fn fetch_matches_before(
 
         offset += BATCH_SIZE;
         attempts += 1;
-
-        // Pause between pagination requests to avoid rate limiting.
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
     }
 
     // Sort by start_time descending (most recent first)
@@ -327,36 +363,87 @@ pub async fn fetch_matches_before(
     Ok(all_matches)
 }
 
+/// One page of the player's match history, newest first: `limit` matches after skipping
+/// the `offset` most recent ones.
+pub async fn fetch_match_history_page(
+    steam_id: &str,
+    offset: usize,
+    limit: usize,
+    api_key: Option<&str>,
+) -> Result<Vec<Match>, DotaKeeperError> {
+    let account_id = steam_id64_to_id32(steam_id)?;
+    let mut url = format!(
+        "{}/players/{}/matches?limit={}&offset={}&significant=0",
+        api_base(), account_id, limit, offset
+    );
+    if let Some(key) = api_key {
+        url.push_str(&format!("&api_key={}", key));
+    }
+    debug!("fetch_match_history_page GET {}", url);
+
+    let response = http::send(ApiHost::opendota(api_key), http::client().get(&url))
+        .await
+        .map_err(|e| api_err("matchHistoryPage", e))?;
+
+    let matches: Vec<OpenDotaMatch> = response
+        .json()
+        .await
+        .map_err(|_| DotaKeeperError::Api("OpenDota returned unexpected data while importing match history.".to_string()))?;
+    Ok(matches.into_iter().map(Into::into).collect())
+}
+
+/// Response from OpenDota /players/{account_id}/wl
+#[derive(Debug, Deserialize)]
+struct OpenDotaWinLoss {
+    win: usize,
+    lose: usize,
+}
+
+/// How many matches the player has in total (wins plus losses), counted the same way as
+/// `fetch_match_history_page` pages through them.
+pub async fn fetch_match_count(steam_id: &str, api_key: Option<&str>) -> Result<usize, DotaKeeperError> {
+    let account_id = steam_id64_to_id32(steam_id)?;
+    let mut url = format!("{}/players/{}/wl?significant=0", api_base(), account_id);
+    if let Some(key) = api_key {
+        url.push_str(&format!("&api_key={}", key));
+    }
+    debug!("fetch_match_count GET {}", url);
+
+    let response = http::send(ApiHost::opendota(api_key), http::client().get(&url))
+        .await
+        .map_err(|e| api_err("wl", e))?;
+
+    let wl: OpenDotaWinLoss = response
+        .json()
+        .await
+        .map_err(|_| DotaKeeperError::Api("OpenDota returned unexpected data while counting matches.".to_string()))?;
+    Ok(wl.win + wl.lose)
+}
+
 /// Request OpenDota to parse a match.
 /// Returns the job ID if OpenDota queued a parse job, or None if the match
 /// was already parsed (response had no job).
-pub async fn request_match_parse(match_id: i64, api_key: Option<&str>) -> Result<Option<i64>, String> {
-    let mut url = format!("{}/request/{}", OPENDOTA_API_BASE, match_id);
+pub async fn request_match_parse(match_id: i64, api_key: Option<&str>) -> Result<Option<i64>, DotaKeeperError> {
+    let mut url = format!("{}/request/{}", api_base(), match_id);
     if let Some(key) = api_key {
         url.push_str(&format!("?api_key={}", key));
     }
     debug!("request_match_parse POST {}", url);
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(&url)
-        .send()
-        .await
-        .map_err(|e| {
-            let msg = friendly_network_err(&e);
-            debug!("request_match_parse network error: {}", msg);
-            msg
-        })?;
-
-    let status = response.status();
-    debug!("request_match_parse response status={}", status.as_u16());
-
-    if !status.is_success() {
-        log_api_error("parseRequest", status.as_u16());
-        let err = format!("OpenDota couldn't parse this match — it will be skipped and retried later. ({})", friendly_status_err(status));
-        debug!("request_match_parse failed: {}", err);
-        return Err(err);
-    }
+    let response = match http::send(ApiHost::opendota(api_key), http::client().post(&url)).await {
+        Ok(response) => response,
+        Err(e) => {
+            let err = match api_err("parseRequest", e) {
+                err @ (DotaKeeperError::Api(_) | DotaKeeperError::NotFound(_)) => DotaKeeperError::Api(format!(
+                    "OpenDota couldn't parse this match — it will be skipped and retried later. ({})",
+                    err
+                )),
+                err => err,
+            };
+            debug!("request_match_parse failed: {}", err);
+            return Err(err);
+        }
+    };
 
     let parsed: ParseRequestResponse = response
         .json()
@@ -372,8 +459,7 @@ pub async fn request_match_parse(match_id: i64, api_key: Option<&str>) -> Result
 /// Returns true if the job completed cleanly, false if it timed out or errored.
 /// OpenDota returns `null` (or 404) for the job once parsing is done.
 pub async fn wait_for_parse_job(job_id: i64) -> bool {
-    let url = format!("{}/request/{}", OPENDOTA_API_BASE, job_id);
-    let client = reqwest::Client::new();
+    let url = format!("{}/request/{}", api_base(), job_id);
     debug!("wait_for_parse_job polling job_id={} url={}", job_id, url);
 
     // Poll with exponential back-off: 5 s → 10 s → 20 s, up to 12 attempts (~3 min max).
@@ -382,28 +468,26 @@ pub async fn wait_for_parse_job(job_id: i64) -> bool {
         let sleep_secs: u64 = if attempt < 3 { 5 } else if attempt < 6 { 10 } else { 20 };
         tokio::time::sleep(tokio::time::Duration::from_secs(sleep_secs)).await;
 
-        let response = match client.get(&url).send().await {
+        let response = match http::send(ApiHost::OpenDota, http::client().get(&url)).await {
             Ok(r) => r,
-            Err(e) => {
-                debug!("wait_for_parse_job attempt={} network error: {}", attempt, e);
-                break; // network issue — proceed and try fetching details anyway
-            }
-        };
-
-        let status = response.status().as_u16();
-        match status {
             // 404 means the job is gone (done or never existed) — ready to fetch.
-            404 => {
+            Err(HttpError::Status(404)) => {
                 debug!("wait_for_parse_job attempt={} status=404 → done", attempt);
                 return true;
             }
-            s if s >= 500 => {
-                debug!("wait_for_parse_job attempt={} server error {} → give up", attempt, s);
-                break; // server error — give up polling
+            // Rate limited: the limiter holds later requests back, so just poll again.
+            Err(HttpError::RateLimited { .. }) => continue,
+            Err(e @ HttpError::Status(s)) if s < 500 => {
+                debug!("wait_for_parse_job attempt={} {} → keep polling", attempt, e);
+                continue;
             }
-            _ => {}
-        }
+            Err(e) => {
+                debug!("wait_for_parse_job attempt={} {} → give up", attempt, e);
+                break; // network or server error — proceed and try fetching details anyway
+            }
+        };
 
+        let status = response.status().as_u16();
         let body = response.text().await.unwrap_or_default();
         debug!("wait_for_parse_job attempt={} status={} body={:?}", attempt, status, &body[..body.len().min(80)]);
         // OpenDota returns the literal string "null" when the job is finished.
@@ -419,40 +503,32 @@ pub async fn wait_for_parse_job(job_id: i64) -> bool {
 }
 
 /// Fetch detailed match data from OpenDota
-pub async fn fetch_match_details(match_id: i64, api_key: Option<&str>) -> Result<DetailedMatch, String> {
-    let mut url = format!("{}/matches/{}", OPENDOTA_API_BASE, match_id);
+pub async fn fetch_match_details(match_id: i64, api_key: Option<&str>) -> Result<DetailedMatch, DotaKeeperError> {
+    let mut url = format!("{}/matches/{}", api_base(), match_id);
     if let Some(key) = api_key {
         url.push_str(&format!("?api_key={}", key));
     }
 
     debug!("fetch_match_details GET {}", url);
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| {
-            let msg = friendly_network_err(&e);
-            debug!("fetch_match_details network error: {}", msg);
-            msg
-        })?;
-
-    let status = response.status();
-    debug!("fetch_match_details match_id={} response status={}", match_id, status.as_u16());
-
-    if !status.is_success() {
-        if status.as_u16() == 404 {
-            return Err("This match hasn't been parsed by OpenDota yet. It will be picked up on the next sync.".to_string());
+    let response = match http::send(ApiHost::opendota(api_key), http::client().get(&url)).await {
+        Ok(response) => response,
+        Err(HttpError::Status(404)) => {
+            return Err(DotaKeeperError::NotParsedYet(
+                "This match hasn't been parsed by OpenDota yet. It will be picked up on the next sync.".to_string(),
+            ));
         }
-        log_api_error("matchDetails", status.as_u16());
-        return Err(friendly_status_err(status));
-    }
+        Err(e) => {
+            debug!("fetch_match_details match_id={} failed: {}", match_id, e);
+            return Err(api_err("matchDetails", e));
+        }
+    };
 
-    let match_details: DetailedMatch = response
+    let mut match_details: DetailedMatch = response
         .json()
         .await
-        .map_err(|e| format!("Unexpected response from OpenDota for this match ({}). Try again or check https://www.opendota.com/matches/{}", e, match_id))?;
+        .map_err(|e| DotaKeeperError::Api(format!("Unexpected response from OpenDota for this match ({}). Try again or check https://www.opendota.com/matches/{}", e, match_id)))?;
+    fill_kill_death_times(&mut match_details);
 
     debug!("fetch_match_details match_id={} OK players={}", match_id, match_details.players.len());
     Ok(match_details)
@@ -467,31 +543,22 @@ struct OpenDotaPatchEntry {
 
 /// Fetch the list of Dota 2 patches from OpenDota constants.
 /// Returns patches as PatchInfo with Unix epoch timestamps.
-pub async fn fetch_patches(api_key: Option<&str>) -> Result<Vec<PatchInfo>, String> {
-    let mut url = format!("{}/constants/patch", OPENDOTA_API_BASE);
+pub async fn fetch_patches(api_key: Option<&str>) -> Result<Vec<PatchInfo>, DotaKeeperError> {
+    let mut url = format!("{}/constants/patch", api_base());
     if let Some(key) = api_key {
         url.push_str(&format!("?api_key={}", key));
     }
 
     debug!("fetch_patches GET {}", url);
 
-    let client = reqwest::Client::new();
-    let response = client
-        .get(&url)
-        .send()
+    let response = http::send(ApiHost::opendota(api_key), http::client().get(&url))
         .await
-        .map_err(|e| friendly_network_err(&e))?;
-
-    let status = response.status();
-    if !status.is_success() {
-        log_api_error("constants/patch", status.as_u16());
-        return Err(friendly_status_err(status));
-    }
+        .map_err(|e| api_err("constants/patch", e))?;
 
     let entries: Vec<OpenDotaPatchEntry> = response
         .json()
         .await
-        .map_err(|_| "Failed to parse patch data from OpenDota".to_string())?;
+        .map_err(|_| DotaKeeperError::Api("Failed to parse patch data from OpenDota".to_string()))?;
 
     let patches: Vec<PatchInfo> = entries
         .into_iter()
@@ -0,0 +1,336 @@
+//! Match data providers.
+//!
+//! Every flow that talks to an external stats API goes through a
+//! `MatchDataProvider` chosen once from the user's settings, so adding a provider
+//! means adding an implementation here rather than editing each call site.
+//!
+//! When both API keys are configured, the chosen provider is wrapped in a
+//! `ProviderChain` that switches to the other one if the primary is rate-limited
+//! or down.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::warn;
+
+use serde::Serialize;
+
+use crate::database::Match;
+use crate::error::DotaKeeperError;
+use crate::opendota::{self, DetailedMatch};
+use crate::settings::Settings;
+use crate::stratz;
+
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// What a provider can and can't do; callers branch on these rather than on the provider name.
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct ProviderCapabilities {
+    /// Matches must be submitted for parsing before detailed data is available.
+    pub needs_parse_request: bool,
+    /// Match lists include the player's rank tier (used for medal history).
+    pub supports_rank_tier: bool,
+}
+
+pub trait MatchDataProvider: Send + Sync {
+    /// Settings identifier, e.g. "opendota". Stored with each parsed match.
+    fn id(&self) -> &'static str;
+
+    fn capabilities(&self) -> ProviderCapabilities;
+
+    /// Error shown when details come back without per-minute data yet.
+    fn not_ready_message(&self) -> &'static str;
+
+    fn fetch_recent_matches<'a>(
+        &'a self,
+        steam_id: &'a str,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>>;
+
+    fn fetch_matches_before<'a>(
+        &'a self,
+        steam_id: &'a str,
+        before_timestamp: i64,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>>;
+
+    /// One page of the player's full match history, newest first, skipping the `offset`
+    /// most recent matches. A page shorter than `limit` is the last one.
+    fn fetch_match_history_page<'a>(
+        &'a self,
+        steam_id: &'a str,
+        offset: usize,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>>;
+
+    /// Submit a parse job and return its ID, or `None` when no job was queued.
+    /// Providers without `needs_parse_request` return `Ok(None)` immediately.
+    fn request_parse(&self, _match_id: i64) -> ProviderFuture<'_, Result<Option<i64>, DotaKeeperError>> {
+        Box::pin(async { Ok(None) })
+    }
+
+    /// Poll a parse job until it completes. Returns true if it finished cleanly.
+    fn wait_for_parse(&self, _job_id: i64) -> ProviderFuture<'_, bool> {
+        Box::pin(async { true })
+    }
+
+    fn fetch_match_details(&self, match_id: i64) -> ProviderFuture<'_, Result<DetailedMatch, DotaKeeperError>>;
+}
+
+/// Pick the provider configured in settings (OpenDota for unknown values). When the user
+/// has configured both API keys, the other provider is kept as a fallback.
+pub fn from_settings(settings: &Settings) -> ProviderChain {
+    let opendota = Box::new(OpenDotaProvider {
+        api_key: settings.opendota_api_key.clone(),
+    });
+    let stratz = Box::new(StratzProvider {
+        api_key: settings.stratz_api_key.clone(),
+    });
+    let (primary, secondary): (Box<dyn MatchDataProvider>, Box<dyn MatchDataProvider>) =
+        match settings.data_provider.as_str() {
+            "stratz" => (stratz, opendota),
+            _ => (opendota, stratz),
+        };
+    let has_both_keys = settings.opendota_api_key.is_some() && settings.stratz_api_key.is_some();
+
+    ProviderChain {
+        primary,
+        fallback: if has_both_keys { Some(secondary) } else { None },
+        using_fallback: AtomicBool::new(false),
+    }
+}
+
+// ── Fallback chain ────────────────────────────────────────────────────────────
+
+/// The configured provider plus an optional fallback. The first time the primary is
+/// rate-limited or down, the chain switches to the fallback for the rest of its
+/// lifetime (one sync, backfill or background-parse run), retrying the failed call
+/// there. `id()` always names the provider that served the most recent call.
+pub struct ProviderChain {
+    primary: Box<dyn MatchDataProvider>,
+    fallback: Option<Box<dyn MatchDataProvider>>,
+    using_fallback: AtomicBool,
+}
+
+impl ProviderChain {
+    fn active(&self) -> &dyn MatchDataProvider {
+        match &self.fallback {
+            Some(fallback) if self.using_fallback.load(Ordering::Relaxed) => fallback.as_ref(),
+            _ => self.primary.as_ref(),
+        }
+    }
+
+    /// Switch to the fallback after `err` from the primary. Returns true if the failed
+    /// call should be retried on the fallback.
+    fn switch_to_fallback(&self, err: &DotaKeeperError) -> bool {
+        let Some(fallback) = &self.fallback else { return false };
+        if self.using_fallback.load(Ordering::Relaxed) || !err.is_unavailable() {
+            return false;
+        }
+        warn!("{} unavailable ({}); falling back to {}", self.primary.id(), err, fallback.id());
+        self.using_fallback.store(true, Ordering::Relaxed);
+        true
+    }
+}
+
+impl MatchDataProvider for ProviderChain {
+    fn id(&self) -> &'static str {
+        self.active().id()
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.active().capabilities()
+    }
+
+    fn not_ready_message(&self) -> &'static str {
+        self.active().not_ready_message()
+    }
+
+    fn fetch_recent_matches<'a>(
+        &'a self,
+        steam_id: &'a str,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(async move {
+            match self.active().fetch_recent_matches(steam_id, limit).await {
+                Err(e) if self.switch_to_fallback(&e) => self.active().fetch_recent_matches(steam_id, limit).await,
+                result => result,
+            }
+        })
+    }
+
+    fn fetch_matches_before<'a>(
+        &'a self,
+        steam_id: &'a str,
+        before_timestamp: i64,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(async move {
+            match self.active().fetch_matches_before(steam_id, before_timestamp, limit).await {
+                Err(e) if self.switch_to_fallback(&e) => {
+                    self.active().fetch_matches_before(steam_id, before_timestamp, limit).await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn fetch_match_history_page<'a>(
+        &'a self,
+        steam_id: &'a str,
+        offset: usize,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(async move {
+            match self.active().fetch_match_history_page(steam_id, offset, limit).await {
+                Err(e) if self.switch_to_fallback(&e) => {
+                    self.active().fetch_match_history_page(steam_id, offset, limit).await
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn request_parse(&self, match_id: i64) -> ProviderFuture<'_, Result<Option<i64>, DotaKeeperError>> {
+        Box::pin(async move {
+            match self.active().request_parse(match_id).await {
+                Err(e) if self.switch_to_fallback(&e) => self.active().request_parse(match_id).await,
+                result => result,
+            }
+        })
+    }
+
+    fn wait_for_parse(&self, job_id: i64) -> ProviderFuture<'_, bool> {
+        self.active().wait_for_parse(job_id)
+    }
+
+    fn fetch_match_details(&self, match_id: i64) -> ProviderFuture<'_, Result<DetailedMatch, DotaKeeperError>> {
+        Box::pin(async move {
+            match self.active().fetch_match_details(match_id).await {
+                Err(e) if self.switch_to_fallback(&e) => self.active().fetch_match_details(match_id).await,
+                result => result,
+            }
+        })
+    }
+}
+
+// ── OpenDota ──────────────────────────────────────────────────────────────────
+
+pub struct OpenDotaProvider {
+    api_key: Option<String>,
+}
+
+impl MatchDataProvider for OpenDotaProvider {
+    fn id(&self) -> &'static str {
+        "opendota"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            needs_parse_request: true,
+            supports_rank_tier: true,
+        }
+    }
+
+    fn not_ready_message(&self) -> &'static str {
+        "OpenDota has not finished parsing this match yet. Try again in a few minutes."
+    }
+
+    fn fetch_recent_matches<'a>(
+        &'a self,
+        steam_id: &'a str,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(opendota::fetch_recent_matches(steam_id, limit, self.api_key.as_deref()))
+    }
+
+    fn fetch_matches_before<'a>(
+        &'a self,
+        steam_id: &'a str,
+        before_timestamp: i64,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(opendota::fetch_matches_before(steam_id, before_timestamp, limit, self.api_key.as_deref()))
+    }
+
+    fn fetch_match_history_page<'a>(
+        &'a self,
+        steam_id: &'a str,
+        offset: usize,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(opendota::fetch_match_history_page(steam_id, offset, limit, self.api_key.as_deref()))
+    }
+
+    fn request_parse(&self, match_id: i64) -> ProviderFuture<'_, Result<Option<i64>, DotaKeeperError>> {
+        Box::pin(opendota::request_match_parse(match_id, self.api_key.as_deref()))
+    }
+
+    fn wait_for_parse(&self, job_id: i64) -> ProviderFuture<'_, bool> {
+        Box::pin(opendota::wait_for_parse_job(job_id))
+    }
+
+    fn fetch_match_details(&self, match_id: i64) -> ProviderFuture<'_, Result<DetailedMatch, DotaKeeperError>> {
+        Box::pin(opendota::fetch_match_details(match_id, self.api_key.as_deref()))
+    }
+}
+
+// ── Stratz ────────────────────────────────────────────────────────────────────
+
+pub struct StratzProvider {
+    api_key: Option<String>,
+}
+
+impl StratzProvider {
+    fn key(&self) -> Result<&str, DotaKeeperError> {
+        self.api_key.as_deref().ok_or(DotaKeeperError::AuthFailed { provider: "Stratz" })
+    }
+}
+
+impl MatchDataProvider for StratzProvider {
+    fn id(&self) -> &'static str {
+        "stratz"
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            needs_parse_request: false,
+            supports_rank_tier: false,
+        }
+    }
+
+    fn not_ready_message(&self) -> &'static str {
+        "Stratz hasn't processed this match yet. Try again in a few minutes."
+    }
+
+    fn fetch_recent_matches<'a>(
+        &'a self,
+        steam_id: &'a str,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(async move { stratz::fetch_recent_matches(steam_id, self.key()?, limit).await })
+    }
+
+    fn fetch_matches_before<'a>(
+        &'a self,
+        steam_id: &'a str,
+        before_timestamp: i64,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(async move { stratz::fetch_matches_before(steam_id, self.key()?, before_timestamp, limit).await })
+    }
+
+    fn fetch_match_history_page<'a>(
+        &'a self,
+        steam_id: &'a str,
+        offset: usize,
+        limit: usize,
+    ) -> ProviderFuture<'a, Result<Vec<Match>, DotaKeeperError>> {
+        Box::pin(async move { stratz::fetch_match_history_page(steam_id, self.key()?, offset, limit).await })
+    }
+
+    fn fetch_match_details(&self, match_id: i64) -> ProviderFuture<'_, Result<DetailedMatch, DotaKeeperError>> {
+        Box::pin(async move { stratz::fetch_match_details(match_id, self.key()?).await })
+    }
+}
@@ -4,6 +4,8 @@ use std::path::PathBuf;
 use std::sync::OnceLock;
 use uuid;
 
+use crate::error::DotaKeeperError;
+
 static SETTINGS_DIR: OnceLock<PathBuf> = OnceLock::new();
 
 pub fn set_settings_dir(dir: PathBuf) {
@@ -23,9 +25,27 @@ impl Default for AnalyticsConsent {
     }
 }
 
+/// A tracked Steam account. Each profile has its own database, so matches, goals,
+/// challenges and streaks never mix between accounts.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Profile {
+    pub steam_id: String,
+    /// Optional label shown in the account switcher
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// Suffix of this profile's database file; `None` for the original database,
+    /// which belongs to the first profile
+    #[serde(default)]
+    pub db_key: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Settings {
+    /// Steam ID of the active profile
     pub steam_id: Option<String>,
+    /// Every account tracked in this install, in the order they were added
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
     #[serde(default = "Settings::default_difficulty")]
     pub suggestion_difficulty: String,
     #[serde(default)]
@@ -65,12 +85,25 @@ pub struct Settings {
     /// Minimum number of games required to show the last hitting rank for a hero
     #[serde(default = "Settings::default_min_benchmark_games")]
     pub min_benchmark_games: i32,
+    /// Whether the Game State Integration listener runs (live match tracking)
+    #[serde(default)]
+    pub gsi_enabled: bool,
+    /// Local port Dota 2 posts its game state to
+    #[serde(default = "Settings::default_gsi_port")]
+    pub gsi_port: u16,
+    /// Shared secret written into the GSI config file; payloads without it are ignored
+    #[serde(default = "Settings::generate_gsi_token")]
+    pub gsi_token: String,
+    /// How many recent games a goal must be on track over before it's marked achieved
+    #[serde(default = "Settings::default_goal_completion_games")]
+    pub goal_completion_games: u32,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             steam_id: None,
+            profiles: Vec::new(),
             suggestion_difficulty: Self::default_difficulty(),
             suggestion_custom_percentage: None,
             analytics_consent: AnalyticsConsent::default(),
@@ -85,6 +118,10 @@ impl Default for Settings {
             opendota_api_key: None,
             privacy_mode: false,
             min_benchmark_games: Self::default_min_benchmark_games(),
+            gsi_enabled: false,
+            gsi_port: Self::default_gsi_port(),
+            gsi_token: Self::generate_gsi_token(),
+            goal_completion_games: Self::default_goal_completion_games(),
         }
     }
 }
@@ -110,6 +147,19 @@ impl Settings {
         5
     }
 
+    fn default_goal_completion_games() -> u32 {
+        20
+    }
+
+    fn default_gsi_port() -> u16 {
+        crate::gsi::DEFAULT_GSI_PORT
+    }
+
+    /// Generate the GSI auth token (random, persisted like the installation ID)
+    fn generate_gsi_token() -> String {
+        uuid::Uuid::new_v4().simple().to_string()
+    }
+
     /// Generate a unique installation ID (UUID v4)
     fn generate_installation_id() -> String {
         uuid::Uuid::new_v4().to_string()
@@ -142,8 +192,8 @@ impl Settings {
     }
 
     /// Load settings from the JSON file, or return default if not found.
-    /// If `installation_id` is missing from the file, generates one and saves it immediately
-    /// so the same ID is used on all subsequent loads.
+    /// If `installation_id` or `gsi_token` is missing from the file, generates one and saves
+    /// it immediately so the same value is used on all subsequent loads.
     pub fn load() -> Self {
         let Some(path) = Self::get_settings_path() else {
             return Self::default();
@@ -157,14 +207,23 @@ impl Settings {
 
         match fs::read_to_string(&path) {
             Ok(contents) => {
-                // Check whether installation_id was absent before serde fills it in with a fresh UUID.
-                let missing_installation_id = serde_json::from_str::<serde_json::Value>(&contents)
-                    .map(|v| v.get("installation_id").is_none())
+                // Check whether the generated IDs were absent before serde fills them in with fresh UUIDs.
+                let missing_generated_id = serde_json::from_str::<serde_json::Value>(&contents)
+                    .map(|v| v.get("installation_id").is_none() || v.get("gsi_token").is_none())
                     .unwrap_or(false);
 
-                let settings: Self = serde_json::from_str(&contents).unwrap_or_default();
+                let mut settings: Self = serde_json::from_str(&contents).unwrap_or_default();
+
+                // Settings from before profiles existed: the logged-in account becomes the first profile.
+                let missing_profile = match settings.steam_id.clone() {
+                    Some(steam_id) if settings.profile(&steam_id).is_none() => {
+                        settings.add_profile(&steam_id);
+                        true
+                    }
+                    _ => false,
+                };
 
-                if missing_installation_id {
+                if missing_generated_id || missing_profile {
                     let _ = settings.save();
                 }
 
@@ -175,20 +234,20 @@ impl Settings {
     }
 
     /// Save settings to the JSON file
-    pub fn save(&self) -> Result<(), String> {
+    pub fn save(&self) -> Result<(), DotaKeeperError> {
         let path = Self::get_settings_path()
-            .ok_or_else(|| "Could not determine settings directory".to_string())?;
+            .ok_or_else(|| DotaKeeperError::Other("Could not determine settings directory".to_string()))?;
 
         // Create the directory if it doesn't exist
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+                .map_err(|e| DotaKeeperError::Other(format!("Failed to create settings directory: {}", e)))?;
         }
 
         let json = serde_json::to_string_pretty(self)
-            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+            .map_err(|e| DotaKeeperError::Other(format!("Failed to serialize settings: {}", e)))?;
 
-        fs::write(&path, json).map_err(|e| format!("Failed to write settings file: {}", e))?;
+        fs::write(&path, json).map_err(|e| DotaKeeperError::Other(format!("Failed to write settings file: {}", e)))?;
 
         Ok(())
     }
@@ -198,14 +257,40 @@ impl Settings {
         self.steam_id.is_none()
     }
 
+    /// Look up the profile for a Steam ID.
+    pub fn profile(&self, steam_id: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|p| p.steam_id == steam_id)
+    }
+
+    /// The profile of the logged-in account, if any.
+    pub fn active_profile(&self) -> Option<&Profile> {
+        self.profile(self.steam_id.as_deref()?)
+    }
+
+    /// Return the profile for `steam_id`, registering it first if needed. The first
+    /// profile keeps the original database so existing data stays with it; later ones
+    /// get a database named after their Steam ID.
+    pub fn add_profile(&mut self, steam_id: &str) -> Profile {
+        if let Some(profile) = self.profile(steam_id) {
+            return profile.clone();
+        }
+        let profile = Profile {
+            steam_id: steam_id.to_string(),
+            display_name: None,
+            db_key: if self.profiles.is_empty() { None } else { Some(steam_id.to_string()) },
+        };
+        self.profiles.push(profile.clone());
+        profile
+    }
+
     /// Delete the settings file from disk (factory reset).
-    pub fn delete_settings_file() -> Result<(), String> {
+    pub fn delete_settings_file() -> Result<(), DotaKeeperError> {
         let Some(path) = Self::get_settings_path() else {
             return Ok(()); // nothing to delete
         };
         if path.exists() {
             fs::remove_file(&path)
-                .map_err(|e| format!("Failed to delete settings file: {}", e))?;
+                .map_err(|e| DotaKeeperError::Other(format!("Failed to delete settings file: {}", e)))?;
         }
         Ok(())
     }
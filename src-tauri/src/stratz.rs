@@ -1,17 +1,32 @@
 use crate::database::{Match, MatchState};
+use crate::error::DotaKeeperError;
+use crate::http::{self, ApiHost};
 use crate::items;
 use crate::opendota::{DetailedMatch, DetailedPlayer, PurchaseLogEntry};
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 use tracing::debug;
 
 const STRATZ_API_BASE: &str = "https://api.stratz.com/graphql";
 
+/// Optional replacement for `STRATZ_API_BASE`, e.g. a local fixture server.
+static API_BASE_OVERRIDE: OnceLock<String> = OnceLock::new();
+
+/// Point every Stratz request at a different server. Called at most once, before any request.
+pub fn set_api_base(url: String) {
+    let _ = API_BASE_OVERRIDE.set(url);
+}
+
+fn api_base() -> &'static str {
+    API_BASE_OVERRIDE.get().map(String::as_str).unwrap_or(STRATZ_API_BASE)
+}
+
 // ── Helpers ───────────────────────────────────────────────────────────────────
 
-fn steam_id64_to_id32(steam_id64: &str) -> Result<u64, String> {
-    let id64: u64 = steam_id64
-        .parse()
-        .map_err(|_| "No matches found for this Steam ID. Double-check the ID in Settings.".to_string())?;
+fn steam_id64_to_id32(steam_id64: &str) -> Result<u64, DotaKeeperError> {
+    let id64: u64 = steam_id64.parse().map_err(|_| {
+        DotaKeeperError::InvalidInput("No matches found for this Steam ID. Double-check the ID in Settings.".to_string())
+    })?;
 
     const STEAM_ID64_BASE: u64 = 76561197960265728;
     if id64 >= STEAM_ID64_BASE {
@@ -91,22 +106,7 @@ fn parse_position(val: &Option<serde_json::Value>) -> i32 {
     }
 }
 
-fn friendly_network_err(err: &reqwest::Error) -> String {
-    if err.is_connect() || err.is_timeout() {
-        "Couldn't reach Stratz — check your internet connection.".to_string()
-    } else {
-        "Network error while contacting Stratz. Will retry automatically.".to_string()
-    }
-}
-
-fn friendly_status_err(status: reqwest::StatusCode) -> String {
-    match status.as_u16() {
-        401 => "Stratz API key is invalid or missing. Check the API key in Settings.".to_string(),
-        429 => "Too many requests to Stratz. Will try again shortly.".to_string(),
-        500..=599 => "Stratz is unavailable right now. Your matches will sync when it comes back.".to_string(),
-        code => format!("Stratz returned an unexpected error (HTTP {}). Will retry later.", code),
-    }
-}
+const PROVIDER: &str = "Stratz";
 
 // ── GraphQL request ───────────────────────────────────────────────────────────
 
@@ -131,51 +131,38 @@ async fn graphql_post<T: for<'de> Deserialize<'de>>(
     api_key: &str,
     query: &str,
     variables: serde_json::Value,
-) -> Result<T, String> {
+) -> Result<T, DotaKeeperError> {
     let api_key = api_key.trim();
     if api_key.is_empty() {
-        return Err("Stratz API key is empty. Add your API key in Settings.".to_string());
+        return Err(DotaKeeperError::AuthFailed { provider: PROVIDER });
     }
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(STRATZ_API_BASE)
+    let request = http::client()
+        .post(api_base())
         .header("Authorization", format!("Bearer {}", api_key))
         .header("Content-Type", "application/json")
         .header("User-Agent", "DotaKeeper/1.0")
-        .json(&GraphQLRequest { query, variables })
-        .send()
-        .await
-        .map_err(|e| {
-            let msg = friendly_network_err(&e);
-            debug!("stratz graphql_post network error: {}", msg);
-            msg
-        })?;
-
-    let status = response.status();
-    debug!("stratz graphql_post response status={}", status.as_u16());
-
-    if !status.is_success() {
-        let body = response.text().await.unwrap_or_default();
-        debug!("stratz graphql_post error body: {}", &body[..body.len().min(500)]);
-        return Err(friendly_status_err(status));
-    }
+        .json(&GraphQLRequest { query, variables });
+    let response = http::send(ApiHost::Stratz, request).await.map_err(|e| {
+        debug!("stratz graphql_post failed: {}", e);
+        DotaKeeperError::from_http(PROVIDER, e)
+    })?;
 
-    let body = response.text().await.map_err(|e| format!("Failed to read Stratz response: {}", e))?;
+    let body = response.text().await.map_err(|e| DotaKeeperError::Api(format!("Failed to read Stratz response: {}", e)))?;
     debug!("stratz graphql_post response body (first 500 chars): {}", &body[..body.len().min(500)]);
 
     let resp: GraphQLResponse<T> = serde_json::from_str(&body)
-        .map_err(|e| format!("Stratz returned unexpected data: {} — body snippet: {}", e, &body[..body.len().min(200)]))?;
+        .map_err(|e| DotaKeeperError::Api(format!("Stratz returned unexpected data: {} — body snippet: {}", e, &body[..body.len().min(200)])))?;
 
     if let Some(errors) = resp.errors {
         if !errors.is_empty() {
             let msg = errors.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join("; ");
             debug!("stratz graphql_post GraphQL errors: {}", msg);
-            return Err(format!("Stratz API error: {}", msg));
+            return Err(DotaKeeperError::Api(format!("Stratz API error: {}", msg)));
         }
     }
 
-    resp.data.ok_or_else(|| "Stratz returned no data".to_string())
+    resp.data.ok_or_else(|| DotaKeeperError::Api("Stratz returned no data".to_string()))
 }
 
 // ── Player match list ─────────────────────────────────────────────────────────
@@ -265,6 +252,7 @@ impl StratzMatch {
             role: 0,
             rank_tier: None,
             patch: None, // assigned after insert using patch lookup
+            parsed_by: None,
         }
     }
 }
@@ -302,7 +290,7 @@ query GetPlayerMatches($steamAccountId: Long!, $take: Int!, $skip: Int) {
 "#;
 
 /// Fetch recent matches from Stratz API.
-pub async fn fetch_recent_matches(steam_id: &str, api_key: &str, limit: usize) -> Result<Vec<Match>, String> {
+pub async fn fetch_recent_matches(steam_id: &str, api_key: &str, limit: usize) -> Result<Vec<Match>, DotaKeeperError> {
     let account_id = steam_id64_to_id32(steam_id)?;
     debug!("stratz fetch_recent_matches account_id={} limit={}", account_id, limit);
 
@@ -328,7 +316,7 @@ pub async fn fetch_matches_before(
     api_key: &str,
     before_timestamp: i64,
     limit: usize,
-) -> Result<Vec<Match>, String> {
+) -> Result<Vec<Match>, DotaKeeperError> {
     let account_id = steam_id64_to_id32(steam_id)?;
     debug!("stratz fetch_matches_before account_id={} before={} limit={}", account_id, before_timestamp, limit);
 
@@ -378,10 +366,33 @@ pub async fn fetch_matches_before(
     Ok(all_matches)
 }
 
-fn extract_matches(data: PlayerMatchesData, account_id: u64) -> Result<Vec<Match>, String> {
+/// One page of the player's match history, newest first: `limit` matches after skipping
+/// the `offset` most recent ones.
+pub async fn fetch_match_history_page(
+    steam_id: &str,
+    api_key: &str,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<Match>, DotaKeeperError> {
+    let account_id = steam_id64_to_id32(steam_id)?;
+    debug!("stratz fetch_match_history_page account_id={} offset={} limit={}", account_id, offset, limit);
+    let data: PlayerMatchesData = graphql_post(
+        api_key,
+        PLAYER_MATCHES_QUERY,
+        serde_json::json!({
+            "steamAccountId": account_id,
+            "take": limit,
+            "skip": offset
+        }),
+    )
+    .await?;
+    extract_matches(data, account_id)
+}
+
+fn extract_matches(data: PlayerMatchesData, account_id: u64) -> Result<Vec<Match>, DotaKeeperError> {
     let matches = data
         .player
-        .ok_or("No player data returned from Stratz. Check your Steam ID in Settings.")?
+        .ok_or_else(|| DotaKeeperError::NotFound("No player data returned from Stratz. Check your Steam ID in Settings.".to_string()))?
         .matches
         .unwrap_or_default();
 
@@ -421,7 +432,11 @@ struct StratzDetailedMatch {
 struct StratzDetailedPlayer {
     steam_account_id: Option<u64>,
     player_slot: Option<i32>,
+    hero_id: Option<i32>,
     position: Option<serde_json::Value>,
+    kills: Option<i32>,
+    deaths: Option<i32>,
+    assists: Option<i32>,
     experience_per_minute: Option<i32>,
     gold_per_minute: Option<i32>,
     num_last_hits: Option<i32>,
@@ -440,6 +455,8 @@ struct StratzPlayerStats {
     networth_per_minute: Option<Vec<i32>>,
     xp_per_minute: Option<Vec<i32>>,
     item_purchases: Option<Vec<StratzItemPurchase>>,
+    kill_events: Option<Vec<StratzTimedEvent>>,
+    death_events: Option<Vec<StratzTimedEvent>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -449,6 +466,12 @@ struct StratzItemPurchase {
     item_id: i32,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct StratzTimedEvent {
+    time: i32,
+}
+
 const MATCH_DETAILS_QUERY: &str = r#"
 query GetMatchDetails($matchId: Long!) {
   match(id: $matchId) {
@@ -456,7 +479,11 @@ query GetMatchDetails($matchId: Long!) {
     players {
       steamAccountId
       playerSlot
+      heroId
       position
+      kills
+      deaths
+      assists
       experiencePerMinute
       goldPerMinute
       numLastHits
@@ -473,6 +500,12 @@ query GetMatchDetails($matchId: Long!) {
           time
           itemId
         }
+        killEvents {
+          time
+        }
+        deathEvents {
+          time
+        }
       }
     }
   }
@@ -481,7 +514,7 @@ query GetMatchDetails($matchId: Long!) {
 
 /// Fetch detailed match data from Stratz.
 /// Unlike OpenDota, no parse request is needed — Stratz data is available immediately.
-pub async fn fetch_match_details(match_id: i64, api_key: &str) -> Result<DetailedMatch, String> {
+pub async fn fetch_match_details(match_id: i64, api_key: &str) -> Result<DetailedMatch, DotaKeeperError> {
     debug!("stratz fetch_match_details match_id={}", match_id);
 
     let data: MatchDetailsData = graphql_post(
@@ -493,7 +526,7 @@ pub async fn fetch_match_details(match_id: i64, api_key: &str) -> Result<Detaile
 
     let stratz_match = data
         .match_data
-        .ok_or_else(|| format!("No data returned for match {}. It may not be tracked by Stratz yet.", match_id))?;
+        .ok_or_else(|| DotaKeeperError::NotFound(format!("No data returned for match {}. It may not be tracked by Stratz yet.", match_id)))?;
 
     let players: Vec<DetailedPlayer> = stratz_match
         .players
@@ -517,6 +550,7 @@ pub async fn fetch_match_details(match_id: i64, api_key: &str) -> Result<Detaile
             DetailedPlayer {
                 account_id: p.steam_account_id.map(|id| id as u32),
                 player_slot: p.player_slot.unwrap_or(0),
+                hero_id: p.hero_id,
                 lane_role: Some(lane_role),
                 // Stratz returns per-minute deltas; convert to cumulative totals
                 // to match the OpenDota lh_t / dn_t format the rest of the app expects.
@@ -548,6 +582,16 @@ pub async fn fetch_match_details(match_id: i64, api_key: &str) -> Result<Detaile
                     out
                 }),
                 purchase_log,
+                kills_log: None,
+                kill_times: p.stats.as_ref().and_then(|s| s.kill_events.as_ref()).map(|events| {
+                    events.iter().map(|e| e.time).collect()
+                }),
+                death_times: p.stats.as_ref().and_then(|s| s.death_events.as_ref()).map(|events| {
+                    events.iter().map(|e| e.time).collect()
+                }),
+                kills: p.kills,
+                deaths: p.deaths,
+                assists: p.assists,
                 xp_per_min: p.experience_per_minute,
                 gold_per_min: p.gold_per_minute,
                 last_hits: p.num_last_hits,
@@ -0,0 +1,28 @@
+/**
+ * Backend commands reject with `{ code, message, retryable, retry_after_secs }`
+ * (see `DotaKeeperError` in src-tauri/src/error.rs). Branch on `code`, show `message`.
+ */
+
+/**
+ * @typedef {{ code: string, message: string, retryable: boolean, retry_after_secs: number | null }} AppError
+ */
+
+/**
+ * Normalise anything thrown by `invoke` or a plugin into an AppError.
+ * @param {unknown} e
+ * @returns {AppError}
+ */
+export function toAppError(e) {
+  if (e && typeof e === "object" && "code" in e && "message" in e) {
+    return /** @type {AppError} */ (e);
+  }
+  return { code: "other", message: String(e), retryable: false, retry_after_secs: null };
+}
+
+/**
+ * User-facing message for a thrown error.
+ * @param {unknown} e
+ */
+export function errorMessage(e) {
+  return toAppError(e).message;
+}
@@ -6,6 +6,7 @@
  * reassigned $state variables from a module — only mutating class properties.
  */
 import { invoke } from "@tauri-apps/api/core";
+import { toAppError } from "$lib/errors.js";
 
 class ParseQueueStore {
   /** @type {number[]} */
@@ -51,14 +52,12 @@ export function enqueueParse(matchId) {
   if (!_isDraining && _steamId) _drain();
 }
 
-/** @param {string} msg */
-export function isPendingError(msg) {
-  return (
-    msg.includes('not yet')   ||
-    msg.includes('next sync') ||
-    msg.includes('will be')   ||
-    msg.includes('per-minute')
-  );
+/**
+ * True when the parse should simply be retried later (not parsed yet, provider busy).
+ * @param {import("$lib/errors.js").AppError} err
+ */
+export function isPendingError(err) {
+  return err.retryable;
 }
 
 // ── Internal ──────────────────────────────────────────────────────────────────
@@ -88,14 +87,14 @@ async function _parseMatch(matchId) {
     await invoke("parse_match", { matchId, steamId: _steamId });
     if (_onComplete) await _onComplete();
   } catch (e) {
-    const errMsg = String(e);
-    console.error(`[parse-queue] parse_match failed for ${matchId}:`, errMsg);
+    const err = toAppError(e);
+    console.error(`[parse-queue] parse_match failed for ${matchId}:`, err.code, err.message);
     const m = new Map(pqs.errors);
-    m.set(matchId, errMsg);
+    m.set(matchId, err);
     pqs.errors = m;
-    if (isPendingError(errMsg)) {
+    if (isPendingError(err)) {
       const rc = new Map(pqs.countdowns);
-      rc.set(matchId, 60);
+      rc.set(matchId, Math.max(60, err.retry_after_secs ?? 0));
       pqs.countdowns = rc;
     }
   } finally {